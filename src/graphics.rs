@@ -1,30 +1,147 @@
 //! Graphics abstractions and tools.
 
+use std::io::{Error, ErrorKind};
+
 use image::{ImageBuffer, ImageFormat, ImageResult, Rgba, RgbaImage};
 
 pub use patterns::*;
+pub use textures::*;
+pub use tone_mapping::*;
 
-use crate::maths::Color;
+use crate::maths::{Color, rgb, StoragePrecision};
 
 mod patterns;
+mod textures;
+mod tone_mapping;
+
+/// A destination for rendered pixels, such as a canvas, a terminal or a window.
+pub trait OutputSink {
+  /// Receives the final color of the pixel at the given (x, y) position.
+  fn write_pixel(&mut self, x: usize, y: usize, color: Color);
+
+  /// Called once all pixels have been written.
+  fn flush(&mut self) {}
+}
+
+/// The bytes that open a raw canvas file.
+const RAW_MAGIC: &[u8; 4] = b"RAWC";
 
 /// A canvas is a 2D array of pixels that can be drawn to.
-pub struct Canvas {
+///
+/// Pixels are stored at the precision `P`; a `Canvas<f32>` takes half the memory of the default.
+/// Colors are always read and written at full precision.
+pub struct Canvas<P: StoragePrecision = f64> {
   width: u32,
   height: u32,
-  pixels: Vec<Color>,
+  pixels: Vec<P::Color>,
 }
 
 impl Canvas {
   /// Creates a new canvas with the given width and height with a default black color.
   pub fn new(width: u32, height: u32) -> Self {
+    Self::with_precision(width, height)
+  }
+
+  /// Creates a new canvas by evaluating the given function at each pixel.
+  pub fn from_fn(width: u32, height: u32, function: impl Fn(u32, u32) -> Color) -> Self {
+    let mut canvas = Self::new(width, height);
+
+    for y in 0..height {
+      for x in 0..width {
+        canvas.pixels[(x + y * width) as usize] = function(x, y);
+      }
+    }
+
+    canvas
+  }
+
+  /// Creates a new canvas by evaluating the given function at each pixel, splitting rows across threads.
+  pub fn par_from_fn(width: u32, height: u32, function: impl Fn(u32, u32) -> Color + Sync) -> Self {
+    let mut canvas = Self::new(width, height);
+
+    if canvas.pixels.is_empty() {
+      return canvas;
+    }
+
+    let threads = std::thread::available_parallelism().map_or(1, |count| count.get());
+    let rows_per_chunk = (height as usize).div_ceil(threads).max(1);
+    let function = &function;
+
+    std::thread::scope(|scope| {
+      for (chunk_index, chunk) in canvas.pixels.chunks_mut(rows_per_chunk * width as usize).enumerate() {
+        scope.spawn(move || {
+          let first_row = chunk_index * rows_per_chunk;
+
+          for (index, pixel) in chunk.iter_mut().enumerate() {
+            let x = index % width as usize;
+            let y = first_row + index / width as usize;
+
+            *pixel = function(x as u32, y as u32);
+          }
+        });
+      }
+    });
+
+    canvas
+  }
+
+  /// Accesses the pixels as a slice of colors.
+  pub fn as_slice(&self) -> &[Color] {
+    &self.pixels
+  }
+
+  /// Loads a canvas written by `save_raw` from the given path.
+  pub fn load_raw(path: &str) -> std::io::Result<Self> {
+    let bytes = std::fs::read(path)?;
+    let invalid = |message: &str| Error::new(ErrorKind::InvalidData, message.to_string());
+
+    let (header, body) = bytes.split_at_checked(12).ok_or_else(|| invalid("raw canvas header is truncated"))?;
+
+    if &header[0..4] != RAW_MAGIC {
+      return Err(invalid("not a raw canvas file"));
+    }
+
+    let width = u32::from_le_bytes(header[4..8].try_into().unwrap());
+    let height = u32::from_le_bytes(header[8..12].try_into().unwrap());
+
+    if body.len() as u64 != width as u64 * height as u64 * 24 {
+      return Err(invalid("raw canvas pixels don't match its size"));
+    }
+
+    let channel = |bytes: &[u8]| f64::from_le_bytes(bytes.try_into().unwrap());
+    let pixels = body
+      .chunks_exact(24)
+      .map(|pixel| rgb(channel(&pixel[0..8]), channel(&pixel[8..16]), channel(&pixel[16..24])))
+      .collect();
+
+    Ok(Self { width, height, pixels })
+  }
+}
+
+impl<P: StoragePrecision> Canvas<P> {
+  /// Creates a new black canvas storing its pixels at the precision `P`.
+  pub fn with_precision(width: u32, height: u32) -> Self {
     Self {
       width,
       height,
-      pixels: vec![Color::BLACK; (width * height) as usize],
+      pixels: vec![P::pack(Color::BLACK); (width * height) as usize],
     }
   }
 
+  /// Copies the canvas into a new canvas storing its pixels at another precision.
+  pub fn to_precision<Q: StoragePrecision>(&self) -> Canvas<Q> {
+    Canvas {
+      width: self.width,
+      height: self.height,
+      pixels: self.colors().map(Q::pack).collect(),
+    }
+  }
+
+  /// Iterates over the pixels as full precision colors.
+  fn colors(&self) -> impl Iterator<Item=Color> + '_ {
+    self.pixels.iter().map(|&pixel| P::unpack(pixel))
+  }
+
   /// The width of the canvas in pixels.
   pub fn width(&self) -> usize {
     self.width as usize
@@ -37,22 +154,279 @@ impl Canvas {
 
   /// Retrieves the pixels at the given (x, y) position in the canvas.
   pub fn get_pixel(&mut self, x: usize, y: usize) -> Color {
-    self.pixels[x + y * self.width as usize]
+    P::unpack(self.pixels[x + y * self.width as usize])
   }
 
   /// Sets the pixels at the given (x, y) position in the canvas.
   pub fn set_pixel(&mut self, x: usize, y: usize, color: Color) {
-    self.pixels[x + y * self.width as usize] = color;
+    self.pixels[x + y * self.width as usize] = P::pack(color);
+  }
+
+  /// Draws a straight line between the given (fractional) pixel positions.
+  ///
+  /// Parts of the line outside the canvas are skipped.
+  pub fn draw_line(&mut self, (x0, y0): (f64, f64), (x1, y1): (f64, f64), color: Color) {
+    let steps = (x1 - x0).abs().max((y1 - y0).abs()).ceil().max(1.) as usize;
+
+    for step in 0..=steps {
+      let t = step as f64 / steps as f64;
+      let x = (x0 + (x1 - x0) * t).floor();
+      let y = (y0 + (y1 - y0) * t).floor();
+
+      if x >= 0. && y >= 0. && x < self.width as f64 && y < self.height as f64 {
+        self.set_pixel(x as usize, y as usize, color);
+      }
+    }
   }
 
   /// Fills the canvas with the given color.
   pub fn fill(&mut self, color: Color) {
-    self.pixels.fill(color);
+    self.pixels.fill(P::pack(color));
   }
 
-  /// Accesses the pixels as a slice of colors.
-  pub fn as_slice(&self) -> &[Color] {
-    &self.pixels
+  /// Resizes the canvas to the given dimensions using nearest-neighbour sampling.
+  pub fn resize_nearest(&self, width: u32, height: u32) -> Self {
+    let mut canvas = Self::with_precision(width, height);
+
+    for y in 0..height as usize {
+      for x in 0..width as usize {
+        let source_x = x * self.width as usize / width as usize;
+        let source_y = y * self.height as usize / height as usize;
+
+        canvas.pixels[x + y * width as usize] = self.pixels[source_x + source_y * self.width as usize];
+      }
+    }
+
+    canvas
+  }
+
+  /// Copies the given rectangle of the canvas into a new canvas.
+  pub fn crop(&self, x: usize, y: usize, width: u32, height: u32) -> Self {
+    assert!(
+      x + width as usize <= self.width as usize && y + height as usize <= self.height as usize,
+      "Cannot crop a {}x{} region at ({}, {}) from a {}x{} canvas",
+      width, height, x, y, self.width, self.height,
+    );
+
+    let mut canvas = Self::with_precision(width, height);
+
+    for row in 0..height as usize {
+      let start = x + (y + row) * self.width as usize;
+      let end = start + width as usize;
+
+      canvas.pixels[row * width as usize..(row + 1) * width as usize].copy_from_slice(&self.pixels[start..end]);
+    }
+
+    canvas
+  }
+
+  /// Flips the canvas upside down, swapping the top and bottom rows.
+  pub fn flip_vertical(&mut self) {
+    let width = self.width as usize;
+    let height = self.height as usize;
+
+    for y in 0..height / 2 {
+      let (top, bottom) = self.pixels.split_at_mut((height - 1 - y) * width);
+
+      top[y * width..(y + 1) * width].swap_with_slice(&mut bottom[..width]);
+    }
+  }
+
+  /// Mirrors the canvas, swapping the left and right columns.
+  pub fn flip_horizontal(&mut self) {
+    for row in self.pixels.chunks_exact_mut(self.width.max(1) as usize) {
+      row.reverse();
+    }
+  }
+
+  /// Rotates the canvas a quarter turn clockwise, swapping its width and height.
+  pub fn rotate_90(&mut self) {
+    let width = self.width as usize;
+    let height = self.height as usize;
+    let mut pixels = Vec::with_capacity(self.pixels.len());
+
+    // each row of the result is a column of the original, read from bottom to top
+    for x in 0..width {
+      for y in (0..height).rev() {
+        pixels.push(self.pixels[x + y * width]);
+      }
+    }
+
+    self.pixels = pixels;
+    std::mem::swap(&mut self.width, &mut self.height);
+  }
+
+  /// Computes a stable hash of the canvas, for golden-image regression tests.
+  ///
+  /// Pixels are quantized to 8-bit first so that tiny floating point differences don't change
+  /// the result. The hash (64-bit FNV-1a) is stable across platforms and compiler versions.
+  pub fn fingerprint(&self) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let dimensions = [self.width.to_le_bytes(), self.height.to_le_bytes()];
+    let pixels = self.colors().flat_map(|pixel| pixel.to_rgba8());
+
+    dimensions
+      .into_iter()
+      .flatten()
+      .chain(pixels)
+      .fold(OFFSET_BASIS, |hash, byte| (hash ^ byte as u64).wrapping_mul(PRIME))
+  }
+
+  /// Computes the absolute per-channel difference between this canvas and another.
+  ///
+  /// Identical canvases give an all-black image; bright pixels show where they differ.
+  pub fn diff(&self, other: &Self) -> Self {
+    self.check_same_size(other);
+
+    let pixels = self.colors()
+      .zip(other.colors())
+      .map(|(a, b)| P::pack((a - b).map(f64::abs)))
+      .collect();
+
+    Self { width: self.width, height: self.height, pixels }
+  }
+
+  /// Computes the largest difference in any channel of any pixel between the two canvases.
+  pub fn max_diff(&self, other: &Self) -> f64 {
+    self.diff(other)
+      .colors()
+      .map(|pixel| pixel.r.max(pixel.g).max(pixel.b))
+      .fold(0., f64::max)
+  }
+
+  /// Computes the mean squared error across every channel of the two canvases.
+  pub fn mean_squared_error(&self, other: &Self) -> f64 {
+    let total: f64 = self.diff(other)
+      .colors()
+      .map(|pixel| pixel.r * pixel.r + pixel.g * pixel.g + pixel.b * pixel.b)
+      .sum();
+
+    total / (self.pixels.len() * 3).max(1) as f64
+  }
+
+  /// Panics if the other canvas has different dimensions; they can't be compared.
+  fn check_same_size(&self, other: &Self) {
+    assert!(
+      self.width == other.width && self.height == other.height,
+      "Cannot compare a {}x{} canvas with a {}x{} canvas",
+      self.width, self.height, other.width, other.height,
+    );
+  }
+
+  /// Computes the log-average luminance of the canvas; the 'key' used for exposure.
+  pub fn average_luminance(&self) -> f64 {
+    // offset to avoid the log of black pixels
+    const DELTA: f64 = 0.0001;
+
+    if self.pixels.is_empty() {
+      return 0.;
+    }
+
+    let (total, _) = self.reduce_luminance(|luminance| (DELTA + luminance).ln());
+
+    (total / self.pixels.len() as f64).exp() - DELTA
+  }
+
+  /// Counts the pixels with NaN or infinite channels, which usually indicate a lighting bug.
+  ///
+  /// Such pixels are sanitized on conversion to images, but are worth reporting.
+  pub fn invalid_pixel_count(&self) -> usize {
+    self.colors().filter(|pixel| !pixel.is_finite()).count()
+  }
+
+  /// Computes the peak luminance of the canvas.
+  pub fn max_luminance(&self) -> f64 {
+    let (_, max) = self.reduce_luminance(|_| 0.);
+
+    max
+  }
+
+  /// Counts the pixels falling into each of the given number of equal luminance bins over [0, 1].
+  ///
+  /// Negative luminance falls into the first bin, and anything brighter than 1 into the last.
+  pub fn luminance_histogram(&self, bins: usize) -> Vec<usize> {
+    let mut histogram = vec![0; bins];
+
+    if bins == 0 {
+      return histogram;
+    }
+
+    for pixel in self.colors() {
+      let bin = (pixel.luminance() * bins as f64).clamp(0., (bins - 1) as f64) as usize;
+
+      histogram[bin] += 1;
+    }
+
+    histogram
+  }
+
+  /// Finds the luminance below which the given percentage (0 to 100) of pixels fall.
+  ///
+  /// Interpolates linearly between the nearest two pixels when the percentile falls between them.
+  pub fn percentile(&self, percentile: f64) -> f64 {
+    if self.pixels.is_empty() {
+      return 0.;
+    }
+
+    let mut luminances: Vec<f64> = self.colors().map(|pixel| pixel.luminance()).collect();
+
+    luminances.sort_by(f64::total_cmp);
+
+    let rank = percentile.clamp(0., 100.) / 100. * (luminances.len() - 1) as f64;
+    let lower = luminances[rank.floor() as usize];
+    let upper = luminances[rank.ceil() as usize];
+
+    lower + (upper - lower) * rank.fract()
+  }
+
+  /// Applies the given tone-mapping curve to every pixel, e.g. `canvas.tone_map(aces_filmic)`.
+  pub fn tone_map(&mut self, curve: impl Fn(Color) -> Color) {
+    for pixel in &mut self.pixels {
+      *pixel = P::pack(curve(P::unpack(*pixel)));
+    }
+  }
+
+  /// Scales the canvas so that its average luminance maps to middle gray.
+  pub fn auto_expose(&mut self) {
+    const MIDDLE_GRAY: f64 = 0.18;
+
+    let average = self.average_luminance();
+
+    if average > 0. {
+      let exposure = MIDDLE_GRAY / average;
+
+      for pixel in &mut self.pixels {
+        *pixel = P::pack(P::unpack(*pixel) * exposure);
+      }
+    }
+  }
+
+  /// Sums the given function of each pixel's luminance and finds the peak luminance, in parallel.
+  fn reduce_luminance(&self, map: impl Fn(f64) -> f64 + Sync) -> (f64, f64) {
+    let threads = std::thread::available_parallelism().map_or(1, |count| count.get());
+    let chunk_size = self.pixels.len().div_ceil(threads).max(4096);
+
+    let reduce = |pixels: &[P::Color]| {
+      pixels.iter().fold((0., 0f64), |(total, max), &pixel| {
+        let luminance = P::unpack(pixel).luminance();
+
+        (total + map(luminance), max.max(luminance))
+      })
+    };
+
+    std::thread::scope(|scope| {
+      let workers: Vec<_> = self.pixels
+        .chunks(chunk_size)
+        .map(|chunk| scope.spawn(|| reduce(chunk)))
+        .collect();
+
+      workers
+        .into_iter()
+        .map(|worker| worker.join().expect("Luminance worker panicked"))
+        .fold((0., 0.), |(total, max), (chunk_total, chunk_max)| (total + chunk_total, f64::max(max, chunk_max)))
+    })
   }
 
   /// Converts the canvas to an image of RGBA pixels.
@@ -60,14 +434,22 @@ impl Canvas {
     let mut image = RgbaImage::new(self.width, self.height);
 
     for (x, y, pixel) in image.enumerate_pixels_mut() {
-      let color = self.pixels[x as usize + y as usize * self.width as usize];
+      let color = P::unpack(self.pixels[x as usize + y as usize * self.width as usize]);
+
+      *pixel = Rgba(color.to_rgba8());
+    }
+
+    image
+  }
+
+  /// Converts the canvas to an image of 16-bit RGBA pixels.
+  pub fn to_image_u16(&self) -> ImageBuffer<Rgba<u16>, Vec<u16>> {
+    let mut image = ImageBuffer::new(self.width, self.height);
+
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+      let color = P::unpack(self.pixels[x as usize + y as usize * self.width as usize]);
 
-      *pixel = Rgba([
-        (color.r * 255.0) as u8,
-        (color.g * 255.0) as u8,
-        (color.b * 255.0) as u8,
-        255,
-      ]);
+      *pixel = Rgba(color.to_rgba16());
     }
 
     image
@@ -79,10 +461,45 @@ impl Canvas {
 
     image.save_with_format(path, ImageFormat::Png)
   }
+
+  /// Saves the image to the given path as a .png file with 16 bits per channel.
+  pub fn save_to_png16(&self, path: &str) -> ImageResult<()> {
+    let image = self.to_image_u16();
+
+    image.save_with_format(path, ImageFormat::Png)
+  }
+
+  /// Saves the canvas losslessly to the given path, keeping the full range of every channel.
+  ///
+  /// The file holds a magic number, the width and height as little-endian u32s, then the
+  /// r, g and b of each pixel as little-endian f64s, row by row.
+  pub fn save_raw(&self, path: &str) -> std::io::Result<()> {
+    let mut bytes = Vec::with_capacity(12 + self.pixels.len() * 24);
+
+    bytes.extend_from_slice(RAW_MAGIC);
+    bytes.extend_from_slice(&self.width.to_le_bytes());
+    bytes.extend_from_slice(&self.height.to_le_bytes());
+
+    for pixel in self.colors() {
+      for channel in [pixel.r, pixel.g, pixel.b] {
+        bytes.extend_from_slice(&channel.to_le_bytes());
+      }
+    }
+
+    std::fs::write(path, bytes)
+  }
+}
+
+impl<P: StoragePrecision> OutputSink for Canvas<P> {
+  fn write_pixel(&mut self, x: usize, y: usize, color: Color) {
+    self.set_pixel(x, y, color);
+  }
 }
 
 #[cfg(test)]
 mod tests {
+  use crate::maths::rgb;
+
   use super::*;
 
   #[test]
@@ -106,6 +523,32 @@ mod tests {
     assert_eq!(canvas.get_pixel(2, 3), Color::RED);
   }
 
+  #[test]
+  fn canvas_should_draw_clipped_lines() {
+    let mut canvas = Canvas::new(10, 10);
+
+    canvas.draw_line((0.5, 0.5), (9.5, 9.5), Color::RED);
+    canvas.draw_line((5.5, 5.5), (20.5, 5.5), Color::GREEN);
+
+    for i in 0..10 {
+      assert_eq!(canvas.get_pixel(i, i), if i == 5 { Color::GREEN } else { Color::RED });
+    }
+
+    assert_eq!(canvas.get_pixel(9, 5), Color::GREEN);
+    assert_eq!(canvas.get_pixel(4, 5), Color::BLACK);
+  }
+
+  #[test]
+  fn par_from_fn_should_match_serial_from_fn() {
+    let gradient = |x: u32, y: u32| rgb(x as f64 / 37., y as f64 / 23., (x ^ y) as f64 / 64.);
+
+    let mut serial = Canvas::from_fn(37, 23, gradient);
+    let parallel = Canvas::par_from_fn(37, 23, gradient);
+
+    assert_eq!(serial.get_pixel(5, 7), gradient(5, 7));
+    assert_eq!(serial.as_slice(), parallel.as_slice());
+  }
+
   #[test]
   fn canvas_should_convert_to_image() {
     let canvas = Canvas::new(10, 20);
@@ -116,4 +559,335 @@ mod tests {
 
     assert_eq!(image.pixels().len(), 10 * 20);
   }
+
+  #[test]
+  fn canvas_should_convert_non_finite_pixels_to_valid_bytes() {
+    let mut canvas = Canvas::new(2, 1);
+
+    canvas.set_pixel(0, 0, rgb(f64::NAN, 0.5, f64::NAN));
+    canvas.set_pixel(1, 0, rgb(f64::INFINITY, f64::NEG_INFINITY, 1.));
+
+    let image = canvas.to_image();
+
+    assert_eq!(canvas.invalid_pixel_count(), 2);
+    assert_eq!(image.get_pixel(0, 0).0, [0, 127, 0, 255]);
+    assert_eq!(image.get_pixel(1, 0).0, [255, 0, 255, 255]);
+  }
+
+  #[test]
+  fn canvas_should_resize_with_nearest_neighbour() {
+    let mut canvas = Canvas::new(2, 1);
+
+    canvas.set_pixel(0, 0, Color::RED);
+    canvas.set_pixel(1, 0, Color::BLUE);
+
+    let mut resized = canvas.resize_nearest(4, 2);
+
+    assert_eq!(resized.width(), 4);
+    assert_eq!(resized.height(), 2);
+
+    for y in 0..2 {
+      assert_eq!(resized.get_pixel(0, y), Color::RED);
+      assert_eq!(resized.get_pixel(1, y), Color::RED);
+      assert_eq!(resized.get_pixel(2, y), Color::BLUE);
+      assert_eq!(resized.get_pixel(3, y), Color::BLUE);
+    }
+  }
+
+  /// Creates a 3x2 canvas with a different color in each pixel.
+  fn create_asymmetric_canvas() -> Canvas {
+    let mut canvas = Canvas::new(3, 2);
+
+    for y in 0..2 {
+      for x in 0..3 {
+        canvas.set_pixel(x, y, rgb(x as f64, y as f64, 0.));
+      }
+    }
+
+    canvas
+  }
+
+  #[test]
+  fn canvas_flip_vertical_moves_top_row_to_bottom() {
+    let mut canvas = create_asymmetric_canvas();
+
+    canvas.flip_vertical();
+
+    for x in 0..3 {
+      assert_eq!(canvas.get_pixel(x, 1), rgb(x as f64, 0., 0.));
+      assert_eq!(canvas.get_pixel(x, 0), rgb(x as f64, 1., 0.));
+    }
+  }
+
+  #[test]
+  fn canvas_flip_horizontal_moves_left_column_to_right() {
+    let mut canvas = create_asymmetric_canvas();
+
+    canvas.flip_horizontal();
+
+    for y in 0..2 {
+      assert_eq!(canvas.get_pixel(2, y), rgb(0., y as f64, 0.));
+      assert_eq!(canvas.get_pixel(0, y), rgb(2., y as f64, 0.));
+    }
+  }
+
+  #[test]
+  fn canvas_flipped_twice_is_unchanged() {
+    let original = create_asymmetric_canvas();
+    let mut canvas = create_asymmetric_canvas();
+
+    canvas.flip_vertical();
+    canvas.flip_vertical();
+
+    assert_eq!(canvas.as_slice(), original.as_slice());
+
+    canvas.flip_horizontal();
+    canvas.flip_horizontal();
+
+    assert_eq!(canvas.as_slice(), original.as_slice());
+  }
+
+  #[test]
+  fn canvas_rotate_90_turns_clockwise() {
+    let original = create_asymmetric_canvas();
+    let mut canvas = create_asymmetric_canvas();
+
+    canvas.rotate_90();
+
+    assert_eq!(canvas.width(), 2);
+    assert_eq!(canvas.height(), 3);
+
+    // the bottom-left corner moves to the top-left
+    assert_eq!(canvas.get_pixel(0, 0), rgb(0., 1., 0.));
+    assert_eq!(canvas.get_pixel(1, 0), rgb(0., 0., 0.));
+    assert_eq!(canvas.get_pixel(1, 2), rgb(2., 0., 0.));
+
+    for _ in 0..3 {
+      canvas.rotate_90();
+    }
+
+    assert_eq!(canvas.width(), 3);
+    assert_eq!(canvas.as_slice(), original.as_slice());
+  }
+
+  #[test]
+  fn canvas_crop_copies_a_rectangle() {
+    let mut canvas = create_asymmetric_canvas().crop(1, 1, 2, 1);
+
+    assert_eq!(canvas.width(), 2);
+    assert_eq!(canvas.height(), 1);
+    assert_eq!(canvas.get_pixel(0, 0), rgb(1., 1., 0.));
+    assert_eq!(canvas.get_pixel(1, 0), rgb(2., 1., 0.));
+  }
+
+  #[test]
+  #[should_panic]
+  fn canvas_crop_rejects_regions_outside_the_canvas() {
+    create_asymmetric_canvas().crop(2, 0, 2, 1);
+  }
+
+  #[test]
+  fn canvas_diff_with_itself_is_black() {
+    let mut canvas = Canvas::new(4, 4);
+    canvas.fill(rgb(0.2, 0.4, 0.6));
+    canvas.set_pixel(1, 2, Color::RED);
+
+    let diff = canvas.diff(&canvas);
+
+    assert!(diff.as_slice().iter().all(|&pixel| pixel == Color::BLACK));
+    assert_eq!(canvas.max_diff(&canvas), 0.);
+    assert_eq!(canvas.mean_squared_error(&canvas), 0.);
+  }
+
+  #[test]
+  fn canvas_diff_highlights_differing_pixels() {
+    let a = Canvas::new(2, 2);
+    let mut b = Canvas::new(2, 2);
+    b.set_pixel(1, 0, rgb(0.5, 0., 1.));
+
+    let mut diff = a.diff(&b);
+
+    assert_eq!(diff.get_pixel(0, 0), Color::BLACK);
+    assert_eq!(diff.get_pixel(1, 0), rgb(0.5, 0., 1.));
+    assert_eq!(a.max_diff(&b), 1.);
+    assert_eq!(a.mean_squared_error(&b), 1.25 / 12.);
+  }
+
+  #[test]
+  #[should_panic]
+  fn canvas_diff_requires_matching_dimensions() {
+    Canvas::new(2, 2).diff(&Canvas::new(2, 3));
+  }
+
+  #[test]
+  fn canvas_fingerprint_ignores_sub_quantization_jitter() {
+    let mut a = Canvas::new(4, 4);
+    let mut b = Canvas::new(4, 4);
+
+    a.fill(rgb(0.5, 0.25, 0.125));
+    b.fill(rgb(0.5 + 1e-9, 0.25 - 1e-9, 0.125));
+
+    assert_eq!(a.fingerprint(), b.fingerprint());
+
+    b.set_pixel(3, 3, Color::WHITE);
+
+    assert_ne!(a.fingerprint(), b.fingerprint());
+  }
+
+  #[test]
+  fn canvas_fingerprint_depends_on_dimensions() {
+    assert_ne!(Canvas::new(2, 8).fingerprint(), Canvas::new(4, 4).fingerprint());
+  }
+
+  #[test]
+  fn canvas_should_report_luminance_of_uniform_gray() {
+    let mut canvas = Canvas::new(100, 100);
+
+    canvas.fill(rgb(0.5, 0.5, 0.5));
+
+    assert!((canvas.average_luminance() - 0.5).abs() < 1e-9);
+    assert!((canvas.max_luminance() - 0.5).abs() < 1e-9);
+  }
+
+  #[test]
+  fn luminance_histogram_of_uniform_gray_fills_a_single_bin() {
+    let mut canvas = Canvas::new(10, 10);
+
+    canvas.fill(rgb(0.5, 0.5, 0.5));
+
+    let histogram = canvas.luminance_histogram(8);
+
+    assert_eq!(histogram.iter().filter(|&&count| count > 0).count(), 1);
+    assert_eq!(histogram[4], 100);
+  }
+
+  #[test]
+  fn percentile_interpolates_between_pixel_luminances() {
+    let mut canvas = Canvas::new(4, 1);
+
+    canvas.fill(rgb(0.2, 0.2, 0.2));
+    canvas.set_pixel(2, 0, rgb(0.8, 0.8, 0.8));
+    canvas.set_pixel(3, 0, rgb(0.8, 0.8, 0.8));
+
+    assert!((canvas.percentile(50.) - 0.5).abs() < 1e-9);
+    assert!((canvas.percentile(0.) - 0.2).abs() < 1e-9);
+    assert!((canvas.percentile(100.) - 0.8).abs() < 1e-9);
+  }
+
+  #[test]
+  fn canvas_should_report_peak_luminance() {
+    let mut canvas = Canvas::new(200, 100);
+
+    canvas.fill(rgb(0.1, 0.1, 0.1));
+    canvas.set_pixel(150, 75, rgb(4., 4., 4.));
+
+    assert!((canvas.max_luminance() - 4.).abs() < 1e-9);
+    assert!(canvas.average_luminance() < 0.2);
+  }
+
+  #[test]
+  fn tone_map_with_identity_leaves_the_canvas_unchanged() {
+    let mut canvas = Canvas::from_fn(8, 8, |x, y| rgb(x as f64, y as f64 / 4., 0.5));
+    let before = canvas.fingerprint();
+
+    canvas.tone_map(|color| color);
+
+    assert_eq!(canvas.fingerprint(), before);
+    assert_eq!(canvas.get_pixel(7, 4), rgb(7., 1., 0.5));
+  }
+
+  #[test]
+  fn tone_map_applies_a_curve_to_every_pixel() {
+    let mut canvas = Canvas::new(4, 4);
+
+    canvas.fill(rgb(16., 16., 16.));
+    canvas.tone_map(aces_filmic);
+
+    assert!(canvas.as_slice().iter().all(|pixel| pixel.r > 0.99 && pixel.r <= 1.));
+  }
+
+  #[test]
+  fn canvas_should_auto_expose_underexposed_images() {
+    let mut canvas = Canvas::new(10, 10);
+
+    canvas.fill(rgb(0.01, 0.02, 0.03));
+    canvas.auto_expose();
+
+    assert!((canvas.average_luminance() - 0.18).abs() < 1e-9);
+    assert!(canvas.as_slice()[0].r > 0.01);
+  }
+
+  #[test]
+  fn canvas_should_have_finer_gradients_in_16_bit() {
+    let mut canvas = Canvas::new(256, 1);
+
+    for x in 0..canvas.width() {
+      let value = x as f64 / 255. * 0.05;
+
+      canvas.set_pixel(x, 0, rgb(value, value, value));
+    }
+
+    let mut levels8: Vec<_> = canvas.to_image().pixels().map(|pixel| pixel[0]).collect();
+    let mut levels16: Vec<_> = canvas.to_image_u16().pixels().map(|pixel| pixel[0]).collect();
+
+    levels8.dedup();
+    levels16.dedup();
+
+    assert!(levels8.len() <= 13);
+    assert_eq!(levels16.len(), 256);
+  }
+
+  #[test]
+  fn canvas_should_save_16_bit_png() {
+    let path = std::env::temp_dir().join(format!("raytracer_canvas_16_bit_{}.png", std::process::id()));
+    let mut canvas = Canvas::new(2, 1);
+
+    canvas.set_pixel(1, 0, Color::WHITE);
+    canvas.save_to_png16(path.to_str().unwrap()).unwrap();
+
+    let image = image::open(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(image.color(), image::ColorType::Rgba16);
+    assert_eq!(image.to_rgba16().get_pixel(1, 0).0, [65535; 4]);
+  }
+
+  #[test]
+  fn canvas_should_round_trip_raw_files_exactly() {
+    let path = std::env::temp_dir().join(format!("raytracer_canvas_round_trip_{}.raw", std::process::id()));
+    let mut canvas = Canvas::new(3, 2);
+
+    canvas.set_pixel(0, 0, rgb(1e6, 0.1, -0.25));
+    canvas.set_pixel(2, 1, rgb(f64::MIN_POSITIVE, 17.5, -1e-12));
+    canvas.set_pixel(1, 1, rgb(1. / 3., 2. / 3., 42.));
+    canvas.save_raw(path.to_str().unwrap()).unwrap();
+
+    let loaded = Canvas::load_raw(path.to_str().unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded.width(), 3);
+    assert_eq!(loaded.height(), 2);
+
+    // colors compare approximately, so check every channel bit-for-bit
+    for (expected, actual) in canvas.as_slice().iter().zip(loaded.as_slice()) {
+      assert_eq!(expected.r.to_bits(), actual.r.to_bits());
+      assert_eq!(expected.g.to_bits(), actual.g.to_bits());
+      assert_eq!(expected.b.to_bits(), actual.b.to_bits());
+    }
+  }
+
+  #[test]
+  fn canvas_should_reject_truncated_raw_files() {
+    let path = std::env::temp_dir().join(format!("raytracer_canvas_truncated_{}.raw", std::process::id()));
+
+    Canvas::new(2, 2).save_raw(path.to_str().unwrap()).unwrap();
+
+    let bytes = std::fs::read(&path).unwrap();
+    std::fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+
+    let result = Canvas::load_raw(path.to_str().unwrap());
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(result.is_err());
+  }
 }
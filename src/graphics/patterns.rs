@@ -1,6 +1,8 @@
 //! Patterns for shape rendering.
 
-use crate::maths::{Color, Matrix4x4, Vector};
+use std::any::Any;
+
+use crate::maths::{Color, EPSILON, Matrix4x4, Point};
 
 /// A pattern that can be independently transformed.
 #[derive(Clone, Debug, PartialEq)]
@@ -25,19 +27,43 @@ impl<P> TransformPattern<P> {
 }
 
 impl<P> ColorPattern for TransformPattern<P> where P: ColorPattern {
-  fn sample_at(&self, mut point: Vector) -> Color {
+  fn sample_at(&self, mut point: Point) -> Color {
     if let Ok(inverse) = self.transform.invert() {
       point = inverse * point;
     }
 
     self.pattern.sample_at(point)
   }
+
+  fn pattern_eq(&self, other: &dyn ColorPattern) -> bool {
+    let other: &dyn Any = other;
+
+    other
+      .downcast_ref::<Self>()
+      .is_some_and(|other| self.transform == other.transform && self.pattern.pattern_eq(&other.pattern))
+  }
 }
 
 /// Represents a pattern that can produces colors at distinct points on an object.
-pub trait ColorPattern {
+pub trait ColorPattern: Any + Send + Sync {
   /// Samples the color of the pattern at the given point.
-  fn sample_at(&self, point: Vector) -> Color;
+  fn sample_at(&self, point: Point) -> Color;
+
+  /// Determines if this pattern is equal to another pattern.
+  ///
+  /// Patterns that don't override this are never equal by value, only by sharing an instance.
+  fn pattern_eq(&self, _other: &dyn ColorPattern) -> bool {
+    false
+  }
+}
+
+/// Compares a pattern by value with another pattern, which may be of a different type.
+///
+/// Patterns that implement `PartialEq` can use this to implement `ColorPattern::pattern_eq`.
+pub fn pattern_eq<P: ColorPattern + PartialEq>(pattern: &P, other: &dyn ColorPattern) -> bool {
+  let other: &dyn Any = other;
+
+  other.downcast_ref::<P>() == Some(pattern)
 }
 
 /// A simple striped color pattern.
@@ -45,23 +71,39 @@ pub trait ColorPattern {
 pub struct StripedPattern {
   a: Color,
   b: Color,
+  scale: f64,
 }
 
 impl StripedPattern {
   /// Creates a new striped pattern with the given colors.
   pub fn new(a: Color, b: Color) -> Self {
-    Self { a, b }
+    Self { a, b, scale: 1. }
+  }
+
+  /// Sets the stripe width, in the pattern's space.
+  ///
+  /// Panics if the scale isn't positive, since the pattern divides by it.
+  pub fn with_scale(self, scale: f64) -> Self {
+    assert!(scale > 0., "Pattern scale must be positive, got {}", scale);
+
+    Self { scale, ..self }
   }
 }
 
 impl ColorPattern for StripedPattern {
-  fn sample_at(&self, point: Vector) -> Color {
-    if (point.x.floor() % 2.) == 0. {
+  fn sample_at(&self, point: Point) -> Color {
+    let x = point.x / self.scale;
+
+    if (x.floor() % 2.) == 0. {
       self.a
     } else {
       self.b
     }
   }
+
+  fn pattern_eq(&self, other: &dyn ColorPattern) -> bool {
+    pattern_eq(self, other)
+  }
 }
 
 /// A simple gradient color pattern.
@@ -69,22 +111,37 @@ impl ColorPattern for StripedPattern {
 pub struct GradientPattern {
   a: Color,
   b: Color,
+  scale: f64,
 }
 
 impl GradientPattern {
   /// Creates a new gradient pattern with the given colors.
   pub fn new(a: Color, b: Color) -> Self {
-    Self { a, b }
+    Self { a, b, scale: 1. }
+  }
+
+  /// Sets the gradient length, in the pattern's space.
+  ///
+  /// Panics if the scale isn't positive, since the pattern divides by it.
+  pub fn with_scale(self, scale: f64) -> Self {
+    assert!(scale > 0., "Pattern scale must be positive, got {}", scale);
+
+    Self { scale, ..self }
   }
 }
 
 impl ColorPattern for GradientPattern {
-  fn sample_at(&self, point: Vector) -> Color {
+  fn sample_at(&self, point: Point) -> Color {
+    let x = point.x / self.scale;
     let distance = self.b - self.a;
-    let fraction = point.x - point.x.floor();
+    let fraction = x - x.floor();
 
     self.a + distance * fraction
   }
+
+  fn pattern_eq(&self, other: &dyn ColorPattern) -> bool {
+    pattern_eq(self, other)
+  }
 }
 
 /// A simple ring color pattern.
@@ -92,20 +149,33 @@ impl ColorPattern for GradientPattern {
 pub struct RingPattern {
   a: Color,
   b: Color,
+  scale: f64,
 }
 
 impl RingPattern {
   /// Creates a new ring pattern with the given colors.
   pub fn new(a: Color, b: Color) -> Self {
-    Self { a, b }
+    Self { a, b, scale: 1. }
+  }
+
+  /// Sets the ring width, in the pattern's space.
+  ///
+  /// Panics if the scale isn't positive, since the pattern divides by it.
+  pub fn with_scale(self, scale: f64) -> Self {
+    assert!(scale > 0., "Pattern scale must be positive, got {}", scale);
+
+    Self { scale, ..self }
   }
 }
 
 
 impl ColorPattern for RingPattern {
-  fn sample_at(&self, point: Vector) -> Color {
-    let x2 = point.x * point.x;
-    let z2 = point.z * point.z;
+  fn sample_at(&self, point: Point) -> Color {
+    let x = point.x / self.scale;
+    let z = point.z / self.scale;
+
+    let x2 = x * x;
+    let z2 = z * z;
 
     if (x2 + z2).sqrt().floor() % 2. == 0. {
       self.a
@@ -113,6 +183,10 @@ impl ColorPattern for RingPattern {
       self.b
     }
   }
+
+  fn pattern_eq(&self, other: &dyn ColorPattern) -> bool {
+    pattern_eq(self, other)
+  }
 }
 
 /// A simple checker color pattern.
@@ -120,23 +194,46 @@ impl ColorPattern for RingPattern {
 pub struct CheckerPattern {
   a: Color,
   b: Color,
+  scale: f64,
 }
 
 impl CheckerPattern {
   /// Creates a new checker pattern with the given colors.
   pub fn new(a: Color, b: Color) -> Self {
-    Self { a, b }
+    Self { a, b, scale: 1. }
+  }
+
+  /// Sets the cell size, in the pattern's space.
+  ///
+  /// Panics if the scale isn't positive, since the pattern divides by it.
+  pub fn with_scale(self, scale: f64) -> Self {
+    assert!(scale > 0., "Pattern scale must be positive, got {}", scale);
+
+    Self { scale, ..self }
   }
 }
 
 impl ColorPattern for CheckerPattern {
-  fn sample_at(&self, point: Vector) -> Color {
-    if (point.x.floor() + point.y.floor() + point.z.floor()) % 2. == 0. {
+  fn sample_at(&self, point: Point) -> Color {
+    // snap coordinates that sit on a cell boundary, so a plane at y = 0 doesn't flicker between
+    // the cells either side of it from floating point noise in the intersection
+    let cell = |component: f64| {
+      let scaled = component / self.scale;
+      let nearest = scaled.round();
+
+      if (scaled - nearest).abs() < EPSILON { nearest as i64 } else { scaled.floor() as i64 }
+    };
+
+    if (cell(point.x) + cell(point.y) + cell(point.z)).rem_euclid(2) == 0 {
       self.a
     } else {
       self.b
     }
   }
+
+  fn pattern_eq(&self, other: &dyn ColorPattern) -> bool {
+    pattern_eq(self, other)
+  }
 }
 
 
@@ -213,4 +310,61 @@ mod tests {
     assert_eq!(pattern.sample_at(point(0., 0., 0.99)), Color::WHITE);
     assert_eq!(pattern.sample_at(point(0., 0., 1.01)), Color::BLACK);
   }
-}
\ No newline at end of file
+
+  #[test]
+  fn checker_pattern_treats_negative_zero_as_zero() {
+    let pattern = CheckerPattern::new(Color::WHITE, Color::BLACK);
+
+    assert_eq!(pattern.sample_at(point(-0., 0., 0.)), Color::WHITE);
+    assert_eq!(pattern.sample_at(point(0., 0., 0.)), Color::WHITE);
+    assert_eq!(pattern.sample_at(point(0.5, -0., 0.5)), Color::WHITE);
+  }
+
+  #[test]
+  fn checker_pattern_alternates_across_the_origin() {
+    let pattern = CheckerPattern::new(Color::WHITE, Color::BLACK);
+
+    assert_eq!(pattern.sample_at(point(-1.5, 0., 0.)), Color::WHITE);
+    assert_eq!(pattern.sample_at(point(-0.5, 0., 0.)), Color::BLACK);
+    assert_eq!(pattern.sample_at(point(0.5, 0., 0.)), Color::WHITE);
+    assert_eq!(pattern.sample_at(point(1.5, 0., 0.)), Color::BLACK);
+    assert_eq!(pattern.sample_at(point(-0.5, 0., -0.5)), Color::WHITE);
+  }
+
+  #[test]
+  fn checker_pattern_ignores_noise_on_a_plane_at_the_origin() {
+    let pattern = CheckerPattern::new(Color::WHITE, Color::BLACK);
+
+    assert_eq!(pattern.sample_at(point(0.5, -1e-9, 0.5)), Color::WHITE);
+    assert_eq!(pattern.sample_at(point(0.5, 1e-9, 0.5)), Color::WHITE);
+    assert_eq!(pattern.sample_at(point(-0.5, -1e-9, 0.5)), Color::BLACK);
+  }
+
+  #[test]
+  fn checker_pattern_with_scale_has_cells_at_even_coordinates() {
+    let pattern = CheckerPattern::new(Color::WHITE, Color::BLACK).with_scale(2.);
+
+    assert_eq!(pattern.sample_at(point(0., 0., 0.)), Color::WHITE);
+    assert_eq!(pattern.sample_at(point(1.5, 0., 0.)), Color::WHITE);
+    assert_eq!(pattern.sample_at(point(2., 0., 0.)), Color::BLACK);
+    assert_eq!(pattern.sample_at(point(0., 1.99, 1.)), Color::WHITE);
+    assert_eq!(pattern.sample_at(point(0., 0., 2.01)), Color::BLACK);
+    assert_eq!(pattern.sample_at(point(-1., 0., 0.)), Color::BLACK);
+    assert_eq!(pattern.sample_at(point(3.9, 3.9, 0.)), Color::WHITE);
+  }
+
+  #[test]
+  fn striped_pattern_with_scale_widens_stripes() {
+    let pattern = StripedPattern::new(Color::WHITE, Color::BLACK).with_scale(2.);
+
+    assert_eq!(pattern.sample_at(point(1.9, 0., 0.)), Color::WHITE);
+    assert_eq!(pattern.sample_at(point(2., 0., 0.)), Color::BLACK);
+    assert_eq!(pattern.sample_at(point(4., 0., 0.)), Color::WHITE);
+  }
+
+  #[test]
+  #[should_panic(expected = "Pattern scale must be positive")]
+  fn patterns_reject_scales_that_are_not_positive() {
+    StripedPattern::new(Color::WHITE, Color::BLACK).with_scale(0.);
+  }
+}
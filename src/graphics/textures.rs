@@ -0,0 +1,222 @@
+//! Image-backed textures for shape rendering.
+
+use std::path::Path;
+
+use image::ImageResult;
+
+use crate::graphics::{ColorPattern, pattern_eq};
+use crate::maths::{Color, PI, Point, rgb};
+
+/// Determines how points on an object are mapped to (u, v) texture coordinates.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum UvMapping {
+  /// Projects the texture down the y axis, repeating every unit in x and z.
+  Planar,
+  /// Wraps the texture around a unit sphere at the origin.
+  Spherical,
+  /// Wraps the texture around the y axis, repeating every unit in y.
+  Cylindrical,
+}
+
+impl UvMapping {
+  /// Maps the given object point to (u, v) coordinates in the range [0, 1].
+  pub fn map(&self, point: Point) -> (f64, f64) {
+    match self {
+      UvMapping::Planar => {
+        (point.x.rem_euclid(1.), point.z.rem_euclid(1.))
+      }
+      UvMapping::Spherical => {
+        let radius = (point.x * point.x + point.y * point.y + point.z * point.z).sqrt();
+        let phi = (point.y / radius).acos();
+
+        (Self::azimuth(point), 1. - phi / PI)
+      }
+      UvMapping::Cylindrical => {
+        (Self::azimuth(point), point.y.rem_euclid(1.))
+      }
+    }
+  }
+
+  /// Computes the fraction of a full turn about the y axis for the given point.
+  fn azimuth(point: Point) -> f64 {
+    let theta = point.x.atan2(point.z);
+    let raw_u = theta / (2. * PI);
+
+    1. - (raw_u + 0.5)
+  }
+}
+
+/// Determines how texture coordinates outside of [0, 1] are brought back into range.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum WrapMode {
+  /// Tiles the texture, so 1.25 samples as 0.25.
+  Repeat,
+  /// Stretches the edges of the texture outwards, so 1.25 samples as 1.
+  #[default]
+  Clamp,
+  /// Tiles the texture, flipping every other tile, so 1.25 samples as 0.75.
+  Mirror,
+}
+
+impl WrapMode {
+  /// Wraps the given texture coordinate into the range [0, 1].
+  pub fn apply(&self, t: f64) -> f64 {
+    match self {
+      WrapMode::Repeat => t.rem_euclid(1.),
+      WrapMode::Clamp => t.clamp(0., 1.),
+      WrapMode::Mirror => {
+        let t = t.rem_euclid(2.);
+
+        if t > 1. { 2. - t } else { t }
+      }
+    }
+  }
+}
+
+/// A pattern that samples colors from an image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TexturePattern {
+  width: usize,
+  height: usize,
+  pixels: Vec<Color>,
+  mapping: UvMapping,
+  wrap: WrapMode,
+}
+
+impl TexturePattern {
+  /// Creates a new texture pattern from the given pixels, in row-major order.
+  pub fn new(width: usize, height: usize, pixels: Vec<Color>, mapping: UvMapping) -> Self {
+    assert_eq!(pixels.len(), width * height, "Pixel count does not match dimensions");
+
+    Self { width, height, pixels, mapping, wrap: WrapMode::default() }
+  }
+
+  /// Sets how coordinates outside of [0, 1] are wrapped before sampling.
+  pub fn with_wrap(self, wrap: WrapMode) -> Self {
+    Self { wrap, ..self }
+  }
+
+  /// Loads a texture pattern from the image at the given path.
+  pub fn from_file(path: impl AsRef<Path>, mapping: UvMapping) -> ImageResult<Self> {
+    let image = image::open(path)?.to_rgb8();
+
+    let pixels = image
+      .pixels()
+      .map(|pixel| {
+        let [r, g, b] = pixel.0;
+
+        rgb(r as f64 / 255., g as f64 / 255., b as f64 / 255.)
+      })
+      .collect();
+
+    Ok(Self::new(image.width() as usize, image.height() as usize, pixels, mapping))
+  }
+
+  /// Samples the color of the texture at the given (u, v) coordinates.
+  pub fn sample_uv(&self, u: f64, v: f64) -> Color {
+    let u = self.wrap.apply(u);
+    let v = self.wrap.apply(v);
+
+    // flip v so that v = 0 is the bottom of the image
+    let x = (u * (self.width - 1) as f64).round() as usize;
+    let y = ((1. - v) * (self.height - 1) as f64).round() as usize;
+
+    self.pixels[x.min(self.width - 1) + y.min(self.height - 1) * self.width]
+  }
+}
+
+impl ColorPattern for TexturePattern {
+  fn sample_at(&self, point: Point) -> Color {
+    let (u, v) = self.mapping.map(point);
+
+    self.sample_uv(u, v)
+  }
+
+  fn pattern_eq(&self, other: &dyn ColorPattern) -> bool {
+    pattern_eq(self, other)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::maths::point;
+
+  use super::*;
+
+  #[test]
+  fn planar_mapping_repeats_in_x_and_z() {
+    let mapping = UvMapping::Planar;
+
+    assert_eq!(mapping.map(point(0.25, 0., 0.5)), (0.25, 0.5));
+    assert_eq!(mapping.map(point(1.25, 5., -0.5)), (0.25, 0.5));
+  }
+
+  #[test]
+  fn spherical_mapping_of_points_on_the_unit_sphere() {
+    let mapping = UvMapping::Spherical;
+
+    assert_eq!(mapping.map(point(0., 0., -1.)), (0.0, 0.5));
+    assert_eq!(mapping.map(point(1., 0., 0.)), (0.25, 0.5));
+    assert_eq!(mapping.map(point(0., 0., 1.)), (0.5, 0.5));
+    assert_eq!(mapping.map(point(0., 1., 0.)), (0.5, 1.0));
+  }
+
+  #[test]
+  fn cylindrical_mapping_wraps_around_the_y_axis() {
+    let mapping = UvMapping::Cylindrical;
+
+    assert_eq!(mapping.map(point(0., 0.25, -1.)), (0.0, 0.25));
+    assert_eq!(mapping.map(point(1., 1.5, 0.)), (0.25, 0.5));
+  }
+
+  #[test]
+  fn texture_pattern_samples_pixels_by_uv() {
+    let texture = TexturePattern::new(
+      2,
+      2,
+      vec![Color::RED, Color::GREEN, Color::BLUE, Color::WHITE],
+      UvMapping::Planar,
+    );
+
+    assert_eq!(texture.sample_uv(0., 1.), Color::RED);
+    assert_eq!(texture.sample_uv(1., 1.), Color::GREEN);
+    assert_eq!(texture.sample_uv(0., 0.), Color::BLUE);
+    assert_eq!(texture.sample_uv(1., 0.), Color::WHITE);
+  }
+
+  #[test]
+  fn wrap_modes_bring_coordinates_into_range() {
+    assert_eq!(WrapMode::Repeat.apply(1.25), 0.25);
+    assert_eq!(WrapMode::Clamp.apply(1.25), 1.);
+    assert_eq!(WrapMode::Mirror.apply(1.25), 0.75);
+
+    assert_eq!(WrapMode::Repeat.apply(-0.25), 0.75);
+    assert_eq!(WrapMode::Clamp.apply(-0.25), 0.);
+    assert_eq!(WrapMode::Mirror.apply(-0.25), 0.25);
+  }
+
+  #[test]
+  fn texture_pattern_wraps_out_of_range_coordinates() {
+    // a horizontal ramp, with each pixel a quarter brighter than the last
+    let ramp = TexturePattern::new(5, 1, (0..5).map(|i| Color::gray(i as f64 / 4.)).collect(), UvMapping::Planar);
+
+    assert_eq!(ramp.sample_uv(1.25, 0.5), Color::gray(1.));
+    assert_eq!(ramp.clone().with_wrap(WrapMode::Repeat).sample_uv(1.25, 0.5), Color::gray(0.25));
+    assert_eq!(ramp.clone().with_wrap(WrapMode::Clamp).sample_uv(1.25, 0.5), Color::gray(1.));
+    assert_eq!(ramp.clone().with_wrap(WrapMode::Mirror).sample_uv(1.25, 0.5), Color::gray(0.75));
+    assert_eq!(ramp.with_wrap(WrapMode::Mirror).sample_uv(-3.5, 0.5), Color::gray(0.5));
+  }
+
+  #[test]
+  fn texture_pattern_samples_object_points_through_mapping() {
+    let texture = TexturePattern::new(
+      2,
+      2,
+      vec![Color::RED, Color::GREEN, Color::BLUE, Color::WHITE],
+      UvMapping::Planar,
+    );
+
+    assert_eq!(texture.sample_at(point(0.1, 0., 0.9)), Color::RED);
+    assert_eq!(texture.sample_at(point(0.9, 0., 0.1)), Color::WHITE);
+  }
+}
@@ -0,0 +1,83 @@
+//! Tone-mapping curves for bringing high dynamic range colors into the displayable range.
+//!
+//! Each curve maps a color per channel, and can be passed straight to `Canvas::tone_map`.
+
+use crate::maths::Color;
+
+/// The classic Reinhard curve, x / (1 + x), which never quite reaches white.
+pub fn reinhard(color: Color) -> Color {
+  color.map(|channel| {
+    let channel = channel.max(0.);
+
+    channel / (1. + channel)
+  })
+}
+
+/// Narkowicz's fit of the ACES filmic curve, with a gentle toe and a soft shoulder.
+pub fn aces_filmic(color: Color) -> Color {
+  color.map(|channel| {
+    let x = channel.max(0.);
+
+    (x * (2.51 * x + 0.03) / (x * (2.43 * x + 0.59) + 0.14)).clamp(0., 1.)
+  })
+}
+
+/// John Hable's filmic curve from Uncharted 2, with the white point at 11.2.
+pub fn hable(color: Color) -> Color {
+  const EXPOSURE_BIAS: f64 = 2.;
+  const WHITE_POINT: f64 = 11.2;
+
+  fn curve(x: f64) -> f64 {
+    const A: f64 = 0.15; // shoulder strength
+    const B: f64 = 0.50; // linear strength
+    const C: f64 = 0.10; // linear angle
+    const D: f64 = 0.20; // toe strength
+    const E: f64 = 0.02; // toe numerator
+    const F: f64 = 0.30; // toe denominator
+
+    (x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F) - E / F
+  }
+
+  let white_scale = 1. / curve(WHITE_POINT);
+
+  color.map(|channel| (curve(channel.max(0.) * EXPOSURE_BIAS) * white_scale).clamp(0., 1.))
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::maths::rgb;
+
+  use super::*;
+
+  #[test]
+  fn aces_filmic_rolls_bright_values_off_into_range() {
+    let mapped = aces_filmic(rgb(0.18, 1., 16.));
+
+    assert!((mapped.r - 0.2669).abs() < 0.001);
+    assert!((mapped.g - 0.8038).abs() < 0.001);
+    assert!(mapped.b > 0.99 && mapped.b <= 1.);
+
+    // the shoulder compresses highlights: doubling a bright input changes the output far less
+    let doubled = |value: f64| aces_filmic(rgb(value * 2., 0., 0.)).r - aces_filmic(rgb(value, 0., 0.)).r;
+
+    assert!(doubled(4.) < doubled(0.1) / 5.);
+  }
+
+  #[test]
+  fn curves_map_black_to_black_and_stay_in_range() {
+    for curve in [reinhard, aces_filmic, hable] {
+      assert_eq!(curve(Color::BLACK), Color::BLACK);
+
+      for value in [0.01, 0.5, 1., 4., 100.] {
+        let mapped = curve(rgb(value, value, value));
+
+        assert!(mapped.r > 0. && mapped.r <= 1.);
+      }
+    }
+  }
+
+  #[test]
+  fn hable_reaches_white_at_its_white_point() {
+    assert!((hable(rgb(5.6, 5.6, 5.6)).r - 1.).abs() < 1e-9);
+  }
+}
@@ -0,0 +1,30 @@
+//! A fun little Ray Tracer built with Rust.
+//!
+//! Scenes are built from shapes, materials and lights (or loaded from YAML via the `loader`),
+//! then rendered to a `Canvas` through a `Camera`.
+//!
+//! ```
+//! use raytracer::maths::{point, vec3, Color, Matrix4x4, PI};
+//! use raytracer::scene::{Camera, Material, PointLight, Scene, Sphere};
+//!
+//! let mut scene = Scene::new();
+//!
+//! scene.add_light(PointLight::new(point(-10., 10., -10.), Color::WHITE));
+//! scene.add_object(Sphere::new().with_material(Material::default().with_color(Color::RED)));
+//!
+//! let mut camera = Camera::new(1, 1, PI / 3.);
+//! camera.transform = Matrix4x4::look_at(point(0., 0., -5.), point(0., 0., 0.), vec3(0., 1., 0.));
+//!
+//! let mut canvas = camera.render(&scene);
+//! let pixel = canvas.get_pixel(0, 0);
+//!
+//! // the red sphere fills the single pixel
+//! assert!(pixel.r > pixel.g && pixel.r > pixel.b);
+//! ```
+
+#![allow(dead_code)]
+
+pub mod graphics;
+pub mod loader;
+pub mod maths;
+pub mod scene;
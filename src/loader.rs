@@ -1,9 +1,25 @@
-use serde::{Deserialize, Serialize};
-
-use crate::graphics::{CheckerPattern, GradientPattern, RingPattern, StripedPattern};
-use crate::maths::{Color, Matrix4x4, rgb, vec3, Vector};
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::{Deserialize, Deserializer, forward_to_deserialize_any, Serialize};
+use serde::de::{DeserializeOwned, Visitor};
+use serde_yaml::Value;
+
+use crate::graphics::{CheckerPattern, GradientPattern, RingPattern, StripedPattern, TexturePattern, UvMapping};
+use crate::maths::{Color, Matrix4x4, point, Point, rgb, vec3, Vector};
 use crate::scene::*;
 
+pub use errors::*;
+pub use obj::*;
+pub use ply::*;
+
+mod errors;
+mod obj;
+mod ply;
+
 type PackedTuple = [f64; 3];
 
 impl From<PackedTuple> for Vector {
@@ -12,17 +28,119 @@ impl From<PackedTuple> for Vector {
   }
 }
 
+impl From<PackedTuple> for Point {
+  fn from([x, y, z]: PackedTuple) -> Self {
+    point(x, y, z)
+  }
+}
+
 impl From<PackedTuple> for Color {
   fn from([r, g, b]: PackedTuple) -> Self {
     rgb(r, g, b)
   }
 }
 
+/// A tagged enum from a scene file, which keeps tags this version doesn't recognize.
+///
+/// Unrecognized tags are reported by name when the scene is built, rather than by picking apart
+/// serde's error messages.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Tagged<T> {
+  Known(T),
+  Unknown(Value),
+}
+
+impl<T: DeserializeOwned> Tagged<T> {
+  /// Resolves the tagged value, reporting an unrecognized tag with the given error.
+  fn resolve(&self, unknown: impl Fn(String) -> LoaderError) -> Result<&T, LoaderError> {
+    let value = match self {
+      Tagged::Known(known) => return Ok(known),
+      Tagged::Unknown(value) => value,
+    };
+
+    // unit variants are written as plain strings, and the others as single-entry mappings
+    let tag = match value {
+      Value::String(tag) => Some(tag.as_str()),
+      Value::Mapping(mapping) if mapping.len() == 1 => mapping.iter().next().and_then(|(tag, _)| tag.as_str()),
+      _ => None,
+    };
+
+    match tag {
+      Some(tag) if !variant_names::<T>().contains(&tag) => Err(unknown(tag.to_string())),
+      // the tag is known but its contents are malformed, so let serde explain what's wrong
+      _ => match serde_yaml::from_value::<T>(value.clone()) {
+        Ok(_) => Err(LoaderError::Parse("ambiguous tagged value".to_string())),
+        Err(error) => Err(error.into()),
+      },
+    }
+  }
+}
+
+/// Lists the variant names of the given enum, as its derived `Deserialize` implementation knows them.
+fn variant_names<T: DeserializeOwned>() -> &'static [&'static str] {
+  /// A deserializer that only records the variants it's asked for.
+  struct Probe<'a>(&'a Cell<&'static [&'static str]>);
+
+  impl<'de> Deserializer<'de> for Probe<'_> {
+    type Error = serde::de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+      Err(serde::de::Error::custom("only enums can be probed"))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, variants: &'static [&'static str], _visitor: V) -> Result<V::Value, Self::Error> {
+      self.0.set(variants);
+
+      Err(serde::de::Error::custom("probed"))
+    }
+
+    forward_to_deserialize_any! {
+      bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf option
+      unit unit_struct newtype_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+  }
+
+  let variants = Cell::new(&[][..]);
+  let _ = T::deserialize(Probe(&variants));
+
+  variants.get()
+}
+
 /// A serialized `Scene` that can be read from a file.
 #[derive(Serialize, Deserialize)]
 pub struct PackedScene {
+  ambient: Option<PackedTuple>,
+  background: Option<PackedTuple>,
   lights: Vec<PackedLight>,
   objects: Vec<PackedObject>,
+  cameras: Option<BTreeMap<String, PackedCamera>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PackedCamera {
+  from: PackedTuple,
+  to: PackedTuple,
+  up: Option<PackedTuple>,
+  field_of_view: Option<f64>,
+  width: Option<u32>,
+  height: Option<u32>,
+}
+
+impl PackedCamera {
+  pub fn build(&self) -> Camera {
+    let up = self.up.unwrap_or([0., 1., 0.]);
+    let field_of_view = self.field_of_view.unwrap_or(60.).to_radians();
+
+    let mut camera = Camera::new(
+      self.width.unwrap_or(1920),
+      self.height.unwrap_or(1080),
+      field_of_view,
+    );
+
+    camera.transform = Matrix4x4::look_at(self.from.into(), self.to.into(), up.into());
+    camera
+  }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -36,48 +154,131 @@ impl PackedLight {
     let [x, y, z] = self.position;
     let [r, g, b] = self.color.unwrap_or([1., 1., 1.]);
 
-    PointLight::new(vec3(x, y, z), rgb(r, g, b))
+    PointLight::new(point(x, y, z), rgb(r, g, b))
   }
 }
 
 #[derive(Serialize, Deserialize)]
 struct PackedObject {
-  kind: PackedKind,
+  kind: Tagged<PackedKind>,
   position: Option<PackedTuple>,
   rotation: Option<PackedTuple>,
   scale: Option<PackedTuple>,
   material: Option<PackedMaterial>,
+  keyframes: Option<Vec<PackedKeyframe>>,
 }
 
-#[derive(Copy, Clone, Serialize, Deserialize)]
+/// A keyed position, rotation and/or scale for an object at a point in time.
+#[derive(Serialize, Deserialize)]
+struct PackedKeyframe {
+  time: f64,
+  position: Option<PackedTuple>,
+  rotation: Option<PackedTuple>,
+  scale: Option<PackedTuple>,
+}
+
+/// Samples a keyframed property at the given time, interpolating linearly between keys.
+///
+/// Times outside of the keyed range hold the first or last key.
+fn sample_track(keyframes: &[PackedKeyframe], time: f64, property: impl Fn(&PackedKeyframe) -> Option<PackedTuple>) -> Option<PackedTuple> {
+  let mut keys: Vec<_> = keyframes
+    .iter()
+    .filter_map(|keyframe| property(keyframe).map(|value| (keyframe.time, value)))
+    .collect();
+
+  keys.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+
+  let (first_time, first) = *keys.first()?;
+  let (last_time, last) = *keys.last()?;
+
+  if time <= first_time {
+    return Some(first);
+  }
+
+  if time >= last_time {
+    return Some(last);
+  }
+
+  keys.windows(2).find_map(|window| {
+    let [(from_time, from), (to_time, to)] = [window[0], window[1]];
+
+    if time < from_time || time > to_time {
+      return None;
+    }
+
+    let amount = (time - from_time) / (to_time - from_time);
+
+    Some([0, 1, 2].map(|axis| from[axis] + (to[axis] - from[axis]) * amount))
+  })
+}
+
+#[derive(Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 enum PackedKind {
   Sphere,
   Plane,
+  Cube,
+  Cylinder { min: Option<f64>, max: Option<f64>, closed: Option<bool> },
+  Cone { min: Option<f64>, max: Option<f64>, closed: Option<bool> },
+  Triangle { p1: PackedTuple, p2: PackedTuple, p3: PackedTuple },
+  Group { children: Vec<PackedObject> },
+  Mesh { path: String },
+}
+
+/// Resolves a relative file path against the given directory; absolute paths are kept as they are.
+fn resolve_path(directory: &Path, path: &mut String) {
+  *path = directory.join(&*path).to_string_lossy().into_owned();
 }
 
 impl PackedObject {
-  pub fn build(&self) -> Box<dyn Traceable> {
+  /// Resolves the files this object refers to, and those of its children, against the given directory.
+  fn resolve_paths(&mut self, directory: &Path) {
+    if let Tagged::Known(kind) = &mut self.kind {
+      match kind {
+        PackedKind::Group { children } => children.iter_mut().for_each(|child| child.resolve_paths(directory)),
+        PackedKind::Mesh { path } => resolve_path(directory, path),
+        _ => {}
+      }
+    }
+
+    if let Some(Tagged::Known(PackedTexture::Image { path, .. })) = self.material.as_mut().and_then(|material| material.texture.as_mut()) {
+      resolve_path(directory, path);
+    }
+  }
+
+  /// Builds the object, with any keyframed transforms sampled at the given time.
+  pub fn build_at(&self, time: f64) -> Result<Box<dyn Traceable>, LoaderError> {
     let mut transform = Matrix4x4::identity();
-    let material = self.material.as_ref()
-      .map(|packed| packed.build())
-      .unwrap_or(Material::default());
+    let material = match &self.material {
+      Some(packed) => packed.build()?,
+      None => Material::default(),
+    };
 
-    if let Some([x, y, z]) = self.position {
+    let keyframes = self.keyframes.as_deref().unwrap_or_default();
+
+    let position = sample_track(keyframes, time, |keyframe| keyframe.position).or(self.position);
+    let rotation = sample_track(keyframes, time, |keyframe| keyframe.rotation).or(self.rotation);
+    let scale = sample_track(keyframes, time, |keyframe| keyframe.scale).or(self.scale);
+
+    if let Some([x, y, z]) = position {
       transform = transform * Matrix4x4::translate(x, y, z);
     }
 
-    if let Some([x, y, z]) = self.rotation {
+    if let Some([x, y, z]) = rotation {
       transform = transform * Matrix4x4::rotate_x(x);
       transform = transform * Matrix4x4::rotate_y(y);
       transform = transform * Matrix4x4::rotate_z(z);
     };
 
-    if let Some([x, y, z]) = self.scale {
+    if let Some([x, y, z]) = scale {
       transform = transform * Matrix4x4::scale(x, y, z);
     }
 
-    match self.kind {
+    if !transform.is_invertible() {
+      return Err(LoaderError::InvalidTransform);
+    }
+
+    Ok(match *self.kind.resolve(LoaderError::UnknownKind)? {
       PackedKind::Sphere => {
         Box::new(
           Sphere::new()
@@ -92,13 +293,69 @@ impl PackedObject {
             .with_transform(transform)
         )
       }
-    }
+      PackedKind::Cube => {
+        Box::new(
+          Cube::new()
+            .with_material(material)
+            .with_transform(transform)
+        )
+      }
+      PackedKind::Cylinder { min, max, closed } => {
+        let min = min.unwrap_or(f64::NEG_INFINITY);
+        let max = max.unwrap_or(f64::INFINITY);
+
+        Box::new(
+          Cylinder::new(min, max, closed.unwrap_or(false))
+            .with_material(material)
+            .with_transform(transform)
+        )
+      }
+      PackedKind::Cone { min, max, closed } => {
+        let min = min.unwrap_or(f64::NEG_INFINITY);
+        let max = max.unwrap_or(f64::INFINITY);
+
+        Box::new(
+          Cone::new(min, max, closed.unwrap_or(false))
+            .with_material(material)
+            .with_transform(transform)
+        )
+      }
+      PackedKind::Triangle { p1, p2, p3 } => {
+        let [x1, y1, z1] = p1;
+        let [x2, y2, z2] = p2;
+        let [x3, y3, z3] = p3;
+
+        Box::new(
+          Triangle::new(point(x1, y1, z1), point(x2, y2, z2), point(x3, y3, z3))
+            .with_material(material)
+            .with_transform(transform)
+        )
+      }
+      PackedKind::Group { ref children } => {
+        let mut group = Group::new().with_transform(transform);
+
+        for child in children {
+          group
+            .try_add_child(child.build_at(time)?)
+            .map_err(|_| LoaderError::InvalidTransform)?;
+        }
+
+        Box::new(group)
+      }
+      PackedKind::Mesh { ref path } if path.to_lowercase().ends_with(".ply") => {
+        Box::new(parse_ply_file(path)?.with_transform(transform))
+      }
+      PackedKind::Mesh { ref path } => {
+        // mesh materials come from the .obj file's material libraries
+        Box::new(parse_obj_file(path)?.with_transform(transform))
+      }
+    })
   }
 }
 
 #[derive(Serialize, Deserialize)]
 struct PackedMaterial {
-  texture: Option<PackedTexture>,
+  texture: Option<Tagged<PackedTexture>>,
   ambient: Option<f64>,
   diffuse: Option<f64>,
   specular: Option<f64>,
@@ -106,13 +363,25 @@ struct PackedMaterial {
   transparency: Option<f64>,
   reflectivity: Option<f64>,
   refractivity: Option<f64>,
+  fresnel: Option<bool>,
+  cull_backfaces: Option<bool>,
+  reflection_falloff: Option<f64>,
+  reflection_roughness: Option<f64>,
+  refraction_roughness: Option<f64>,
+  reflection_tint: Option<PackedTuple>,
+  blinn_phong: Option<bool>,
+  thin: Option<bool>,
+  conserve_energy: Option<bool>,
 }
 
 impl PackedMaterial {
-  pub fn build(&self) -> Material {
+  pub fn build(&self) -> Result<Material, LoaderError> {
     let default = Material::default();
 
-    let texture = self.texture.unwrap_or(PackedTexture::Solid([1., 1., 1.]));
+    let texture = match &self.texture {
+      Some(packed) => packed.resolve(LoaderError::UnknownTexture)?.build()?,
+      None => Texture::Solid(Color::WHITE),
+    };
     let ambient = self.ambient.unwrap_or(default.ambient);
     let diffuse = self.diffuse.unwrap_or(default.diffuse);
     let specular = self.specular.unwrap_or(default.specular);
@@ -120,9 +389,18 @@ impl PackedMaterial {
     let transparency = self.transparency.unwrap_or(default.transparency);
     let reflectivity = self.reflectivity.unwrap_or(default.reflectivity);
     let refractivity = self.refractivity.unwrap_or(default.refractivity);
-
-    Material {
-      texture: texture.build(),
+    let fresnel = self.fresnel.unwrap_or(default.fresnel);
+    let cull_backfaces = self.cull_backfaces.unwrap_or(default.cull_backfaces);
+    let reflection_falloff = self.reflection_falloff.unwrap_or(default.reflection_falloff);
+    let reflection_roughness = self.reflection_roughness.unwrap_or(default.reflection_roughness).clamp(0., 1.);
+    let refraction_roughness = self.refraction_roughness.unwrap_or(default.refraction_roughness).clamp(0., 1.);
+    let reflection_tint = self.reflection_tint.map_or(default.reflection_tint, Color::from);
+    let blinn_phong = self.blinn_phong.unwrap_or(default.blinn_phong);
+    let thin = self.thin.unwrap_or(default.thin);
+    let conserve_energy = self.conserve_energy.unwrap_or(default.conserve_energy);
+
+    Ok(Material {
+      texture,
       ambient,
       diffuse,
       specular,
@@ -130,11 +408,21 @@ impl PackedMaterial {
       transparency,
       reflectivity,
       refractivity,
-    }
+      fresnel,
+      cull_backfaces,
+      reflection_falloff,
+      reflection_roughness,
+      refraction_roughness,
+      reflection_tint,
+      blinn_phong,
+      thin,
+      conserve_energy,
+      ..default
+    })
   }
 }
 
-#[derive(Copy, Clone, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 enum PackedTexture {
   Solid(PackedTuple),
@@ -142,65 +430,165 @@ enum PackedTexture {
   Gradient(PackedTuple, PackedTuple),
   Ring(PackedTuple, PackedTuple),
   Striped(PackedTuple, PackedTuple),
+  Image { path: String, mapping: Option<PackedMapping> },
+}
+
+#[derive(Copy, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum PackedMapping {
+  Planar,
+  Spherical,
+  Cylindrical,
+}
+
+impl PackedMapping {
+  pub fn build(&self) -> UvMapping {
+    match self {
+      PackedMapping::Planar => UvMapping::Planar,
+      PackedMapping::Spherical => UvMapping::Spherical,
+      PackedMapping::Cylindrical => UvMapping::Cylindrical,
+    }
+  }
 }
 
 impl PackedTexture {
-  pub fn build(&self) -> Texture {
-    match *self {
+  pub fn build(&self) -> Result<Texture, LoaderError> {
+    Ok(match *self {
       PackedTexture::Solid(color) => {
         Texture::Solid(color.into())
       }
       PackedTexture::Checker(from, to) => {
         let pattern = CheckerPattern::new(from.into(), to.into());
 
-        Texture::Pattern(Box::new(pattern))
+        Texture::Pattern(Arc::new(pattern))
       }
       PackedTexture::Gradient(from, to) => {
         let pattern = GradientPattern::new(from.into(), to.into());
 
-        Texture::Pattern(Box::new(pattern))
+        Texture::Pattern(Arc::new(pattern))
       }
       PackedTexture::Ring(from, to) => {
         let pattern = RingPattern::new(from.into(), to.into());
 
-        Texture::Pattern(Box::new(pattern))
+        Texture::Pattern(Arc::new(pattern))
       }
       PackedTexture::Striped(from, to) => {
         let pattern = StripedPattern::new(from.into(), to.into());
 
-        Texture::Pattern(Box::new(pattern))
+        Texture::Pattern(Arc::new(pattern))
       }
-    }
+      PackedTexture::Image { ref path, mapping } => {
+        let mapping = mapping.unwrap_or(PackedMapping::Planar).build();
+        let pattern = TexturePattern::from_file(path, mapping).map_err(|error| match error {
+          image::ImageError::IoError(error) => LoaderError::Io(error),
+          error => LoaderError::Parse(format!("{}: {}", path, error)),
+        })?;
+
+        Texture::Pattern(Arc::new(pattern))
+      }
+    })
   }
 }
 
 impl PackedScene {
   /// Loads the scene from the given YAML file.
-  pub fn from_yaml_file(path: &str) -> anyhow::Result<Self> {
+  ///
+  /// Meshes and images named by the scene are found relative to the file's directory.
+  pub fn from_yaml_file(path: &str) -> Result<Self, LoaderError> {
     let file = std::fs::File::open(path)?;
-    let scene: Self = serde_yaml::from_reader(&file)?;
+    let mut scene = Self::from_reader(file)?;
+
+    if let Some(directory) = Path::new(path).parent() {
+      for object in &mut scene.objects {
+        object.resolve_paths(directory);
+      }
+    }
 
     Ok(scene)
   }
 
+  /// Loads the scene from the given YAML source.
+  pub fn from_yaml_str(source: &str) -> Result<Self, LoaderError> {
+    let scene: Self = serde_yaml::from_str(source)?;
+
+    Ok(scene)
+  }
+
+  /// Loads the scene from YAML read from the given reader.
+  pub fn from_reader(reader: impl Read) -> Result<Self, LoaderError> {
+    let scene: Self = serde_yaml::from_reader(reader)?;
+
+    Ok(scene)
+  }
+
+  /// Combines the lights, objects and cameras of another scene into this one.
+  ///
+  /// This scene's ambient and background colors win when both set them, as do its cameras when
+  /// both scenes name a camera the same. The other scene's objects are added after this one's.
+  pub fn merge(mut self, other: PackedScene) -> Self {
+    self.ambient = self.ambient.or(other.ambient);
+    self.background = self.background.or(other.background);
+    self.lights.extend(other.lights);
+    self.objects.extend(other.objects);
+
+    if let Some(other_cameras) = other.cameras {
+      let cameras = self.cameras.get_or_insert_with(BTreeMap::new);
+
+      for (name, camera) in other_cameras {
+        cameras.entry(name).or_insert(camera);
+      }
+    }
+
+    self
+  }
+
   /// Converts this packed scene into a usable `Scene`.
-  pub fn build(&self) -> anyhow::Result<Scene> {
+  pub fn build(&self) -> Result<Scene, LoaderError> {
+    self.build_at(0.)
+  }
+
+  /// Converts this packed scene into a usable `Scene`, with animated objects posed at the given time.
+  pub fn build_at(&self, time: f64) -> Result<Scene, LoaderError> {
     let mut scene = Scene::new();
 
+    if let Some(ambient) = self.ambient {
+      scene.set_ambient_color(ambient.into());
+    }
+
+    if let Some(background) = self.background {
+      scene.set_background_color(background.into());
+    }
+
     for light in &self.lights {
       scene.add_light(light.build());
     }
 
     for object in &self.objects {
-      scene.add_object_boxed(object.build());
+      scene.add_object_boxed(object.build_at(time)?);
     }
 
     Ok(scene)
   }
+
+  /// Lists the names of the cameras described by this scene.
+  pub fn camera_names(&self) -> impl Iterator<Item=&str> {
+    self.cameras.iter().flat_map(|cameras| cameras.keys().map(String::as_str))
+  }
+
+  /// Builds the named camera described by this scene.
+  pub fn build_camera(&self, name: &str) -> Result<Camera, LoaderError> {
+    self.cameras
+      .as_ref()
+      .and_then(|cameras| cameras.get(name))
+      .map(|camera| camera.build())
+      .ok_or_else(|| LoaderError::UnknownCamera(name.to_string()))
+  }
 }
 
 #[cfg(test)]
 mod tests {
+  use crate::maths::{BoundingBox, point, Ray};
+
   use super::*;
 
   #[test]
@@ -209,5 +597,366 @@ mod tests {
 
     assert_eq!(packed.lights.len(), 1);
     assert_eq!(packed.objects.len(), 4);
+
+    let scene = packed.build().unwrap();
+    let objects = scene.objects();
+
+    // the floor is an untransformed, reflective plane
+    assert_eq!(objects[0].normal_at(point(3., 0., -2.)), vec3(0., 1., 0.));
+    assert_eq!(objects[0].material().reflectivity, 0.7);
+
+    // the spheres are placed and scaled as described, and curve like spheres do
+    assert_eq!(objects[1].bounds(), BoundingBox::new(point(-1.5, 0., -0.5), point(0.5, 2., 1.5)));
+    assert_eq!(objects[2].bounds(), BoundingBox::new(point(-1.83, 0., -1.08), point(-1.17, 0.66, -0.42)));
+    assert_eq!(objects[3].bounds(), BoundingBox::new(point(1., 0., -1.), point(2., 1., 0.)));
+    assert_eq!(objects[3].normal_at(point(1.5 + 0.5 / 2f64.sqrt(), 0.5 + 0.5 / 2f64.sqrt(), -0.5)), vec3(1., 1., 0.).normalize());
+    assert_eq!(objects[1].material().texture.sample_at(Point::ORIGIN), rgb(0.8, 0.1, 1.));
+  }
+
+  #[test]
+  fn packed_scene_can_load_from_yaml_str() {
+    let yaml = r#"
+lights:
+  - position: [ -10, 10, -10 ]
+    color: [ 1, 1, 1 ]
+objects:
+  - kind: sphere
+  - kind: cube
+"#;
+
+    let packed = PackedScene::from_yaml_str(yaml).unwrap();
+
+    assert_eq!(packed.lights.len(), 1);
+    assert_eq!(packed.objects.len(), 2);
+  }
+
+  #[test]
+  fn packed_scenes_can_be_merged() {
+    let library = r#"
+lights:
+  - position: [ 10, 10, -10 ]
+    color: [ 0.5, 0.5, 0.5 ]
+objects:
+  - kind: cube
+cameras:
+  front:
+    from: [ 0, 0, -10 ]
+    to: [ 0, 0, 0 ]
+  side:
+    from: [ 10, 0, 0 ]
+    to: [ 0, 0, 0 ]
+"#;
+
+    let base = PackedScene::from_yaml_file("assets/scenes/test01.yaml").unwrap();
+    let library = PackedScene::from_yaml_str(library).unwrap();
+
+    let merged = base.merge(library);
+    let scene = merged.build().unwrap();
+
+    assert_eq!(scene.lights().len(), 2);
+    assert_eq!(scene.objects().len(), 5);
+    assert!(merged.camera_names().any(|name| name == "side"));
+  }
+
+  #[test]
+  fn packed_scene_can_build_named_cameras() {
+    let yaml = r#"
+lights: []
+objects: []
+cameras:
+  front:
+    from: [ 0, 1, -5 ]
+    to: [ 0, 1, 0 ]
+    field_of_view: 60
+  top:
+    from: [ 0, 10, 0 ]
+    to: [ 0, 0, 0 ]
+    up: [ 0, 0, 1 ]
+    field_of_view: 90
+    width: 320
+    height: 240
+"#;
+
+    let packed = PackedScene::from_yaml_str(yaml).unwrap();
+
+    assert_eq!(packed.camera_names().collect::<Vec<_>>(), vec!["front", "top"]);
+
+    let front = packed.build_camera("front").unwrap();
+    let top = packed.build_camera("top").unwrap();
+
+    assert_eq!(front.transform, Matrix4x4::look_at(point(0., 1., -5.), point(0., 1., 0.), vec3(0., 1., 0.)));
+    assert_eq!(top.transform, Matrix4x4::look_at(point(0., 10., 0.), point(0., 0., 0.), vec3(0., 0., 1.)));
+    assert!((front.field_of_view() - 60f64.to_radians()).abs() < 1e-9);
+    assert!((top.field_of_view() - 90f64.to_radians()).abs() < 1e-9);
+    assert_eq!((top.width(), top.height()), (320, 240));
   }
-}
\ No newline at end of file
+
+  #[test]
+  fn packed_scene_rejects_unknown_cameras() {
+    let packed = PackedScene::from_yaml_str("lights: []\nobjects: []\n").unwrap();
+
+    assert!(matches!(packed.build_camera("hero"), Err(LoaderError::UnknownCamera(name)) if name == "hero"));
+  }
+
+  #[test]
+  fn packed_scene_can_interpolate_keyframes() {
+    let yaml = r#"
+lights: []
+objects:
+  - kind: sphere
+    scale: [ 2, 2, 2 ]
+    keyframes:
+      - time: 0
+        position: [ 0, 0, 0 ]
+      - time: 2
+        position: [ 4, 2, 0 ]
+"#;
+
+    let packed = PackedScene::from_yaml_str(yaml).unwrap();
+    let position_at = |time: f64| {
+      let scene = packed.build_at(time).unwrap();
+
+      scene.objects()[0].object_to_world(point(0., 0., 0.))
+    };
+
+    assert_eq!(position_at(0.), point(0., 0., 0.));
+    assert_eq!(position_at(1.), point(2., 1., 0.));
+    assert_eq!(position_at(2.), point(4., 2., 0.));
+    assert_eq!(position_at(5.), point(4., 2., 0.));
+
+    // unkeyed properties keep their static values
+    let scene = packed.build_at(1.).unwrap();
+
+    assert_eq!(scene.objects()[0].object_to_world(point(1., 0., 0.)), point(4., 1., 0.));
+  }
+
+  #[test]
+  fn packed_scene_can_load_from_reader() {
+    let yaml = b"lights: []\nobjects:\n  - kind: sphere\n";
+
+    let packed = PackedScene::from_reader(&yaml[..]).unwrap();
+
+    assert_eq!(packed.objects.len(), 1);
+  }
+
+  #[test]
+  fn packed_scene_reports_missing_files_as_io_errors() {
+    let result = PackedScene::from_yaml_file("assets/scenes/does-not-exist.yaml");
+
+    assert!(matches!(result, Err(LoaderError::Io(_))));
+  }
+
+  #[test]
+  fn packed_scene_reports_missing_texture_images_as_io_errors() {
+    let yaml = "lights: []\nobjects:\n  - kind: sphere\n    material:\n      texture:\n        image:\n          path: does-not-exist.png\n";
+    let packed = PackedScene::from_yaml_str(yaml).unwrap();
+
+    assert!(matches!(packed.build(), Err(LoaderError::Io(_))));
+  }
+
+  #[test]
+  fn packed_scene_reports_malformed_yaml_as_parse_errors() {
+    let path = std::env::temp_dir().join(format!("raytracer_malformed_scene_{}.yaml", std::process::id()));
+    std::fs::write(&path, "lights: [\nobjects: 7").unwrap();
+
+    let result = PackedScene::from_yaml_file(path.to_str().unwrap());
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(matches!(result, Err(LoaderError::Parse(_))));
+  }
+
+  #[test]
+  fn packed_scene_reports_unknown_kinds_and_textures() {
+    let kind = PackedScene::from_yaml_str("lights: []\nobjects:\n  - kind: torus\n").unwrap();
+    let texture = PackedScene::from_yaml_str("lights: []\nobjects:\n  - kind: sphere\n    material:\n      texture:\n        marble: [ 1, 1, 1 ]\n").unwrap();
+
+    assert!(matches!(kind.build(), Err(LoaderError::UnknownKind(kind)) if kind == "torus"));
+    assert!(matches!(texture.build(), Err(LoaderError::UnknownTexture(texture)) if texture == "marble"));
+  }
+
+  #[test]
+  fn packed_scene_reports_malformed_known_kinds_as_parse_errors() {
+    let packed = PackedScene::from_yaml_str("lights: []\nobjects:\n  - kind:\n      cylinder:\n        min: nope\n").unwrap();
+
+    assert!(matches!(packed.build(), Err(LoaderError::Parse(_))));
+  }
+
+  #[test]
+  fn packed_scene_rejects_transforms_that_degenerate_inside_groups() {
+    // each scale inverts on its own, but not once multiplied together
+    let yaml = r#"
+lights: []
+objects:
+  - kind:
+      group:
+        children:
+          - kind: sphere
+            scale: [ 1e-60, 1e-60, 1e-60 ]
+    scale: [ 1e-60, 1e-60, 1e-60 ]
+"#;
+
+    let packed = PackedScene::from_yaml_str(yaml).unwrap();
+
+    assert!(matches!(packed.build(), Err(LoaderError::InvalidTransform)));
+  }
+
+  #[test]
+  fn packed_scene_rejects_degenerate_transforms() {
+    let yaml = "lights: []\nobjects:\n  - kind: sphere\n    scale: [ 1, 0, 1 ]\n";
+    let packed: PackedScene = serde_yaml::from_str(yaml).unwrap();
+
+    assert!(matches!(packed.build(), Err(LoaderError::InvalidTransform)));
+  }
+
+  #[test]
+  fn packed_scene_can_load_additional_primitives() {
+    let yaml = r#"
+lights: []
+objects:
+  - kind: cube
+  - kind:
+      cylinder:
+        min: 0
+        max: 2
+        closed: true
+  - kind:
+      cone:
+        min: -1
+        max: 0
+  - kind:
+      triangle:
+        p1: [ 0, 1, 0 ]
+        p2: [ -1, 0, 0 ]
+        p3: [ 1, 0, 0 ]
+"#;
+
+    let packed: PackedScene = serde_yaml::from_str(yaml).unwrap();
+
+    assert!(matches!(packed.objects[0].kind, Tagged::Known(PackedKind::Cube)));
+    assert!(matches!(packed.objects[1].kind, Tagged::Known(PackedKind::Cylinder { min: Some(_), max: Some(_), closed: Some(true) })));
+    assert!(matches!(packed.objects[2].kind, Tagged::Known(PackedKind::Cone { closed: None, .. })));
+    assert!(matches!(packed.objects[3].kind, Tagged::Known(PackedKind::Triangle { .. })));
+
+    let scene = packed.build().unwrap();
+
+    assert_eq!(scene.objects().len(), 4);
+  }
+
+  #[test]
+  fn packed_scene_can_load_nested_groups() {
+    let yaml = r#"
+lights: []
+objects:
+  - kind:
+      group:
+        children:
+          - kind: sphere
+            position: [ 2, 0, 0 ]
+          - kind: sphere
+            position: [ -2, 0, 0 ]
+    rotation: [ 0, 1.5707963267948966, 0 ]
+"#;
+
+    let packed: PackedScene = serde_yaml::from_str(yaml).unwrap();
+    let scene = packed.build().unwrap();
+
+    assert_eq!(scene.objects().len(), 1);
+
+    // the group rotation swings both spheres from the x axis onto the z axis
+    let ray = Ray::new(point(0., 0., -10.), vec3(0., 0., 1.));
+    let mut hits = scene.objects()[0].intersect(ray);
+
+    hits.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
+
+    assert_eq!(hits.len(), 4);
+    assert_eq!(hits[0].distance, 7.);
+    assert_eq!(hits[1].distance, 9.);
+    assert_eq!(hits[2].distance, 11.);
+    assert_eq!(hits[3].distance, 13.);
+  }
+
+  #[test]
+  fn packed_scene_can_set_ambient_and_background() {
+    let yaml = r#"
+ambient: [ 0.1, 0.1, 0.1 ]
+background: [ 0.2, 0.4, 0.8 ]
+lights: []
+objects:
+  - kind: sphere
+"#;
+
+    let packed: PackedScene = serde_yaml::from_str(yaml).unwrap();
+    let scene = packed.build().unwrap();
+
+    let ray = Ray::new(point(0., 5., -5.), vec3(0., 0., 1.));
+
+    assert_eq!(scene.ambient_color(), rgb(0.1, 0.1, 0.1));
+    assert_eq!(scene.trace(ray), rgb(0.2, 0.4, 0.8));
+  }
+
+  #[test]
+  fn packed_texture_can_load_from_image_file() {
+    let path = std::env::temp_dir().join(format!("raytracer_packed_texture_{}.png", std::process::id()));
+    let image = image::RgbImage::from_fn(2, 2, |x, y| {
+      if x == 0 && y == 0 { image::Rgb([255, 0, 0]) } else { image::Rgb([0, 0, 255]) }
+    });
+
+    image.save(&path).unwrap();
+
+    let yaml = format!("image:\n  path: {}\n  mapping: planar\n", path.display());
+    let packed: PackedTexture = serde_yaml::from_str(&yaml).unwrap();
+    let texture = packed.build().unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(texture.sample_at(point(0.1, 0., 0.9)), Color::RED);
+    assert_eq!(texture.sample_at(point(0.9, 0., 0.1)), Color::BLUE);
+  }
+
+  #[test]
+  fn packed_scene_can_load_obj_meshes() {
+    let directory = std::env::temp_dir().join(format!("raytracer_packed_mesh_{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+
+    std::fs::write(directory.join("quad.mtl"), "newmtl red\nKd 1 0 0\n").unwrap();
+    std::fs::write(
+      directory.join("quad.obj"),
+      "mtllib quad.mtl\nv -1 1 0\nv -1 0 0\nv 1 0 0\nv 1 1 0\nusemtl red\nf 1 2 3 4\n",
+    ).unwrap();
+
+    let yaml = format!(
+      "lights: []\nobjects:\n  - kind:\n      mesh:\n        path: {}\n",
+      directory.join("quad.obj").display()
+    );
+    let scene = serde_yaml::from_str::<PackedScene>(&yaml).unwrap().build().unwrap();
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    assert_eq!(scene.objects().len(), 1);
+
+    let ray = Ray::new(point(0.5, 0.8, -5.), vec3(0., 0., 1.));
+    let hits = scene.objects()[0].intersect(ray);
+
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].object.material().texture.sample_at(Point::ORIGIN), Color::RED);
+  }
+
+  #[test]
+  fn packed_scene_finds_files_relative_to_the_scene_file() {
+    let directory = std::env::temp_dir().join(format!("raytracer_relative_scene_{}", std::process::id()));
+    std::fs::create_dir_all(directory.join("meshes")).unwrap();
+
+    std::fs::write(directory.join("meshes/quad.obj"), "v -1 1 0\nv -1 0 0\nv 1 0 0\nv 1 1 0\nf 1 2 3 4\n").unwrap();
+    image::RgbImage::from_pixel(1, 1, image::Rgb([255, 0, 0])).save(directory.join("red.png")).unwrap();
+    std::fs::write(
+      directory.join("scene.yaml"),
+      "lights: []\nobjects:\n  - kind:\n      mesh:\n        path: meshes/quad.obj\n  - kind: sphere\n    material:\n      texture:\n        image:\n          path: red.png\n",
+    ).unwrap();
+
+    let scene = PackedScene::from_yaml_file(directory.join("scene.yaml").to_str().unwrap()).and_then(|packed| packed.build());
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    let scene = scene.unwrap();
+
+    assert_eq!(scene.objects()[0].intersect(Ray::new(point(0.5, 0.8, -5.), vec3(0., 0., 1.))).len(), 1);
+    assert_eq!(scene.objects()[1].material().texture.sample_at(Point::ORIGIN), Color::RED);
+  }
+}
@@ -0,0 +1,73 @@
+//! Error types for scene and mesh loading.
+
+use std::fmt::{Display, Formatter};
+
+/// An error that occurred whilst loading a scene or mesh.
+#[derive(Debug)]
+pub enum LoaderError {
+  /// A file could not be read.
+  Io(std::io::Error),
+  /// A file was malformed and could not be parsed.
+  Parse(String),
+  /// An object kind was not recognized.
+  UnknownKind(String),
+  /// An object's transform is degenerate and cannot be inverted.
+  InvalidTransform,
+  /// A texture kind was not recognized.
+  UnknownTexture(String),
+  /// A named camera was not described by the scene.
+  UnknownCamera(String),
+}
+
+impl Display for LoaderError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      LoaderError::Io(error) => write!(f, "Failed to read file: {}", error),
+      LoaderError::Parse(message) => write!(f, "Failed to parse: {}", message),
+      LoaderError::UnknownKind(kind) => write!(f, "Unknown object kind '{}'", kind),
+      LoaderError::InvalidTransform => write!(f, "Object transform cannot be inverted"),
+      LoaderError::UnknownTexture(texture) => write!(f, "Unknown texture '{}'", texture),
+      LoaderError::UnknownCamera(camera) => write!(f, "Unknown camera '{}'", camera),
+    }
+  }
+}
+
+impl std::error::Error for LoaderError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      LoaderError::Io(error) => Some(error),
+      _ => None,
+    }
+  }
+}
+
+impl From<std::io::Error> for LoaderError {
+  fn from(error: std::io::Error) -> Self {
+    LoaderError::Io(error)
+  }
+}
+
+impl From<serde_yaml::Error> for LoaderError {
+  fn from(error: serde_yaml::Error) -> Self {
+    LoaderError::Parse(error.to_string())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn loader_error_should_describe_itself() {
+    let error = LoaderError::UnknownKind("torus".to_string());
+
+    assert_eq!(error.to_string(), "Unknown object kind 'torus'");
+  }
+
+  #[test]
+  fn loader_error_should_expose_io_source() {
+    let error = LoaderError::from(std::io::Error::from(std::io::ErrorKind::NotFound));
+
+    assert!(std::error::Error::source(&error).is_some());
+  }
+}
@@ -0,0 +1,538 @@
+//! Loading for Wavefront .obj meshes and their .mtl material libraries.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::graphics::{TexturePattern, UvMapping};
+use crate::loader::LoaderError;
+use crate::maths::{EPSILON, point, Point, rgb, vec3, Vector};
+use crate::scene::{Group, Material, SmoothTriangle, Texture, Triangle};
+
+/// A set of named materials, as declared in a .mtl file.
+pub type MaterialLibrary = HashMap<String, Material>;
+
+/// Loads the .obj file at the given path, resolving `mtllib` references relative to it.
+pub fn parse_obj_file(path: impl AsRef<Path>) -> Result<Group, LoaderError> {
+  let path = path.as_ref();
+  let base_path = path.parent().unwrap_or(Path::new("."));
+
+  let source = std::fs::read_to_string(path)?;
+
+  parse_obj(&source, |name| {
+    let source = std::fs::read_to_string(base_path.join(name))?;
+
+    parse_mtl(&source, base_path)
+  })
+}
+
+/// Parses .obj source into a group of triangles.
+///
+/// Faces with more than three vertices are fan-triangulated. Named groups (`g`) become child
+/// groups, and `mtllib` references are resolved via the given callback, so that `usemtl` can
+/// assign materials to the faces that follow it.
+///
+/// Faces with `vn` normals become smooth triangles. If the file has no normals at all, smooth
+/// vertex normals are computed by averaging the area-weighted normals of adjacent faces.
+pub fn parse_obj(source: &str, mut load_library: impl FnMut(&str) -> Result<MaterialLibrary, LoaderError>) -> Result<Group, LoaderError> {
+  let mut vertices: Vec<Point> = Vec::new();
+  let mut normals: Vec<Vector> = Vec::new();
+  let mut materials = MaterialLibrary::new();
+  let mut material = Material::default();
+
+  // faces are gathered first, since smoothing needs every face before normals are known
+  let mut faces: Vec<Face> = Vec::new();
+  let mut group_count = 0;
+
+  for (index, line) in source.lines().enumerate() {
+    let mut tokens = line.split_whitespace();
+    let line_number = index + 1;
+
+    match tokens.next() {
+      Some("v") => {
+        let [x, y, z] = parse_floats(tokens)
+          .ok_or_else(|| parse_error("Invalid vertex", line_number))?;
+
+        vertices.push(point(x, y, z));
+      }
+      Some("vn") => {
+        let [x, y, z] = parse_floats(tokens)
+          .ok_or_else(|| parse_error("Invalid normal", line_number))?;
+
+        normals.push(vec3(x, y, z).normalize());
+      }
+      Some("f") => {
+        let indices = tokens
+          .map(|token| parse_face_vertex(token, vertices.len(), normals.len()))
+          .collect::<Option<Vec<_>>>()
+          .ok_or_else(|| parse_error("Invalid face", line_number))?;
+
+        if indices.len() < 3 {
+          return Err(parse_error("Face has fewer than three vertices", line_number));
+        }
+
+        for i in 1..indices.len() - 1 {
+          let corners = [indices[0], indices[i], indices[i + 1]];
+
+          faces.push(Face {
+            vertices: corners.map(|(vertex, _)| vertex),
+            normals: corners.map(|(_, normal)| normal),
+            material: material.clone(),
+            group: group_count,
+          });
+        }
+      }
+      Some("g") | Some("o") => {
+        group_count += 1;
+      }
+      Some("mtllib") => {
+        for name in tokens {
+          materials.extend(load_library(name)?);
+        }
+      }
+      Some("usemtl") => {
+        let name = tokens.next().unwrap_or_default();
+
+        material = materials
+          .get(name)
+          .cloned()
+          .ok_or_else(|| parse_error(&format!("Unknown material '{}'", name), line_number))?;
+      }
+      _ => {
+        // ignore comments, texture coordinates and unsupported statements
+      }
+    }
+  }
+
+  let vertex_normals = if normals.is_empty() {
+    Some(compute_vertex_normals(&vertices, &faces))
+  } else {
+    None
+  };
+
+  // group 0 holds faces declared before any named group, which sit directly in the root
+  let mut groups: Vec<Group> = (0..=group_count).map(|_| Group::new()).collect();
+
+  for face in faces {
+    let [p1, p2, p3] = face.vertices.map(|index| vertices[index]);
+
+    let explicit_normals = match face.normals {
+      [Some(n1), Some(n2), Some(n3)] => Some([normals[n1], normals[n2], normals[n3]]),
+      _ => None,
+    };
+    let smooth_normals = explicit_normals.or_else(|| {
+      let vertex_normals = vertex_normals.as_ref()?;
+
+      face.vertices
+        .iter()
+        .map(|&index| vertex_normals[index])
+        .collect::<Option<Vec<_>>>()?
+        .try_into()
+        .ok()
+    });
+
+    let group = &mut groups[face.group];
+
+    match smooth_normals {
+      Some([n1, n2, n3]) => group.add_child(SmoothTriangle::new(p1, p2, p3, n1, n2, n3).with_material(face.material)),
+      None => group.add_child(Triangle::new(p1, p2, p3).with_material(face.material)),
+    }
+  }
+
+  let mut groups = groups.into_iter();
+  let mut root = groups.next().unwrap_or_default();
+
+  for group in groups {
+    root.add_child(group);
+  }
+
+  Ok(root)
+}
+
+/// A single triangulated face of an .obj mesh.
+struct Face {
+  vertices: [usize; 3],
+  normals: [Option<usize>; 3],
+  material: Material,
+  group: usize,
+}
+
+/// Computes a smooth normal for each vertex by summing the normals of the faces around it.
+///
+/// Face normals aren't normalized before summing, so larger faces have more influence. Vertices
+/// that aren't used, or whose faces cancel out, have no normal.
+fn compute_vertex_normals(vertices: &[Point], faces: &[Face]) -> Vec<Option<Vector>> {
+  let mut sums = vec![Vector::ZERO; vertices.len()];
+
+  for face in faces {
+    let [p1, p2, p3] = face.vertices.map(|index| vertices[index]);
+
+    // matches the winding used for flat triangle normals; the magnitude is twice the area
+    let face_normal = (p3 - p1).cross(p2 - p1);
+
+    for index in face.vertices {
+      sums[index] += face_normal;
+    }
+  }
+
+  sums
+    .into_iter()
+    .map(|sum| (sum.magnitude() > EPSILON).then(|| sum.normalize()))
+    .collect()
+}
+
+/// Parses .mtl source into a library of named materials.
+///
+/// Texture paths (`map_Kd`) are resolved relative to the given base path. Triangles don't
+/// carry texture coordinates, so image textures are applied with a planar mapping.
+pub fn parse_mtl(source: &str, base_path: &Path) -> Result<MaterialLibrary, LoaderError> {
+  let mut library = MaterialLibrary::new();
+  let mut current: Option<(String, Material)> = None;
+
+  for (index, line) in source.lines().enumerate() {
+    let mut tokens = line.split_whitespace();
+    let line_number = index + 1;
+
+    let Some(keyword) = tokens.next() else {
+      continue;
+    };
+
+    if keyword == "newmtl" {
+      if let Some((name, material)) = current.take() {
+        library.insert(name, material);
+      }
+
+      let name = tokens.next().unwrap_or_default().to_string();
+
+      current = Some((name, Material::default()));
+      continue;
+    }
+
+    let Some((_, material)) = current.as_mut() else {
+      continue;
+    };
+
+    let invalid = || parse_error(&format!("Invalid '{}' statement", keyword), line_number);
+
+    match keyword {
+      "Kd" => {
+        let [r, g, b] = parse_floats(tokens).ok_or_else(invalid)?;
+
+        material.texture = Texture::Solid(rgb(r, g, b));
+      }
+      "Ks" => {
+        let [r, g, b] = parse_floats(tokens).ok_or_else(invalid)?;
+
+        // the phong model only has a scalar specular term
+        material.specular = (r + g + b) / 3.;
+      }
+      "Ns" => {
+        let [shininess] = parse_floats(tokens).ok_or_else(invalid)?;
+
+        material.shininess = shininess;
+      }
+      "d" => {
+        let [dissolve] = parse_floats(tokens).ok_or_else(invalid)?;
+
+        material.transparency = 1. - dissolve;
+      }
+      "Tr" => {
+        let [transparency] = parse_floats(tokens).ok_or_else(invalid)?;
+
+        material.transparency = transparency;
+      }
+      "Ni" => {
+        let [refractivity] = parse_floats(tokens).ok_or_else(invalid)?;
+
+        material.refractivity = refractivity;
+      }
+      "map_Kd" => {
+        let path = tokens.last().ok_or_else(invalid)?;
+        let pattern = TexturePattern::from_file(base_path.join(path), UvMapping::Planar)
+          .map_err(|error| LoaderError::UnknownTexture(format!("{}: {}", path, error)))?;
+
+        material.texture = Texture::Pattern(Arc::new(pattern));
+      }
+      _ => {
+        // ignore comments and unsupported statements
+      }
+    }
+  }
+
+  if let Some((name, material)) = current.take() {
+    library.insert(name, material);
+  }
+
+  Ok(library)
+}
+
+/// Builds a parse error for the given line.
+fn parse_error(message: &str, line_number: usize) -> LoaderError {
+  LoaderError::Parse(format!("{} on line {}", message, line_number))
+}
+
+/// Parses exactly N floating point values from the given tokens.
+fn parse_floats<'a, const N: usize>(tokens: impl Iterator<Item=&'a str>) -> Option<[f64; N]> {
+  let values = tokens
+    .take(N)
+    .map(|token| token.parse::<f64>().ok())
+    .collect::<Option<Vec<_>>>()?;
+
+  values.try_into().ok()
+}
+
+/// Parses the vertex and optional normal index from a face token like `1/2/3` or `1//3`.
+///
+/// Normal indices that don't resolve are ignored, rather than rejecting the face.
+fn parse_face_vertex(token: &str, vertex_count: usize, normal_count: usize) -> Option<(usize, Option<usize>)> {
+  let mut parts = token.split('/');
+
+  let vertex = parse_index(parts.next()?, vertex_count)?;
+  let normal = parts.nth(1).and_then(|part| parse_index(part, normal_count));
+
+  Some((vertex, normal))
+}
+
+/// Parses a 1-based (or negative, relative) index into a list of the given length.
+fn parse_index(token: &str, count: usize) -> Option<usize> {
+  let index: isize = token.parse().ok()?;
+
+  let resolved = match index {
+    0 => None,
+    i if i > 0 => Some(i as usize - 1),
+    i => count.checked_sub(i.unsigned_abs()),
+  };
+
+  resolved.filter(|&i| i < count)
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::maths::Color;
+
+  use super::*;
+
+  fn no_libraries(name: &str) -> Result<MaterialLibrary, LoaderError> {
+    panic!("Unexpected material library {}", name)
+  }
+
+  #[test]
+  fn obj_parser_ignores_unrecognized_lines() {
+    let source = "There was a young lady named Bright\nwho traveled much faster than light.\n";
+
+    let group = parse_obj(source, no_libraries).unwrap();
+
+    assert!(group.children().is_empty());
+  }
+
+  #[test]
+  fn obj_parser_builds_triangles_from_faces() {
+    let source = "
+      v -1 1 0
+      v -1 0 0
+      v 1 0 0
+      v 1 1 0
+      f 1 2 3
+      f 1 3 4
+    ";
+
+    let group = parse_obj(source, no_libraries).unwrap();
+
+    assert_eq!(group.children().len(), 2);
+  }
+
+  #[test]
+  fn obj_parser_triangulates_polygons() {
+    let source = "
+      v -1 1 0
+      v -1 0 0
+      v 1 0 0
+      v 1 1 0
+      v 0 2 0
+      f 1/1/1 2/2/2 3/3/3 4/4/4 -1
+    ";
+
+    let group = parse_obj(source, no_libraries).unwrap();
+
+    assert_eq!(group.children().len(), 3);
+  }
+
+  #[test]
+  fn obj_parser_places_named_groups_in_child_groups() {
+    let source = "
+      v -1 1 0
+      v -1 0 0
+      v 1 0 0
+      v 1 1 0
+      g FirstGroup
+      f 1 2 3
+      g SecondGroup
+      f 1 3 4
+    ";
+
+    let group = parse_obj(source, no_libraries).unwrap();
+
+    assert_eq!(group.children().len(), 2);
+  }
+
+  #[test]
+  fn obj_parser_rejects_out_of_range_faces() {
+    let source = "
+      v 0 0 0
+      f 1 2 3
+    ";
+
+    assert!(matches!(parse_obj(source, no_libraries), Err(LoaderError::Parse(_))));
+  }
+
+  #[test]
+  fn mtl_parser_reads_material_properties() {
+    let source = "
+      # a comment
+      newmtl glass
+      Kd 0.1 0.2 0.3
+      Ks 0.5 0.5 0.5
+      Ns 50
+      d 0.25
+      Ni 1.5
+    ";
+
+    let library = parse_mtl(source, Path::new(".")).unwrap();
+    let glass = &library["glass"];
+
+    assert_eq!(glass.texture.sample_at(Point::ORIGIN), rgb(0.1, 0.2, 0.3));
+    assert_eq!(glass.specular, 0.5);
+    assert_eq!(glass.shininess, 50.);
+    assert_eq!(glass.transparency, 0.75);
+    assert_eq!(glass.refractivity, 1.5);
+  }
+
+  #[test]
+  fn obj_parser_assigns_materials_from_usemtl() {
+    let obj = "
+      mtllib scene.mtl
+      v -1 1 0
+      v -1 0 0
+      v 1 0 0
+      v 1 1 0
+      f 1 2 3
+      usemtl red
+      f 1 3 4
+      usemtl blue
+      f 2 3 4
+    ";
+    let mtl = "
+      newmtl red
+      Kd 1 0 0
+      Ns 10
+
+      newmtl blue
+      Kd 0 0 1
+      Ns 20
+    ";
+
+    let group = parse_obj(obj, |name| {
+      assert_eq!(name, "scene.mtl");
+
+      parse_mtl(mtl, Path::new("."))
+    }).unwrap();
+
+    let colors: Vec<_> = group
+      .children()
+      .iter()
+      .map(|child| child.material().texture.sample_at(Point::ORIGIN))
+      .collect();
+
+    assert_eq!(colors, vec![Color::WHITE, Color::RED, Color::BLUE]);
+    assert_eq!(group.children()[1].material().shininess, 10.);
+    assert_eq!(group.children()[2].material().shininess, 20.);
+  }
+
+  const OCTAHEDRON: &str = "
+    v 1 0 0
+    v -1 0 0
+    v 0 1 0
+    v 0 -1 0
+    v 0 0 1
+    v 0 0 -1
+    f 1 5 3
+    f 2 3 5
+    f 1 4 5
+    f 1 3 6
+    f 2 5 4
+    f 2 6 3
+    f 1 6 4
+    f 2 4 6
+  ";
+
+  #[test]
+  fn obj_parser_computes_unit_outward_vertex_normals() {
+    let mut vertices = Vec::new();
+    let mut faces = Vec::new();
+
+    for line in OCTAHEDRON.lines() {
+      let mut tokens = line.split_whitespace();
+
+      match tokens.next() {
+        Some("v") => vertices.push(parse_floats(tokens).map(|[x, y, z]| point(x, y, z)).unwrap()),
+        Some("f") => faces.push(Face {
+          vertices: tokens.map(|token| parse_index(token, 6).unwrap()).collect::<Vec<_>>().try_into().unwrap(),
+          normals: [None; 3],
+          material: Material::default(),
+          group: 0,
+        }),
+        _ => {}
+      }
+    }
+
+    let normals = compute_vertex_normals(&vertices, &faces);
+
+    for (vertex, normal) in vertices.iter().zip(normals) {
+      let normal = normal.unwrap();
+      let outward = *vertex - Point::ORIGIN;
+
+      assert!((normal.magnitude() - 1.).abs() < EPSILON);
+      assert!(normal.dot(outward) > 0.9);
+    }
+  }
+
+  #[test]
+  fn obj_parser_smooths_meshes_without_normals() {
+    let group = parse_obj(OCTAHEDRON, no_libraries).unwrap();
+
+    assert_eq!(group.children().len(), 8);
+
+    // at each corner the interpolated normal is the averaged vertex normal, not the face normal
+    let face = &group.children()[0];
+
+    assert_eq!(face.normal_at(point(1., 0., 0.)), vec3(1., 0., 0.));
+    assert_eq!(face.normal_at(point(0., 0., 1.)), vec3(0., 0., 1.));
+    assert_eq!(face.normal_at(point(0., 1., 0.)), vec3(0., 1., 0.));
+  }
+
+  #[test]
+  fn obj_parser_uses_explicit_normals() {
+    let source = "
+      v 0 1 0
+      v -1 0 0
+      v 1 0 0
+      vn -1 0 0
+      vn 1 0 0
+      vn 0 1 0
+      f 1//3 2//1 3//2
+    ";
+
+    let group = parse_obj(source, no_libraries).unwrap();
+    let face = &group.children()[0];
+
+    assert_eq!(face.normal_at(point(0., 1., 0.)), vec3(0., 1., 0.));
+    assert_eq!(face.normal_at(point(-1., 0., 0.)), vec3(-1., 0., 0.));
+  }
+
+  #[test]
+  fn obj_parser_rejects_unknown_materials() {
+    let source = "usemtl missing";
+
+    assert!(parse_obj(source, no_libraries).is_err());
+  }
+}
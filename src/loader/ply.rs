@@ -0,0 +1,429 @@
+//! Loading for Stanford .ply meshes, in ASCII or binary form.
+
+use std::path::Path;
+
+use crate::loader::LoaderError;
+use crate::maths::{point, Point, vec3, Vector};
+use crate::scene::{Group, SmoothTriangle, Triangle};
+
+/// Loads the .ply file at the given path.
+pub fn parse_ply_file(path: impl AsRef<Path>) -> Result<Group, LoaderError> {
+  let data = std::fs::read(path)?;
+
+  parse_ply(&data)
+}
+
+/// Parses .ply data into a group of triangles.
+///
+/// Vertices need `x`, `y` and `z` properties; if every vertex also has `nx`, `ny` and `nz`
+/// normals, the faces become smooth triangles. Faces are read from the `vertex_indices` (or
+/// `vertex_index`) list and fan-triangulated. Any other elements and properties are skipped.
+pub fn parse_ply(data: &[u8]) -> Result<Group, LoaderError> {
+  let (header, body) = split_header(data)?;
+  let (format, elements) = parse_header(header)?;
+
+  let mut body = match format {
+    Format::Ascii => {
+      let text = std::str::from_utf8(body).map_err(|_| ply_error("ASCII body is not valid text"))?;
+
+      Body::Ascii(text.split_whitespace())
+    }
+    Format::Binary { little_endian } => Body::Binary { data: body, little_endian },
+  };
+
+  let mut vertices: Vec<Point> = Vec::new();
+  let mut normals: Vec<Option<Vector>> = Vec::new();
+  let mut faces: Vec<Vec<usize>> = Vec::new();
+
+  for element in &elements {
+    for _ in 0..element.count {
+      let mut position = [0.; 3];
+      let mut normal = [None; 3];
+      let mut indices = Vec::new();
+
+      for property in &element.properties {
+        match property.kind {
+          PropertyKind::Scalar(scalar) => {
+            let value = body.read(scalar)?;
+
+            match property.name.as_str() {
+              "x" => position[0] = value,
+              "y" => position[1] = value,
+              "z" => position[2] = value,
+              "nx" => normal[0] = Some(value),
+              "ny" => normal[1] = Some(value),
+              "nz" => normal[2] = Some(value),
+              _ => {}
+            }
+          }
+          PropertyKind::List { count, item } => {
+            let count = body.read(count)?;
+
+            if count < 0. || count.fract() != 0. {
+              return Err(ply_error("Invalid list length"));
+            }
+
+            let is_face_indices = element.name == "face"
+              && (property.name == "vertex_indices" || property.name == "vertex_index");
+
+            for _ in 0..count as usize {
+              let value = body.read(item)?;
+
+              if is_face_indices {
+                indices.push(value);
+              }
+            }
+          }
+        }
+      }
+
+      match element.name.as_str() {
+        "vertex" => {
+          let [x, y, z] = position;
+
+          vertices.push(point(x, y, z));
+          normals.push(match normal {
+            [Some(x), Some(y), Some(z)] => Some(vec3(x, y, z).normalize()),
+            _ => None,
+          });
+        }
+        "face" => {
+          let indices = indices
+            .into_iter()
+            .map(|index| (index >= 0. && index.fract() == 0.).then_some(index as usize))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| ply_error("Invalid face index"))?;
+
+          faces.push(indices);
+        }
+        _ => {
+          // other elements, like edges, aren't needed to build triangles
+        }
+      }
+    }
+  }
+
+  let mut group = Group::new();
+
+  for face in faces {
+    if face.len() < 3 {
+      return Err(ply_error("Face has fewer than three vertices"));
+    }
+
+    if face.iter().any(|&index| index >= vertices.len()) {
+      return Err(ply_error("Face index out of range"));
+    }
+
+    for i in 1..face.len() - 1 {
+      let corners = [face[0], face[i], face[i + 1]];
+      let [p1, p2, p3] = corners.map(|index| vertices[index]);
+
+      match corners.map(|index| normals[index]) {
+        [Some(n1), Some(n2), Some(n3)] => group.add_child(SmoothTriangle::new(p1, p2, p3, n1, n2, n3)),
+        _ => group.add_child(Triangle::new(p1, p2, p3)),
+      }
+    }
+  }
+
+  Ok(group)
+}
+
+/// The encoding of the body of a .ply file.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Format {
+  Ascii,
+  Binary { little_endian: bool },
+}
+
+/// A numeric type used by a .ply property.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Scalar {
+  I8,
+  U8,
+  I16,
+  U16,
+  I32,
+  U32,
+  F32,
+  F64,
+}
+
+impl Scalar {
+  /// Parses a scalar type name, in either its old (`uchar`) or sized (`uint8`) form.
+  fn parse(name: &str) -> Option<Self> {
+    Some(match name {
+      "char" | "int8" => Scalar::I8,
+      "uchar" | "uint8" => Scalar::U8,
+      "short" | "int16" => Scalar::I16,
+      "ushort" | "uint16" => Scalar::U16,
+      "int" | "int32" => Scalar::I32,
+      "uint" | "uint32" => Scalar::U32,
+      "float" | "float32" => Scalar::F32,
+      "double" | "float64" => Scalar::F64,
+      _ => return None,
+    })
+  }
+
+  /// The size of the type in binary .ply files, in bytes.
+  fn size(&self) -> usize {
+    match self {
+      Scalar::I8 | Scalar::U8 => 1,
+      Scalar::I16 | Scalar::U16 => 2,
+      Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+      Scalar::F64 => 8,
+    }
+  }
+}
+
+/// The type of a .ply property; a single value, or a list of values preceded by its length.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum PropertyKind {
+  Scalar(Scalar),
+  List { count: Scalar, item: Scalar },
+}
+
+/// A named property of each item of a .ply element.
+#[derive(Clone, Debug, PartialEq)]
+struct Property {
+  name: String,
+  kind: PropertyKind,
+}
+
+/// A named group of items in a .ply file, such as vertices or faces, and their properties.
+#[derive(Clone, Debug, PartialEq)]
+struct Element {
+  name: String,
+  count: usize,
+  properties: Vec<Property>,
+}
+
+/// Splits the data into the text of the header and the body that follows `end_header`.
+fn split_header(data: &[u8]) -> Result<(&str, &[u8]), LoaderError> {
+  const END_HEADER: &[u8] = b"end_header";
+
+  let end = data
+    .windows(END_HEADER.len())
+    .position(|window| window == END_HEADER)
+    .ok_or_else(|| ply_error("Missing end_header"))?;
+
+  // the body starts after the newline that ends the header, which may be \r\n
+  let mut body_start = end + END_HEADER.len();
+
+  while body_start < data.len() && data[body_start] != b'\n' {
+    body_start += 1;
+  }
+
+  let header = std::str::from_utf8(&data[..end]).map_err(|_| ply_error("Header is not valid text"))?;
+  let body = data.get(body_start + 1..).unwrap_or_default();
+
+  Ok((header, body))
+}
+
+/// Parses the format and element declarations from the header of a .ply file.
+fn parse_header(header: &str) -> Result<(Format, Vec<Element>), LoaderError> {
+  let mut lines = header.lines();
+
+  if lines.next().map(str::trim) != Some("ply") {
+    return Err(ply_error("Missing 'ply' magic number"));
+  }
+
+  let mut format = None;
+  let mut elements: Vec<Element> = Vec::new();
+
+  for line in lines {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+
+    match tokens.as_slice() {
+      ["format", "ascii", _] => format = Some(Format::Ascii),
+      ["format", "binary_little_endian", _] => format = Some(Format::Binary { little_endian: true }),
+      ["format", "binary_big_endian", _] => format = Some(Format::Binary { little_endian: false }),
+      ["element", name, count] => {
+        let count = count.parse().map_err(|_| ply_error(&format!("Invalid count for element '{}'", name)))?;
+
+        elements.push(Element { name: name.to_string(), count, properties: Vec::new() });
+      }
+      ["property", "list", count, item, name] => {
+        let count = Scalar::parse(count).ok_or_else(|| ply_error(&format!("Unknown type '{}'", count)))?;
+        let item = Scalar::parse(item).ok_or_else(|| ply_error(&format!("Unknown type '{}'", item)))?;
+
+        push_property(&mut elements, name, PropertyKind::List { count, item })?;
+      }
+      ["property", scalar, name] => {
+        let scalar = Scalar::parse(scalar).ok_or_else(|| ply_error(&format!("Unknown type '{}'", scalar)))?;
+
+        push_property(&mut elements, name, PropertyKind::Scalar(scalar))?;
+      }
+      ["comment", ..] | ["obj_info", ..] | [] => {}
+      _ => return Err(ply_error(&format!("Unrecognized header line '{}'", line.trim()))),
+    }
+  }
+
+  let format = format.ok_or_else(|| ply_error("Missing format"))?;
+
+  Ok((format, elements))
+}
+
+/// Adds a property to the most recently declared element.
+fn push_property(elements: &mut [Element], name: &str, kind: PropertyKind) -> Result<(), LoaderError> {
+  let element = elements
+    .last_mut()
+    .ok_or_else(|| ply_error(&format!("Property '{}' declared before any element", name)))?;
+
+  element.properties.push(Property { name: name.to_string(), kind });
+
+  Ok(())
+}
+
+/// The body of a .ply file, read one value at a time.
+enum Body<'a> {
+  Ascii(std::str::SplitWhitespace<'a>),
+  Binary { data: &'a [u8], little_endian: bool },
+}
+
+impl Body<'_> {
+  /// Reads the next value of the given type, widened to a float.
+  fn read(&mut self, scalar: Scalar) -> Result<f64, LoaderError> {
+    match self {
+      Body::Ascii(tokens) => {
+        let token = tokens.next().ok_or_else(|| ply_error("Unexpected end of data"))?;
+
+        token.parse().map_err(|_| ply_error(&format!("Invalid value '{}'", token)))
+      }
+      Body::Binary { data, little_endian } => {
+        let size = scalar.size();
+
+        if data.len() < size {
+          return Err(ply_error("Unexpected end of data"));
+        }
+
+        let (bytes, rest) = data.split_at(size);
+        *data = rest;
+
+        // normalize to little endian, so each type only needs converting one way
+        let mut buffer = [0u8; 8];
+        buffer[..size].copy_from_slice(bytes);
+
+        if !*little_endian {
+          buffer[..size].reverse();
+        }
+
+        let [b0, b1, b2, b3, ..] = buffer;
+
+        Ok(match scalar {
+          Scalar::I8 => b0 as i8 as f64,
+          Scalar::U8 => b0 as f64,
+          Scalar::I16 => i16::from_le_bytes([b0, b1]) as f64,
+          Scalar::U16 => u16::from_le_bytes([b0, b1]) as f64,
+          Scalar::I32 => i32::from_le_bytes([b0, b1, b2, b3]) as f64,
+          Scalar::U32 => u32::from_le_bytes([b0, b1, b2, b3]) as f64,
+          Scalar::F32 => f32::from_le_bytes([b0, b1, b2, b3]) as f64,
+          Scalar::F64 => f64::from_le_bytes(buffer),
+        })
+      }
+    }
+  }
+}
+
+/// Builds a parse error for a malformed .ply file.
+fn ply_error(message: &str) -> LoaderError {
+  LoaderError::Parse(format!("{} in .ply file", message))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const CUBE: &str = "ply
+format ascii 1.0
+comment a unit cube, with one quad per side
+element vertex 8
+property float x
+property float y
+property float z
+element face 6
+property list uchar int vertex_indices
+end_header
+-1 -1 -1
+1 -1 -1
+1 1 -1
+-1 1 -1
+-1 -1 1
+1 -1 1
+1 1 1
+-1 1 1
+4 0 1 2 3
+4 5 4 7 6
+4 4 0 3 7
+4 1 5 6 2
+4 3 2 6 7
+4 4 5 1 0
+";
+
+  #[test]
+  fn ply_parser_builds_triangles_from_ascii_cube() {
+    let group = parse_ply(CUBE.as_bytes()).unwrap();
+
+    assert_eq!(group.children().len(), 12);
+  }
+
+  #[test]
+  fn ply_parser_reads_binary_little_endian_data() {
+    let header = "ply
+format binary_little_endian 1.0
+element vertex 4
+property float x
+property float y
+property float z
+property double nx
+property double ny
+property double nz
+element face 1
+property list uchar uint vertex_indices
+end_header
+";
+
+    let mut data = header.as_bytes().to_vec();
+
+    for [x, y] in [[0f32, 0.], [1., 0.], [1., 1.], [0., 1.]] {
+      for value in [x, y, 0.] {
+        data.extend(value.to_le_bytes());
+      }
+      for value in [0f64, 0., 1.] {
+        data.extend(value.to_le_bytes());
+      }
+    }
+
+    data.push(4);
+    for index in [0u32, 1, 2, 3] {
+      data.extend(index.to_le_bytes());
+    }
+
+    let group = parse_ply(&data).unwrap();
+
+    assert_eq!(group.children().len(), 2);
+
+    // the normals are read, and face the opposite way to the winding of the faces
+    assert_eq!(group.children()[0].normal_at(point(0.6, 0.3, 0.)), vec3(0., 0., 1.));
+  }
+
+  #[test]
+  fn ply_parser_reads_big_endian_values() {
+    let mut body = Body::Binary { data: &[0x3f, 0x80, 0x00, 0x00, 0xff, 0xfe], little_endian: false };
+
+    assert_eq!(body.read(Scalar::F32).unwrap(), 1.);
+    assert_eq!(body.read(Scalar::I16).unwrap(), -2.);
+    assert!(body.read(Scalar::U8).is_err());
+  }
+
+  #[test]
+  fn ply_parser_rejects_malformed_files() {
+    let truncated = CUBE.replace("4 4 5 1 0\n", "");
+    let out_of_range = CUBE.replace("4 4 5 1 0", "4 4 5 1 8");
+    let unknown_type = CUBE.replace("property float z", "property quad z");
+
+    assert!(matches!(parse_ply(b"not a ply file"), Err(LoaderError::Parse(_))));
+    assert!(matches!(parse_ply(truncated.as_bytes()), Err(LoaderError::Parse(_))));
+    assert!(matches!(parse_ply(out_of_range.as_bytes()), Err(LoaderError::Parse(_))));
+    assert!(matches!(parse_ply(unknown_type.as_bytes()), Err(LoaderError::Parse(_))));
+  }
+}
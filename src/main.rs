@@ -1,23 +1,131 @@
 //! A fun little Ray Tracer built with Rust.
 
-#![allow(dead_code)]
+use std::time::Instant;
 
-use crate::loader::PackedScene;
+use anyhow::{anyhow, bail, Context};
 
-mod graphics;
-mod maths;
-mod scene;
-mod loader;
+use raytracer::loader::PackedScene;
+use raytracer::scene::Camera;
+
+const USAGE: &str = "usage: raytracer [--scene <path>] [--output <path>] [--width <px>] [--height <px>] [--fov <degrees>] [--samples <n>]";
+
+/// Options for a single render, parsed from the command line.
+#[derive(Clone, Debug, PartialEq)]
+struct Config {
+  scene: String,
+  output: String,
+  width: u32,
+  height: u32,
+  field_of_view: f64,
+  samples: usize,
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Self {
+      scene: "assets/scenes/test01.yaml".to_string(),
+      output: "./output.png".to_string(),
+      width: 1920,
+      height: 1080,
+      field_of_view: 60.,
+      samples: 1,
+    }
+  }
+}
+
+/// Parses the given arguments (excluding the program name) into a render config.
+fn parse_args(args: impl IntoIterator<Item=String>) -> anyhow::Result<Config> {
+  let mut config = Config::default();
+  let mut args = args.into_iter();
+
+  while let Some(flag) = args.next() {
+    let mut value = || args.next().ok_or_else(|| anyhow!("missing value for {}\n{}", flag, USAGE));
+
+    match flag.as_str() {
+      "--scene" => config.scene = value()?,
+      "--output" => config.output = value()?,
+      "--width" => config.width = value()?.parse().context("invalid --width")?,
+      "--height" => config.height = value()?.parse().context("invalid --height")?,
+      "--fov" => config.field_of_view = value()?.parse().context("invalid --fov")?,
+      "--samples" => config.samples = value()?.parse().context("invalid --samples")?,
+      _ => bail!("unknown argument {}\n{}", flag, USAGE),
+    }
+  }
+
+  if config.width == 0 || config.height == 0 {
+    bail!("--width and --height must be positive");
+  }
+
+  Ok(config)
+}
 
 fn main() -> anyhow::Result<()> {
-  use maths::*;
-  use scene::*;
+  let config = parse_args(std::env::args().skip(1))?;
+
+  let start = Instant::now();
+  let scene = PackedScene::from_yaml_file(&config.scene)
+    .and_then(|packed| packed.build())
+    .with_context(|| format!("failed to load scene {}", config.scene))?;
 
-  // lets render a simple scene
-  let scene = PackedScene::from_yaml_file("assets/scenes/test01.yaml")?.build()?;
-  let camera = Camera::new(1920, 1080, PI / 3.);
+  println!("Loaded {} in {:.2?}", config.scene, start.elapsed());
 
-  camera.render(&scene).save_to_png("./output.png")?;
+  let camera = Camera::new(config.width, config.height, config.field_of_view.to_radians())
+    .with_samples(config.samples);
+
+  let start = Instant::now();
+  let (canvas, stats) = camera.render_with_stats(&scene);
+
+  println!("Rendered {}x{} in {:.2?} ({} primary rays)", config.width, config.height, start.elapsed(), stats.primary_rays);
+
+  let invalid = canvas.invalid_pixel_count();
+  if invalid > 0 {
+    eprintln!("Warning: {} pixels had NaN or infinite color values", invalid);
+  }
+
+  canvas.save_to_png(&config.output)?;
 
   Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+  }
+
+  #[test]
+  fn parse_args_should_default_without_arguments() {
+    assert_eq!(parse_args(args(&[])).unwrap(), Config::default());
+  }
+
+  #[test]
+  fn parse_args_should_read_all_options() {
+    let config = parse_args(args(&[
+      "--scene", "scenes/room.yaml",
+      "--output", "room.png",
+      "--width", "640",
+      "--height", "480",
+      "--fov", "45",
+      "--samples", "16",
+    ])).unwrap();
+
+    assert_eq!(config, Config {
+      scene: "scenes/room.yaml".to_string(),
+      output: "room.png".to_string(),
+      width: 640,
+      height: 480,
+      field_of_view: 45.,
+      samples: 16,
+    });
+  }
+
+  #[test]
+  fn parse_args_should_reject_bad_arguments() {
+    assert!(parse_args(args(&["--width"])).is_err());
+    assert!(parse_args(args(&["--width", "wide"])).is_err());
+    assert!(parse_args(args(&["--height", "0"])).is_err());
+    assert!(parse_args(args(&["--colour", "red"])).is_err());
+  }
+}
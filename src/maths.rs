@@ -2,15 +2,23 @@
 
 pub use std::f64::consts::PI as PI;
 
+pub use bounds::*;
 pub use colors::*;
 pub use matrices::*;
+pub use precisions::*;
+pub use quaternions::*;
 pub use rays::*;
+pub use sampling::*;
 pub use transforms::*;
 pub use vectors::*;
 
+mod bounds;
 mod colors;
 mod matrices;
+mod precisions;
+mod quaternions;
 mod rays;
+mod sampling;
 mod transforms;
 mod vectors;
 
@@ -0,0 +1,225 @@
+//! Axis-aligned bounding boxes.
+
+use crate::maths::{EPSILON, Matrix4x4, point, Point, Ray};
+
+/// An axis-aligned box in 3-space, between a minimum and maximum corner.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BoundingBox {
+  pub min: Point,
+  pub max: Point,
+}
+
+impl BoundingBox {
+  /// A box containing nothing; the identity for `union`.
+  pub const EMPTY: Self = Self::new(
+    point(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+    point(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+  );
+
+  /// A box containing all of space.
+  pub const INFINITE: Self = Self::new(
+    point(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+    point(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+  );
+
+  /// Creates a new bounding box between the given corners.
+  pub const fn new(min: Point, max: Point) -> Self {
+    Self { min, max }
+  }
+
+  /// Creates the smallest bounding box containing all of the given points.
+  pub fn from_points(points: impl IntoIterator<Item=Point>) -> Self {
+    points
+      .into_iter()
+      .fold(Self::EMPTY, |bounds, point| bounds.union(Self::new(point, point)))
+  }
+
+  /// Determines if the box contains nothing at all.
+  pub fn is_empty(&self) -> bool {
+    (0..3).any(|axis| self.min[axis] > self.max[axis])
+  }
+
+  /// Determines if the box has a finite extent on every axis.
+  pub fn is_finite(&self) -> bool {
+    (0..3).all(|axis| self.min[axis].is_finite() && self.max[axis].is_finite())
+  }
+
+  /// Creates the smallest bounding box containing both this box and the other.
+  pub fn union(self, other: Self) -> Self {
+    let mut result = self;
+
+    for axis in 0..3 {
+      result.min[axis] = self.min[axis].min(other.min[axis]);
+      result.max[axis] = self.max[axis].max(other.max[axis]);
+    }
+
+    result
+  }
+
+  /// Transforms the box by the given matrix, giving the axis-aligned box around the result.
+  ///
+  /// This bounds the 8 transformed corners, but accumulates each axis separately so that
+  /// infinite extents (e.g. from planes) don't produce NaNs where the matrix has zeroes.
+  pub fn transform(self, matrix: Matrix4x4) -> Self {
+    if self.is_empty() {
+      return Self::EMPTY;
+    }
+
+    let mut result = Self::new(Point::ORIGIN, Point::ORIGIN);
+
+    for row in 0..3 {
+      result.min[row] = matrix[(row, 3)];
+      result.max[row] = matrix[(row, 3)];
+
+      for column in 0..3 {
+        let scale = matrix[(row, column)];
+
+        if scale == 0. {
+          continue;
+        }
+
+        let a = scale * self.min[column];
+        let b = scale * self.max[column];
+
+        result.min[row] += a.min(b);
+        result.max[row] += a.max(b);
+      }
+    }
+
+    result
+  }
+
+  /// Intersects the ray with the box via the slab method.
+  ///
+  /// Returns the distances at which the ray enters and exits the box, which may be negative if
+  /// the box is behind (or around) the ray's origin, or `None` if the ray misses entirely.
+  pub fn intersect(&self, ray: Ray) -> Option<(f64, f64)> {
+    if self.is_empty() {
+      return None;
+    }
+
+    let mut entry = f64::NEG_INFINITY;
+    let mut exit = f64::INFINITY;
+
+    for axis in 0..3 {
+      let (near, far) = Self::check_axis(ray.origin[axis], ray.direction[axis], self.min[axis], self.max[axis])?;
+
+      entry = entry.max(near);
+      exit = exit.min(far);
+    }
+
+    if entry > exit { None } else { Some((entry, exit)) }
+  }
+
+  /// Computes the distances at which the ray crosses the two slabs on a single axis.
+  fn check_axis(origin: f64, direction: f64, min: f64, max: f64) -> Option<(f64, f64)> {
+    if direction.abs() < EPSILON {
+      // a parallel ray is either always between the slabs, or never
+      return if min <= origin && origin <= max {
+        Some((f64::NEG_INFINITY, f64::INFINITY))
+      } else {
+        None
+      };
+    }
+
+    let near = (min - origin) / direction;
+    let far = (max - origin) / direction;
+
+    if near > far { Some((far, near)) } else { Some((near, far)) }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::maths::{ApproxEq, PI, vec3};
+
+  use super::*;
+
+  fn unit_box() -> BoundingBox {
+    BoundingBox::new(point(-1., -1., -1.), point(1., 1., 1.))
+  }
+
+  #[test]
+  fn ray_through_box_center_should_enter_and_exit() {
+    let ray = Ray::new(point(0., 0., -5.), vec3(0., 0., 1.));
+
+    assert_eq!(unit_box().intersect(ray), Some((4., 6.)));
+  }
+
+  #[test]
+  fn ray_from_inside_box_should_enter_behind_origin() {
+    let ray = Ray::new(point(0., 0.5, 0.), vec3(0., 0., 1.));
+
+    assert_eq!(unit_box().intersect(ray), Some((-1., 1.)));
+  }
+
+  #[test]
+  fn ray_grazing_box_face_should_intersect() {
+    let ray = Ray::new(point(0., 1., -5.), vec3(0., 0., 1.));
+
+    assert_eq!(unit_box().intersect(ray), Some((4., 6.)));
+  }
+
+  #[test]
+  fn ray_parallel_to_axis_outside_box_should_miss() {
+    let cases = [
+      Ray::new(point(2., 0., -5.), vec3(0., 0., 1.)),
+      Ray::new(point(0., -2., 0.), vec3(1., 0., 0.)),
+      Ray::new(point(0., 0., 2.), vec3(0., 1., 0.)),
+    ];
+
+    for ray in cases {
+      assert_eq!(unit_box().intersect(ray), None);
+    }
+  }
+
+  #[test]
+  fn diagonal_ray_missing_box_should_miss() {
+    let ray = Ray::new(point(-2., 0., 0.), vec3(0.2673, 0.5345, 0.8018));
+
+    assert_eq!(unit_box().intersect(ray), None);
+  }
+
+  #[test]
+  fn empty_box_should_be_empty_and_never_intersect() {
+    let ray = Ray::new(point(0., 0., -5.), vec3(0., 0., 1.));
+
+    assert!(BoundingBox::EMPTY.is_empty());
+    assert!(!unit_box().is_empty());
+    assert_eq!(BoundingBox::EMPTY.intersect(ray), None);
+  }
+
+  #[test]
+  fn union_should_contain_both_boxes() {
+    let other = BoundingBox::new(point(0., 2., -3.), point(4., 3., 0.));
+
+    assert_eq!(unit_box().union(other), BoundingBox::new(point(-1., -1., -3.), point(4., 3., 1.)));
+    assert_eq!(BoundingBox::EMPTY.union(other), other);
+  }
+
+  #[test]
+  fn box_from_points_should_fit_them_tightly() {
+    let bounds = BoundingBox::from_points([point(1., -2., 0.), point(-1., 3., 2.), point(0., 0., -4.)]);
+
+    assert_eq!(bounds, BoundingBox::new(point(-1., -2., -4.), point(1., 3., 2.)));
+    assert!(BoundingBox::from_points([]).is_empty());
+  }
+
+  #[test]
+  fn transformed_box_should_bound_rotated_corners() {
+    let bounds = unit_box().transform(Matrix4x4::translate(1., 0., 0.) * Matrix4x4::rotate_y(PI / 4.));
+    let extent = 2f64.sqrt();
+
+    assert_eq!(bounds, BoundingBox::new(point(1. - extent, -1., -extent), point(1. + extent, 1., extent)));
+  }
+
+  #[test]
+  fn transformed_infinite_box_should_stay_infinite_without_nans() {
+    let plane = BoundingBox::new(point(f64::NEG_INFINITY, 0., f64::NEG_INFINITY), point(f64::INFINITY, 0., f64::INFINITY));
+    let bounds = plane.transform(Matrix4x4::translate(0., 2., 0.));
+
+    assert!(!bounds.is_finite());
+    assert!(bounds.min.x.is_infinite() && bounds.max.z.is_infinite());
+    assert!(bounds.min.y.is_approx(2.) && bounds.max.y.is_approx(2.));
+  }
+}
@@ -1,6 +1,7 @@
 //! Color types and conversions.
 
-use std::ops::{Add, Mul, Sub};
+use std::fmt::{Display, Formatter};
+use std::ops::{Add, AddAssign, Div, Mul, MulAssign, Sub, SubAssign};
 
 use crate::maths::ApproxEq;
 
@@ -24,6 +25,121 @@ impl Color {
   pub const BLUE: Self = rgb(0., 0., 1.);
   pub const MAGENTA: Self = rgb(1., 0., 1.);
   pub const WHITE: Self = rgb(1., 1., 1.);
+  pub const YELLOW: Self = rgb(1., 1., 0.);
+  pub const CYAN: Self = rgb(0., 1., 1.);
+  pub const ORANGE: Self = rgb(1., 0.5, 0.);
+  pub const PURPLE: Self = rgb(0.5, 0., 1.);
+  pub const PINK: Self = rgb(1., 0.5, 0.75);
+  pub const BROWN: Self = rgb(0.4, 0.2, 0.05);
+  pub const GRAY: Self = Self::gray(0.5);
+  pub const LIGHT_GRAY: Self = Self::gray(0.75);
+  pub const DARK_GRAY: Self = Self::gray(0.25);
+
+  /// Creates a neutral gray with the given value in every channel.
+  pub const fn gray(value: f64) -> Self {
+    rgb(value, value, value)
+  }
+
+  /// Creates a color from a hue in turns [0, 1), and a saturation and value in [0, 1].
+  pub fn from_hsv(hue: f64, saturation: f64, value: f64) -> Self {
+    let sector = hue.rem_euclid(1.) * 6.;
+    let fraction = sector.fract();
+
+    let p = value * (1. - saturation);
+    let q = value * (1. - saturation * fraction);
+    let t = value * (1. - saturation * (1. - fraction));
+
+    match sector as usize {
+      0 => rgb(value, t, p),
+      1 => rgb(q, value, p),
+      2 => rgb(p, value, t),
+      3 => rgb(p, q, value),
+      4 => rgb(t, p, value),
+      _ => rgb(value, p, q),
+    }
+  }
+
+  /// Applies the given function to each channel of the color.
+  pub fn map(self, mut f: impl FnMut(f64) -> f64) -> Self {
+    rgb(f(self.r), f(self.g), f(self.b))
+  }
+
+  /// Clamps each channel to the range [min, max].
+  pub fn clamp(self, min: f64, max: f64) -> Self {
+    self.map(|channel| channel.clamp(min, max))
+  }
+
+  /// Raises each channel to the given power; useful for gamma correction.
+  pub fn powf(self, exponent: f64) -> Self {
+    self.map(|channel| channel.powf(exponent))
+  }
+
+  /// Takes the per-channel minimum of this color and another.
+  pub fn min(self, other: Self) -> Self {
+    rgb(self.r.min(other.r), self.g.min(other.g), self.b.min(other.b))
+  }
+
+  /// Takes the per-channel maximum of this color and another.
+  pub fn max(self, other: Self) -> Self {
+    rgb(self.r.max(other.r), self.g.max(other.g), self.b.max(other.b))
+  }
+
+  /// Determines if every channel is a finite number (not NaN or infinite).
+  pub fn is_finite(&self) -> bool {
+    self.r.is_finite() && self.g.is_finite() && self.b.is_finite()
+  }
+
+  /// Replaces NaN channels with 0 and clamps each channel to [0, 1] for display.
+  pub fn sanitize(self) -> Self {
+    self.map(|channel| if channel.is_nan() { 0. } else { channel.clamp(0., 1.) })
+  }
+
+  /// Computes the perceived brightness of the color, using Rec. 709 weights.
+  pub fn luminance(&self) -> f64 {
+    0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+  }
+
+  /// Converts the color to single-precision [r, g, b] components, for graphics APIs.
+  pub fn as_f32(self) -> [f32; 3] {
+    [self.r as f32, self.g as f32, self.b as f32]
+  }
+
+  /// Converts the color to 8-bit RGBA components, saturating out-of-range values.
+  pub fn to_rgba8(self) -> [u8; 4] {
+    let Color { r, g, b } = self.sanitize();
+
+    [
+      (r * 255.0) as u8,
+      (g * 255.0) as u8,
+      (b * 255.0) as u8,
+      255,
+    ]
+  }
+
+  /// Converts the color to 16-bit RGBA components, saturating out-of-range values.
+  pub fn to_rgba16(self) -> [u16; 4] {
+    let Color { r, g, b } = self.sanitize();
+
+    [
+      (r * 65535.0) as u16,
+      (g * 65535.0) as u16,
+      (b * 65535.0) as u16,
+      65535,
+    ]
+  }
+}
+
+impl Display for Color {
+  /// Formats the color as rgb(r, g, b), honoring any requested precision.
+  fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(formatter, "rgb(")?;
+    Display::fmt(&self.r, formatter)?;
+    write!(formatter, ", ")?;
+    Display::fmt(&self.g, formatter)?;
+    write!(formatter, ", ")?;
+    Display::fmt(&self.b, formatter)?;
+    write!(formatter, ")")
+  }
 }
 
 impl PartialEq for Color {
@@ -85,6 +201,54 @@ impl Mul<f64> for Color {
   }
 }
 
+impl Div for Color {
+  type Output = Self;
+
+  fn div(self, rhs: Self) -> Self::Output {
+    Self {
+      r: self.r / rhs.r,
+      g: self.g / rhs.g,
+      b: self.b / rhs.b,
+    }
+  }
+}
+
+impl Div<f64> for Color {
+  type Output = Self;
+
+  fn div(self, rhs: f64) -> Self::Output {
+    Self {
+      r: self.r / rhs,
+      g: self.g / rhs,
+      b: self.b / rhs,
+    }
+  }
+}
+
+impl AddAssign for Color {
+  fn add_assign(&mut self, rhs: Self) {
+    *self = *self + rhs;
+  }
+}
+
+impl SubAssign for Color {
+  fn sub_assign(&mut self, rhs: Self) {
+    *self = *self - rhs;
+  }
+}
+
+impl MulAssign for Color {
+  fn mul_assign(&mut self, rhs: Self) {
+    *self = *self * rhs;
+  }
+}
+
+impl MulAssign<f64> for Color {
+  fn mul_assign(&mut self, rhs: f64) {
+    *self = *self * rhs;
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -98,6 +262,85 @@ mod tests {
     assert_eq!(color.b, 1.7);
   }
 
+  #[test]
+  fn color_should_create_grays() {
+    assert_eq!(Color::gray(0.5), rgb(0.5, 0.5, 0.5));
+    assert_eq!(Color::gray(0.), Color::BLACK);
+    assert_eq!(Color::gray(1.), Color::WHITE);
+  }
+
+  #[test]
+  fn named_colors_should_have_expected_channels() {
+    assert_eq!(Color::YELLOW, rgb(1., 1., 0.));
+    assert_eq!(Color::CYAN, rgb(0., 1., 1.));
+    assert_eq!(Color::ORANGE, rgb(1., 0.5, 0.));
+    assert_eq!(Color::PURPLE, rgb(0.5, 0., 1.));
+    assert_eq!(Color::GRAY, rgb(0.5, 0.5, 0.5));
+    assert_eq!(Color::LIGHT_GRAY, rgb(0.75, 0.75, 0.75));
+    assert_eq!(Color::DARK_GRAY, rgb(0.25, 0.25, 0.25));
+  }
+
+  #[test]
+  fn color_should_display_as_rgb() {
+    assert_eq!(rgb(1., 0.5, 0.).to_string(), "rgb(1, 0.5, 0)");
+    assert_eq!(format!("{:.2}", rgb(1., 0.5, 0.)), "rgb(1.00, 0.50, 0.00)");
+  }
+
+  #[test]
+  fn color_should_map_each_channel() {
+    assert_eq!(rgb(0.1, 0.2, 0.3).map(|channel| channel * 10.), rgb(1., 2., 3.));
+  }
+
+  #[test]
+  fn color_should_clamp_each_channel() {
+    assert_eq!(rgb(-0.5, 0.5, 1.5).clamp(0., 1.), rgb(0., 0.5, 1.));
+  }
+
+  #[test]
+  fn color_should_raise_each_channel_to_power() {
+    assert_eq!(rgb(0.5, 2., 3.).powf(2.), rgb(0.25, 4., 9.));
+  }
+
+  #[test]
+  fn color_should_take_per_channel_min_and_max() {
+    let a = rgb(0.1, 0.8, 0.5);
+    let b = rgb(0.4, 0.2, 0.5);
+
+    assert_eq!(a.min(b), rgb(0.1, 0.2, 0.5));
+    assert_eq!(a.max(b), rgb(0.4, 0.8, 0.5));
+  }
+
+  #[test]
+  fn color_should_compute_luminance() {
+    assert!(Color::GREEN.luminance().is_approx(0.7152));
+    assert_eq!(Color::WHITE.luminance(), 1.);
+    assert_eq!(Color::BLACK.luminance(), 0.);
+    assert!(Color::GREEN.luminance() > Color::RED.luminance());
+    assert!(Color::RED.luminance() > Color::BLUE.luminance());
+  }
+
+  #[test]
+  fn color_should_convert_to_rgba8() {
+    assert_eq!(rgb(0., 0.5, 1.).to_rgba8(), [0, 127, 255, 255]);
+    assert_eq!(rgb(-1., 2., 0.).to_rgba8(), [0, 255, 0, 255]);
+  }
+
+  #[test]
+  fn color_should_convert_to_rgba16() {
+    assert_eq!(rgb(0., 0.5, 1.).to_rgba16(), [0, 32767, 65535, 65535]);
+    assert_eq!(rgb(-1., 2., 0.).to_rgba16(), [0, 65535, 0, 65535]);
+  }
+
+  #[test]
+  fn color_should_sanitize_non_finite_channels() {
+    let color = rgb(f64::NAN, f64::INFINITY, f64::NEG_INFINITY);
+
+    assert!(!color.is_finite());
+    assert!(Color::WHITE.is_finite());
+    assert_eq!(color.sanitize(), rgb(0., 1., 0.));
+    assert_eq!(rgb(0.5, 2., -1.).sanitize(), rgb(0.5, 1., 0.));
+  }
+
   #[test]
   fn colors_should_add() {
     let a = rgb(0.9, 0.6, 0.75);
@@ -128,4 +371,46 @@ mod tests {
 
     assert_eq!(a * 2., rgb(0.4, 0.6, 0.8));
   }
-}
\ No newline at end of file
+
+  #[test]
+  fn colors_should_divide_by_scalar() {
+    assert_eq!(rgb(0.4, 0.6, 0.8) / 2., rgb(0.2, 0.3, 0.4));
+  }
+
+  #[test]
+  fn colors_should_divide_per_channel() {
+    assert_eq!(rgb(0.4, 0.6, 0.8) / rgb(2., 3., 4.), rgb(0.2, 0.2, 0.2));
+  }
+
+  #[test]
+  fn colors_should_convert_from_hsv() {
+    assert_eq!(Color::from_hsv(0., 1., 1.), Color::RED);
+    assert_eq!(Color::from_hsv(1. / 3., 1., 1.), Color::GREEN);
+    assert_eq!(Color::from_hsv(2. / 3., 1., 1.), Color::BLUE);
+    assert_eq!(Color::from_hsv(1., 1., 1.), Color::RED);
+    assert_eq!(Color::from_hsv(0.5, 0., 0.5), Color::gray(0.5));
+    assert_eq!(Color::from_hsv(1. / 12., 1., 1.), rgb(1., 0.5, 0.));
+  }
+
+  #[test]
+  fn colors_should_accumulate_in_place() {
+    let mut color = Color::BLACK;
+
+    for _ in 0..4 {
+      color += rgb(0.1, 0.2, 0.3);
+    }
+
+    assert_eq!(color, rgb(0.4, 0.8, 1.2));
+
+    color -= rgb(0.4, 0.4, 0.4);
+    color *= 2.;
+    color *= rgb(1., 0.5, 0.);
+
+    assert_eq!(color, rgb(0., 0.4, 0.));
+  }
+
+  #[test]
+  fn color_should_convert_to_f32_components() {
+    assert_eq!(rgb(1., 0.5, 0.25).as_f32(), [1f32, 0.5, 0.25]);
+  }
+}
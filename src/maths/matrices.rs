@@ -1,9 +1,9 @@
 //! Matrix types and utilities.
 
-use std::fmt::{Debug, Formatter};
+use std::fmt::{Debug, Display, Formatter};
 use std::ops::{Index, IndexMut, Mul};
 
-use crate::maths::ApproxEq;
+use crate::maths::{ApproxEq, vec4, Vector};
 
 pub type Matrix2x2 = Matrix<2, 4>;
 pub type Matrix3x3 = Matrix<3, 9>;
@@ -26,6 +26,13 @@ impl<const S: usize, const L: usize> Matrix<S, L> {
     Self { elements: [0.; L] }
   }
 
+  /// Determines if every element of this matrix is bit-for-bit equal to the other's.
+  ///
+  /// Unlike `==`, this doesn't tolerate small differences, so it's safe for reusing results.
+  pub fn exactly_equals(&self, other: &Self) -> bool {
+    self.elements == other.elements
+  }
+
   /// Constructs a matrix from the given elements.
   pub const fn create(elements: &[f64; L]) -> Self {
     Self { elements: *elements }
@@ -45,6 +52,12 @@ impl<const S: usize, const L: usize> Matrix<S, L> {
   }
 }
 
+impl<const S: usize, const L: usize> Default for Matrix<S, L> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
 impl<const S: usize, const L: usize> Debug for Matrix<S, L> {
   /// Formats the matrix in a semi-readable manner.
   fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
@@ -55,7 +68,28 @@ impl<const S: usize, const L: usize> Debug for Matrix<S, L> {
         write!(formatter, "{: >5.2} ", self.elements[x + y * S])?;
       }
 
-      write!(formatter, "]\n")?
+      writeln!(formatter, "]")?
+    }
+
+    Ok(())
+  }
+}
+
+impl<const S: usize, const L: usize> Display for Matrix<S, L> {
+  /// Formats the matrix as rows of right-aligned elements.
+  fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+    for y in 0..S {
+      if y > 0 {
+        writeln!(formatter)?;
+      }
+
+      write!(formatter, "[")?;
+
+      for x in 0..S {
+        write!(formatter, " {: >7.3}", self.elements[x + y * S])?;
+      }
+
+      write!(formatter, " ]")?;
     }
 
     Ok(())
@@ -86,7 +120,8 @@ impl<const S: usize, const L: usize> PartialEq for Matrix<S, L> {
         return false;
       }
     }
-    return true;
+
+    true
   }
 }
 
@@ -127,8 +162,59 @@ impl Matrix4x4 {
     ])
   }
 
+  /// Constructs a matrix from the given rows, top to bottom.
+  pub fn from_rows(rows: [Vector; 4]) -> Self {
+    let mut result = Self::new();
+
+    for (row, vector) in rows.iter().enumerate() {
+      for column in 0..4 {
+        result[(row, column)] = vector[column];
+      }
+    }
+
+    result
+  }
+
+  /// Constructs a matrix from the given columns, left to right.
+  pub fn from_columns(columns: [Vector; 4]) -> Self {
+    Self::from_rows(columns).transpose()
+  }
+
+  /// Retrieves the given row of the matrix.
+  pub fn row(&self, row: usize) -> Vector {
+    vec4(self[(row, 0)], self[(row, 1)], self[(row, 2)], self[(row, 3)])
+  }
+
+  /// Retrieves the given column of the matrix.
+  pub fn column(&self, column: usize) -> Vector {
+    vec4(self[(0, column)], self[(1, column)], self[(2, column)], self[(3, column)])
+  }
+
+  /// Converts the matrix to single-precision elements in column-major order, for graphics APIs.
+  ///
+  /// Each column is laid out contiguously, so the translation sits in elements 12, 13 and 14;
+  /// this is the layout OpenGL, Vulkan and most shader languages expect.
+  pub fn as_f32_array(&self) -> [f32; 16] {
+    let mut result = [0.; 16];
+
+    for column in 0..4 {
+      for row in 0..4 {
+        result[row + column * 4] = self[(row, column)] as f32;
+      }
+    }
+
+    result
+  }
+
+  /// Determines if this matrix is exactly the identity matrix.
+  ///
+  /// Unlike `==`, this doesn't tolerate small differences, so it's safe to skip the transform.
+  pub fn is_identity(&self) -> bool {
+    self.exactly_equals(&Self::IDENTITY)
+  }
+
   /// Computes the sub-matrix of this matrix by removing the given row and column.
-  pub fn to_sub_matrix(&self, row: usize, column: usize) -> Matrix3x3 {
+  pub fn to_sub_matrix(self, row: usize, column: usize) -> Matrix3x3 {
     let mut result = Matrix3x3::new();
     let mut i = 0;
 
@@ -159,7 +245,7 @@ impl Matrix4x4 {
   pub fn cofactor(&self, row: usize, column: usize) -> f64 {
     let minor = self.minor(row, column);
 
-    if (row + column) % 2 == 0 {
+    if (row + column).is_multiple_of(2) {
       minor
     } else {
       -minor
@@ -179,11 +265,27 @@ impl Matrix4x4 {
     result
   }
 
+  /// Determines if this matrix can be inverted, i.e. it doesn't collapse space onto a plane.
+  ///
+  /// Matrices with NaN or infinite elements can't be inverted either.
+  pub fn is_invertible(&self) -> bool {
+    let determinant = self.determinant();
+
+    determinant != 0. && determinant.is_finite()
+  }
+
   /// Inverts this matrix.
   pub fn invert(&self) -> anyhow::Result<Self> {
+    self
+      .try_invert()
+      .ok_or_else(|| anyhow::anyhow!("Cannot invert a matrix with a determinant of 0"))
+  }
+
+  /// Inverts this matrix, or returns `None` if it's singular.
+  pub fn try_invert(&self) -> Option<Self> {
     let determinant = self.determinant();
-    if determinant == 0. {
-      return Err(anyhow::anyhow!("Cannot invert a matrix with a determinant of 0"));
+    if determinant == 0. || !determinant.is_finite() {
+      return None;
     }
 
     let mut result = Self::new();
@@ -194,7 +296,7 @@ impl Matrix4x4 {
       }
     }
 
-    Ok(result)
+    Some(result)
   }
 }
 
@@ -212,7 +314,7 @@ impl Matrix3x3 {
   }
 
   /// Computes the sub-matrix of this matrix by removing the given row and column.
-  pub fn to_sub_matrix(&self, row: usize, column: usize) -> Matrix2x2 {
+  pub fn to_sub_matrix(self, row: usize, column: usize) -> Matrix2x2 {
     let mut result = Matrix2x2::new();
     let mut i = 0;
 
@@ -243,7 +345,7 @@ impl Matrix3x3 {
   pub fn cofactor(&self, row: usize, column: usize) -> f64 {
     let minor = self.minor(row, column);
 
-    if (row + column) % 2 == 0 {
+    if (row + column).is_multiple_of(2) {
       minor
     } else {
       -minor
@@ -292,6 +394,59 @@ mod tests {
 
   use super::*;
 
+  #[test]
+  fn matrix4x4_should_display_aligned_rows() {
+    let expected = "\
+[   1.000   0.000   0.000   0.000 ]
+[   0.000   1.000   0.000   0.000 ]
+[   0.000   0.000   1.000   0.000 ]
+[   0.000   0.000   0.000   1.000 ]";
+
+    assert_eq!(Matrix4x4::IDENTITY.to_string(), expected);
+  }
+
+  #[test]
+  fn matrix4x4_from_identity_rows_is_identity() {
+    let matrix = Matrix4x4::from_rows([
+      vec4(1., 0., 0., 0.),
+      vec4(0., 1., 0., 0.),
+      vec4(0., 0., 1., 0.),
+      vec4(0., 0., 0., 1.),
+    ]);
+
+    assert_eq!(matrix, Matrix4x4::IDENTITY);
+  }
+
+  #[test]
+  fn matrix4x4_rows_and_columns_round_trip() {
+    let matrix = Matrix4x4::create(&[
+      1., 2., 3., 4.,
+      5., 6., 7., 8.,
+      9., 10., 11., 12.,
+      13., 14., 15., 16.,
+    ]);
+
+    assert_eq!(matrix.row(1), vec4(5., 6., 7., 8.));
+    assert_eq!(matrix.column(2), vec4(3., 7., 11., 15.));
+
+    assert_eq!(Matrix4x4::from_rows([0, 1, 2, 3].map(|i| matrix.row(i))), matrix);
+    assert_eq!(Matrix4x4::from_columns([0, 1, 2, 3].map(|i| matrix.column(i))), matrix);
+  }
+
+  #[test]
+  fn matrix4x4_translation_is_last_column() {
+    let matrix = Matrix4x4::translate(5., -3., 2.);
+
+    assert_eq!(matrix.column(3), vec4(5., -3., 2., 1.));
+  }
+
+  #[test]
+  fn matrix2x2_should_display_negative_elements() {
+    let matrix = Matrix2x2::create(&[-1.5, 10., 0.25, -100.]);
+
+    assert_eq!(matrix.to_string(), "[  -1.500  10.000 ]\n[   0.250 -100.000 ]");
+  }
+
   #[test]
   fn matrix2x2_should_construct_from_elements() {
     let matrix = Matrix2x2::create(&[
@@ -420,6 +575,16 @@ mod tests {
     assert_eq!(a * Matrix4x4::IDENTITY, a);
   }
 
+  #[test]
+  fn matrix_identity_check_should_be_exact() {
+    let mut nearly = Matrix4x4::IDENTITY;
+    nearly[(0, 3)] = 1e-9;
+
+    assert!(Matrix4x4::IDENTITY.is_identity());
+    assert!(!nearly.is_identity());
+    assert!(!Matrix4x4::translate(1., 0., 0.).is_identity());
+  }
+
   #[test]
   fn matrix_multiplication_by_tuple_should_be_inert() {
     let a = vec4(1., 2., 3., 4.);
@@ -557,7 +722,11 @@ mod tests {
     ]);
 
     assert_eq!(a.determinant(), 0.);
+    assert!(!a.is_invertible());
     assert!(a.invert().is_err());
+    assert_eq!(a.try_invert(), None);
+    assert!(!Matrix4x4::scale(1., 0., 1.).is_invertible());
+    assert_eq!(Matrix4x4::scale(1., f64::NAN, 1.).try_invert(), None);
   }
 
   #[test]
@@ -572,6 +741,8 @@ mod tests {
     let b = a.invert().expect("Failed to invert matrix");
 
     assert_eq!(a.determinant(), 532.);
+    assert!(a.is_invertible());
+    assert_eq!(a.try_invert(), Some(b));
 
     assert_eq!(a.cofactor(2, 3), -160.);
     assert_eq!(b[(3, 2)], -160. / 532.);
@@ -608,4 +779,22 @@ mod tests {
 
     assert_eq!(c * inverse, a);
   }
-}
\ No newline at end of file
+
+  #[test]
+  fn matrix4x4_should_convert_to_column_major_f32_array() {
+    let matrix = Matrix4x4::create(&[
+      1., 2., 3., 4.,
+      5., 6., 7., 8.,
+      9., 10., 11., 12.,
+      13., 14., 15., 16.,
+    ]);
+
+    assert_eq!(matrix.as_f32_array(), [
+      1f32, 5., 9., 13.,
+      2., 6., 10., 14.,
+      3., 7., 11., 15.,
+      4., 8., 12., 16.,
+    ]);
+    assert_eq!(&Matrix4x4::translate(1., 2., 3.).as_f32_array()[12..], &[1f32, 2., 3., 1.]);
+  }
+}
@@ -0,0 +1,60 @@
+//! Floating point precisions that rendered images can be stored at.
+
+use std::fmt::Debug;
+
+use crate::maths::{Color, rgb};
+
+/// A floating point precision that canvas pixels can be stored at, such as `f32` or `f64`.
+///
+/// Tracing is always done in `f64`; the precision only decides how results are stored, so
+/// large renders can trade a little accuracy for half the memory.
+pub trait StoragePrecision: Copy + Debug + PartialOrd + Send + Sync + 'static {
+  /// The color type stored at this precision.
+  type Color: Copy + Debug + Send + Sync;
+
+  /// Stores the given color at this precision.
+  fn pack(color: Color) -> Self::Color;
+
+  /// Widens the given stored color back to a full precision color.
+  fn unpack(color: Self::Color) -> Color;
+}
+
+impl StoragePrecision for f64 {
+  type Color = Color;
+
+  fn pack(color: Color) -> Self::Color {
+    color
+  }
+
+  fn unpack(color: Self::Color) -> Color {
+    color
+  }
+}
+
+impl StoragePrecision for f32 {
+  type Color = [f32; 3];
+
+  fn pack(color: Color) -> Self::Color {
+    color.as_f32()
+  }
+
+  fn unpack([r, g, b]: Self::Color) -> Color {
+    rgb(r as f64, g as f64, b as f64)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn f32_colors_round_trip_within_single_precision() {
+    let color = rgb(0.1, 1. / 3., 12345.678);
+    let unpacked = f32::unpack(f32::pack(color));
+
+    assert!((unpacked.r - color.r).abs() < 1e-7);
+    assert!((unpacked.g - color.g).abs() < 1e-7);
+    assert!((unpacked.b - color.b).abs() < 1e-3);
+    assert_eq!(std::mem::size_of::<<f32 as StoragePrecision>::Color>() * 2, std::mem::size_of::<Color>());
+  }
+}
@@ -0,0 +1,238 @@
+//! Quaternion types for representing rotations.
+
+use std::ops::Mul;
+
+use crate::maths::{ApproxEq, Matrix4x4, Vector};
+
+/// A quaternion in (x, y, z, w) form, used to represent rotations in 3-space.
+#[derive(Copy, Clone, Debug)]
+pub struct Quaternion {
+  pub x: f64,
+  pub y: f64,
+  pub z: f64,
+  pub w: f64,
+}
+
+impl Quaternion {
+  pub const IDENTITY: Self = Self::new(0., 0., 0., 1.);
+
+  /// Creates a new quaternion with the given components.
+  pub const fn new(x: f64, y: f64, z: f64, w: f64) -> Self {
+    Self { x, y, z, w }
+  }
+
+  /// Creates a rotation of the given angle (in radians) about the given axis.
+  pub fn from_axis_angle(axis: Vector, angle: f64) -> Self {
+    let axis = axis.normalize();
+    let (sin, cos) = (angle / 2.).sin_cos();
+
+    Self::new(axis.x * sin, axis.y * sin, axis.z * sin, cos)
+  }
+
+  /// Extracts the rotation from the upper 3x3 of the given matrix.
+  ///
+  /// The upper 3x3 is assumed to be orthonormal (a pure rotation).
+  pub fn from_rotation_matrix(matrix: &Matrix4x4) -> Self {
+    let m = |row: usize, column: usize| matrix[(row, column)];
+    let trace = m(0, 0) + m(1, 1) + m(2, 2);
+
+    // pick the largest diagonal term to keep the division numerically stable
+    let result = if trace > 0. {
+      let s = (trace + 1.).sqrt() * 2.;
+
+      Self::new(
+        (m(2, 1) - m(1, 2)) / s,
+        (m(0, 2) - m(2, 0)) / s,
+        (m(1, 0) - m(0, 1)) / s,
+        s / 4.,
+      )
+    } else if m(0, 0) > m(1, 1) && m(0, 0) > m(2, 2) {
+      let s = (1. + m(0, 0) - m(1, 1) - m(2, 2)).sqrt() * 2.;
+
+      Self::new(
+        s / 4.,
+        (m(0, 1) + m(1, 0)) / s,
+        (m(0, 2) + m(2, 0)) / s,
+        (m(2, 1) - m(1, 2)) / s,
+      )
+    } else if m(1, 1) > m(2, 2) {
+      let s = (1. + m(1, 1) - m(0, 0) - m(2, 2)).sqrt() * 2.;
+
+      Self::new(
+        (m(0, 1) + m(1, 0)) / s,
+        s / 4.,
+        (m(1, 2) + m(2, 1)) / s,
+        (m(0, 2) - m(2, 0)) / s,
+      )
+    } else {
+      let s = (1. + m(2, 2) - m(0, 0) - m(1, 1)).sqrt() * 2.;
+
+      Self::new(
+        (m(0, 2) + m(2, 0)) / s,
+        (m(1, 2) + m(2, 1)) / s,
+        s / 4.,
+        (m(1, 0) - m(0, 1)) / s,
+      )
+    };
+
+    result.normalize()
+  }
+
+  /// Computes the magnitude of this quaternion.
+  pub fn magnitude(&self) -> f64 {
+    (self.x * self.x + self.y * self.y + self.z * self.z + self.w * self.w).sqrt()
+  }
+
+  /// Normalizes the quaternion to unit length.
+  pub fn normalize(&self) -> Self {
+    let magnitude = self.magnitude();
+
+    Self::new(
+      self.x / magnitude,
+      self.y / magnitude,
+      self.z / magnitude,
+      self.w / magnitude,
+    )
+  }
+
+  /// Computes the dot product of this quaternion and another.
+  pub fn dot(&self, other: Self) -> f64 {
+    self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
+  }
+
+  /// Spherically interpolates between this rotation and another by the given amount.
+  pub fn slerp(&self, other: Self, t: f64) -> Self {
+    let mut other = other;
+    let mut cos = self.dot(other);
+
+    // take the shortest path around the sphere
+    if cos < 0. {
+      other = Self::new(-other.x, -other.y, -other.z, -other.w);
+      cos = -cos;
+    }
+
+    // fall back to linear interpolation for nearly identical rotations
+    let (a, b) = if cos > 0.9995 {
+      (1. - t, t)
+    } else {
+      let theta = cos.acos();
+      let sin = theta.sin();
+
+      (((1. - t) * theta).sin() / sin, (t * theta).sin() / sin)
+    };
+
+    Self::new(
+      self.x * a + other.x * b,
+      self.y * a + other.y * b,
+      self.z * a + other.z * b,
+      self.w * a + other.w * b,
+    ).normalize()
+  }
+
+  /// Converts this (unit) quaternion into a rotation matrix.
+  pub fn to_matrix(self) -> Matrix4x4 {
+    let Self { x, y, z, w } = self;
+
+    Matrix4x4::create(&[
+      1. - 2. * (y * y + z * z), 2. * (x * y - z * w), 2. * (x * z + y * w), 0.,
+      2. * (x * y + z * w), 1. - 2. * (x * x + z * z), 2. * (y * z - x * w), 0.,
+      2. * (x * z - y * w), 2. * (y * z + x * w), 1. - 2. * (x * x + y * y), 0.,
+      0., 0., 0., 1.,
+    ])
+  }
+}
+
+impl PartialEq for Quaternion {
+  fn eq(&self, other: &Self) -> bool {
+    // q and -q describe the same rotation, so compare both orientations
+    let same = |sign: f64| {
+      self.x.is_approx(other.x * sign)
+        && self.y.is_approx(other.y * sign)
+        && self.z.is_approx(other.z * sign)
+        && self.w.is_approx(other.w * sign)
+    };
+
+    same(1.) || same(-1.)
+  }
+}
+
+impl Mul for Quaternion {
+  type Output = Self;
+
+  /// Composes two rotations; the right-hand rotation is applied first.
+  fn mul(self, rhs: Self) -> Self::Output {
+    Self::new(
+      self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+      self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+      self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+      self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::maths::{PI, point, vec3};
+
+  use super::*;
+
+  #[test]
+  fn identity_quaternion_is_identity_matrix() {
+    assert_eq!(Quaternion::IDENTITY.to_matrix(), Matrix4x4::IDENTITY);
+  }
+
+  #[test]
+  fn axis_angle_quaternion_matches_rotation_matrices() {
+    let x = Quaternion::from_axis_angle(vec3(1., 0., 0.), PI / 3.);
+    let y = Quaternion::from_axis_angle(vec3(0., 1., 0.), PI / 4.);
+    let z = Quaternion::from_axis_angle(vec3(0., 0., 1.), -PI / 5.);
+
+    assert_eq!(x.to_matrix(), Matrix4x4::rotate_x(PI / 3.));
+    assert_eq!(y.to_matrix(), Matrix4x4::rotate_y(PI / 4.));
+    assert_eq!(z.to_matrix(), Matrix4x4::rotate_z(-PI / 5.));
+  }
+
+  #[test]
+  fn quaternion_round_trips_through_rotation_matrix() {
+    let rotations = [
+      Matrix4x4::rotate_x(PI / 2.) * Matrix4x4::rotate_y(PI / 3.),
+      Matrix4x4::rotate_y(PI),
+      Matrix4x4::rotate_z(3. * PI / 4.) * Matrix4x4::rotate_x(-PI / 6.),
+    ];
+
+    for rotation in rotations {
+      let quaternion = Quaternion::from_rotation_matrix(&rotation);
+
+      assert_eq!(quaternion.to_matrix(), rotation);
+    }
+  }
+
+  #[test]
+  fn quaternion_multiplication_composes_rotations() {
+    let a = Quaternion::from_axis_angle(vec3(0., 1., 0.), PI / 2.);
+    let b = Quaternion::from_axis_angle(vec3(1., 0., 0.), PI / 2.);
+
+    let composed = (a * b).to_matrix();
+
+    assert_eq!(composed * point(0., 1., 0.), point(1., 0., 0.));
+    assert_eq!(composed, Matrix4x4::rotate_y(PI / 2.) * Matrix4x4::rotate_x(PI / 2.));
+  }
+
+  #[test]
+  fn quaternion_slerp_interpolates_rotation_angle() {
+    let a = Quaternion::IDENTITY;
+    let b = Quaternion::from_axis_angle(vec3(0., 1., 0.), PI / 2.);
+
+    assert_eq!(a.slerp(b, 0.), a);
+    assert_eq!(a.slerp(b, 1.), b);
+    assert_eq!(a.slerp(b, 0.5), Quaternion::from_axis_angle(vec3(0., 1., 0.), PI / 4.));
+  }
+
+  #[test]
+  fn quaternions_are_equal_to_their_negation() {
+    let a = Quaternion::from_axis_angle(vec3(0., 1., 0.), PI / 2.);
+    let b = Quaternion::new(-a.x, -a.y, -a.z, -a.w);
+
+    assert_eq!(a, b);
+  }
+}
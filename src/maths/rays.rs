@@ -1,16 +1,20 @@
 //! Ray types and arithmetic.
 
+use std::fmt::{Display, Formatter};
 use std::ops::Mul;
 
-use crate::maths::Matrix4x4;
+use crate::maths::{EPSILON, Matrix4x4};
 
 use super::{Point, Vector};
 
 /// A ray is a line segment in 3-space with a starting point and a direction.
+///
+/// Rays also carry the time at which they were cast, within the camera's shutter interval.
 #[derive(Copy, Clone, Debug)]
 pub struct Ray {
   pub origin: Point,
   pub direction: Vector,
+  pub time: f64,
 }
 
 impl Ray {
@@ -19,11 +23,55 @@ impl Ray {
     Self {
       origin,
       direction,
+      time: 0.,
     }
   }
 
+  /// Creates a new ray, rejecting directions that are zero-length or not finite.
+  pub fn try_new(origin: Point, direction: Vector) -> Result<Self, DegenerateRayError> {
+    let ray = Self::new(origin, direction);
+
+    if ray.is_degenerate() {
+      Err(DegenerateRayError)
+    } else {
+      Ok(ray)
+    }
+  }
+
+  /// Determines if this ray has no usable direction, e.g. from two coincident points.
+  pub fn is_degenerate(&self) -> bool {
+    let length = self.direction.magnitude();
+
+    !(length > 0. && length.is_finite())
+  }
+
+  /// Sets the time at which this ray was cast.
+  pub fn with_time(self, time: f64) -> Self {
+    Self { time, ..self }
+  }
+
+  /// Transforms this ray by the given matrix; the same as `matrix * ray`.
+  pub fn transformed_by(self, matrix: Matrix4x4) -> Self {
+    Self {
+      origin: matrix * self.origin,
+      direction: matrix * self.direction,
+      time: self.time,
+    }
+  }
+
+  /// Determines if this ray is bit-for-bit the same as another.
+  fn exactly_equals(&self, other: &Ray) -> bool {
+    let components = |ray: &Ray| [
+      ray.origin.x, ray.origin.y, ray.origin.z,
+      ray.direction.x, ray.direction.y, ray.direction.z, ray.direction.w,
+      ray.time,
+    ];
+
+    components(self) == components(other)
+  }
+
   /// Computes the position of the ray at a given distance from it's origin.
-  pub fn position(&self, distance: f64) -> Vector {
+  pub fn position(&self, distance: f64) -> Point {
     self.origin + self.direction * distance
   }
 }
@@ -33,16 +81,104 @@ impl Mul<Ray> for Matrix4x4 {
 
   /// Transforms a ray by a 4x4 matrix.
   fn mul(self, rhs: Ray) -> Self::Output {
-    Ray {
-      origin: self * rhs.origin,
-      direction: self * rhs.direction,
+    rhs.transformed_by(self)
+  }
+}
+
+/// Remembers the last ray moved into an object's space, so that objects sharing a transform
+/// (like the triangles of a mesh in a group) only transform each ray once.
+#[derive(Clone, Debug, Default)]
+pub struct RayCache {
+  last: Option<(Matrix4x4, Ray, Ray)>,
+  transforms: usize,
+}
+
+impl RayCache {
+  /// Creates a new, empty cache.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Transforms the given ray by the given matrix, reusing the last result if both match it exactly.
+  pub fn transform(&mut self, ray: Ray, matrix: Matrix4x4) -> Ray {
+    if let Some((last_matrix, last_ray, result)) = &self.last {
+      if last_matrix.exactly_equals(&matrix) && last_ray.exactly_equals(&ray) {
+        return *result;
+      }
     }
+
+    let result = ray.transformed_by(matrix);
+
+    self.last = Some((matrix, ray, result));
+    self.transforms += 1;
+
+    result
+  }
+
+  /// The number of rays this cache has actually transformed, rather than reused.
+  pub fn transforms(&self) -> usize {
+    self.transforms
+  }
+}
+
+/// Intersects the given ray with a sphere, for picking and other uses outside of rendering.
+///
+/// Returns the distances along the ray to both crossings of the surface, nearest first, or
+/// nothing if the ray misses. Tangent rays give the same distance twice.
+pub fn intersect_sphere(ray: Ray, center: Point, radius: f64) -> Vec<f64> {
+  let center_to_ray = ray.origin - center;
+
+  let a = ray.direction.dot(ray.direction);
+  let b = 2. * center_to_ray.dot(ray.direction);
+  let c = center_to_ray.dot(center_to_ray) - radius * radius;
+
+  // a zero-length direction leaves no quadratic to solve
+  if a < f64::EPSILON {
+    return Vec::new();
+  }
+
+  let discriminant = b * b - 4. * a * c;
+
+  if discriminant < 0. {
+    return Vec::new();
   }
+
+  vec![
+    (-b - discriminant.sqrt()) / (2. * a),
+    (-b + discriminant.sqrt()) / (2. * a),
+  ]
 }
 
+/// Intersects the given ray with the plane through the given point, for picking and other uses.
+///
+/// Returns the distance along the ray to the plane, which may be negative if the plane is behind
+/// the ray, or nothing if the ray is (nearly) parallel to it.
+pub fn intersect_plane(ray: Ray, point: Point, normal: Vector) -> Option<f64> {
+  let denominator = ray.direction.dot(normal);
+
+  // rays (nearly) parallel to the plane never meet it, or meet it unreliably far away
+  if denominator.abs() < EPSILON {
+    return None;
+  }
+
+  Some((point - ray.origin).dot(normal) / denominator)
+}
+
+/// An error for a ray constructed without a usable direction.
+#[derive(Debug, PartialEq)]
+pub struct DegenerateRayError;
+
+impl Display for DegenerateRayError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "Ray direction must be finite and non-zero")
+  }
+}
+
+impl std::error::Error for DegenerateRayError {}
+
 #[cfg(test)]
 mod tests {
-  use crate::maths::{Matrix4x4, point, Ray, vec3};
+  use crate::maths::{DegenerateRayError, intersect_plane, intersect_sphere, Matrix4x4, point, Ray, RayCache, vec3};
 
   #[test]
   fn ray_should_expose_basic_properties() {
@@ -89,4 +225,74 @@ mod tests {
     assert_eq!(scaled_ray.origin, point(2., 6., 12.));
     assert_eq!(scaled_ray.direction, vec3(0., 3., 0.));
   }
-}
\ No newline at end of file
+
+  #[test]
+  fn ray_should_keep_time_when_transformed() {
+    let ray = Ray::new(point(1., 2., 3.), vec3(0., 1., 0.)).with_time(0.5);
+    let transform = Matrix4x4::translate(3., 4., 5.);
+
+    assert_eq!((transform * ray).time, 0.5);
+  }
+
+  #[test]
+  fn ray_should_reject_degenerate_directions() {
+    let origin = point(1., 2., 3.);
+
+    assert_eq!(Ray::try_new(origin, vec3(0., 0., 0.)).unwrap_err(), DegenerateRayError);
+    assert!(Ray::try_new(origin, vec3(f64::NAN, 0., 1.)).is_err());
+    assert!(Ray::try_new(origin, vec3(f64::INFINITY, 0., 0.)).is_err());
+    assert!(Ray::try_new(origin, vec3(0., 0., 1.)).is_ok());
+  }
+
+  #[test]
+  fn ray_should_intersect_sphere_at_a_center_and_radius() {
+    let ray = Ray::new(point(0., 0., -5.), vec3(0., 0., 1.));
+
+    assert_eq!(intersect_sphere(ray, point(0., 0., 0.), 1.), vec![4., 6.]);
+    assert_eq!(intersect_sphere(ray, point(0., 0., 2.), 2.), vec![5., 9.]);
+    assert_eq!(intersect_sphere(Ray::new(point(0., 1., -5.), vec3(0., 0., 1.)), point(0., 0., 0.), 1.), vec![5., 5.]);
+    assert!(intersect_sphere(Ray::new(point(0., 2., -5.), vec3(0., 0., 1.)), point(0., 0., 0.), 1.).is_empty());
+  }
+
+  #[test]
+  fn ray_should_intersect_plane_through_a_point() {
+    let normal = vec3(0., 1., 0.);
+
+    assert_eq!(intersect_plane(Ray::new(point(0., 1., 0.), vec3(0., -1., 0.)), point(0., 0., 0.), normal), Some(1.));
+    assert_eq!(intersect_plane(Ray::new(point(0., -1., 0.), vec3(0., 1., 0.)), point(0., 0., 0.), normal), Some(1.));
+    assert_eq!(intersect_plane(Ray::new(point(0., 10., 0.), vec3(0., 0., 1.)), point(0., 0., 0.), normal), None);
+    assert_eq!(intersect_plane(Ray::new(point(0., 0., 0.), vec3(1., 0., 0.)), point(3., 7., 0.), vec3(1., 0., 0.)), Some(3.));
+  }
+
+  #[test]
+  fn ray_transformed_by_matches_matrix_multiply() {
+    let ray = Ray::new(point(1., 2., 3.), vec3(0., 1., 0.)).with_time(0.25);
+    let matrix = Matrix4x4::translate(3., 4., 5.) * Matrix4x4::scale(2., 3., 4.);
+
+    let transformed = ray.transformed_by(matrix);
+
+    assert_eq!(transformed.origin, matrix * point(1., 2., 3.));
+    assert_eq!(transformed.direction, matrix * vec3(0., 1., 0.));
+    assert_eq!(transformed.origin, point(5., 10., 17.));
+    assert_eq!(transformed.direction, vec3(0., 3., 0.));
+    assert_eq!(transformed.time, 0.25);
+  }
+
+  #[test]
+  fn ray_cache_reuses_rays_with_the_same_transform() {
+    let mut cache = RayCache::new();
+    let ray = Ray::new(point(1., 2., 3.), vec3(0., 1., 0.));
+    let scale = Matrix4x4::scale(2., 3., 4.);
+
+    let first = cache.transform(ray, scale);
+    let second = cache.transform(ray, scale);
+
+    assert_eq!(cache.transforms(), 1);
+    assert_eq!(first.origin, second.origin);
+
+    cache.transform(ray, Matrix4x4::translate(1., 0., 0.));
+    cache.transform(Ray::new(point(0., 0., 0.), vec3(0., 1., 0.)), Matrix4x4::translate(1., 0., 0.));
+
+    assert_eq!(cache.transforms(), 3);
+  }
+}
@@ -0,0 +1,247 @@
+//! Random sampling tools for stochastic rendering.
+
+use crate::maths::{PI, vec3, Vector};
+
+/// A small, fast and deterministic pseudo-random number generator (xorshift64*).
+///
+/// Renders must be reproducible, so generators are always explicitly seeded.
+#[derive(Clone, Debug)]
+pub struct Random {
+  state: u64,
+}
+
+impl Random {
+  /// Creates a new generator from the given seed.
+  pub fn with_seed(seed: u64) -> Self {
+    // scramble the seed (splitmix64) so that nearby seeds give unrelated sequences
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+
+    // the xorshift state must never be zero
+    Self { state: z.max(1) }
+  }
+
+  /// Generates the next random 64-bit integer.
+  pub fn next_u64(&mut self) -> u64 {
+    self.state ^= self.state >> 12;
+    self.state ^= self.state << 25;
+    self.state ^= self.state >> 27;
+
+    self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+  }
+
+  /// Generates a random number in the range [0, 1).
+  pub fn next_f64(&mut self) -> f64 {
+    // use the top 53 bits for a uniformly distributed mantissa
+    (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+  }
+
+  /// Generates a random number in the range [min, max).
+  pub fn range(&mut self, min: f64, max: f64) -> f64 {
+    min + (max - min) * self.next_f64()
+  }
+}
+
+/// A strategy for placing 2D samples in the unit square, e.g. within a pixel.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Sampler {
+  /// Independent uniform random samples.
+  #[default]
+  Random,
+  /// The low-discrepancy Halton sequence (bases 2 and 3), which covers the square more evenly.
+  Halton,
+}
+
+impl Sampler {
+  /// Generates the sample at the given index of a sequence, in the range [0, 1) on both axes.
+  ///
+  /// Random samples ignore the index; Halton samples ignore the generator, so distinct
+  /// sequences should use distinct ranges of indices.
+  pub fn sample_2d(&self, index: u64, random: &mut Random) -> (f64, f64) {
+    match self {
+      Sampler::Random => (random.next_f64(), random.next_f64()),
+      Sampler::Halton => (halton(index.wrapping_add(1), 2), halton(index.wrapping_add(1), 3)),
+    }
+  }
+}
+
+/// A reconstruction filter that weights each sample by its offset from the pixel center.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Filter {
+  /// Every sample counts equally; a plain average.
+  #[default]
+  Box,
+  /// Weights fall off linearly, reaching zero one pixel away from the center.
+  Tent,
+  /// Weights fall off as a Gaussian with a standard deviation of half a pixel.
+  Gaussian,
+}
+
+impl Filter {
+  /// Computes the unnormalized weight of a sample at the given position in the pixel, in [0, 1).
+  pub fn weight(&self, x: f64, y: f64) -> f64 {
+    let (dx, dy) = (x - 0.5, y - 0.5);
+
+    match self {
+      Filter::Box => 1.,
+      Filter::Tent => (1. - dx.abs()).max(0.) * (1. - dy.abs()).max(0.),
+      Filter::Gaussian => {
+        const SIGMA: f64 = 0.5;
+
+        (-(dx * dx + dy * dy) / (2. * SIGMA * SIGMA)).exp()
+      }
+    }
+  }
+
+  /// Computes the weights of the given samples in a pixel, normalized so they sum to 1.
+  pub fn weights(&self, samples: &[(f64, f64)]) -> Vec<f64> {
+    let weights: Vec<f64> = samples.iter().map(|&(x, y)| self.weight(x, y)).collect();
+    let total: f64 = weights.iter().sum();
+
+    weights.into_iter().map(|weight| weight / total).collect()
+  }
+}
+
+/// Computes the radical inverse of the given index in the given base; the Halton sequence.
+pub fn halton(mut index: u64, base: u64) -> f64 {
+  let mut result = 0.;
+  let mut fraction = 1.;
+
+  while index > 0 {
+    fraction /= base as f64;
+    result += fraction * (index % base) as f64;
+    index /= base;
+  }
+
+  result
+}
+
+/// Maps a point in the unit square to a direction uniformly distributed within a cone.
+///
+/// The cone is centred on the given axis, and spreads by the given half-angle (in radians).
+pub fn sample_cone(axis: Vector, half_angle: f64, u: f64, v: f64) -> Vector {
+  let axis = axis.normalize();
+
+  // uniform in solid angle, rather than angle, so samples don't bunch up around the axis
+  let cos_theta = 1. - u * (1. - half_angle.cos());
+  let sin_theta = (1. - cos_theta * cos_theta).max(0.).sqrt();
+  let phi = 2. * PI * v;
+
+  // any vector that isn't parallel to the axis will do to build a basis around it
+  let helper = if axis.x.abs() < 0.9 { vec3(1., 0., 0.) } else { vec3(0., 1., 0.) };
+  let tangent = axis.cross(helper).normalize();
+  let bitangent = axis.cross(tangent);
+
+  tangent * (sin_theta * phi.cos()) + bitangent * (sin_theta * phi.sin()) + axis * cos_theta
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::maths::ApproxEq;
+
+  use super::*;
+
+  #[test]
+  fn random_should_be_deterministic_for_a_seed() {
+    let mut a = Random::with_seed(42);
+    let mut b = Random::with_seed(42);
+
+    for _ in 0..100 {
+      assert_eq!(a.next_u64(), b.next_u64());
+    }
+  }
+
+  #[test]
+  fn random_should_differ_between_seeds() {
+    let mut a = Random::with_seed(0);
+    let mut b = Random::with_seed(1);
+
+    assert_ne!(a.next_u64(), b.next_u64());
+  }
+
+  #[test]
+  fn random_should_generate_uniform_values_in_range() {
+    let mut random = Random::with_seed(7);
+    let mut buckets = [0; 10];
+
+    for _ in 0..10_000 {
+      let value = random.range(2., 3.);
+
+      assert!((2. ..3.).contains(&value));
+      buckets[((value - 2.) * 10.) as usize] += 1;
+    }
+
+    for count in buckets {
+      assert!((850..1150).contains(&count), "uneven bucket count {}", count);
+    }
+  }
+
+  #[test]
+  fn halton_should_generate_radical_inverses() {
+    let expected = [0.5, 0.25, 0.75, 0.125, 0.625];
+
+    for (index, value) in expected.into_iter().enumerate() {
+      assert_eq!(halton(index as u64 + 1, 2), value);
+    }
+
+    assert_eq!(halton(1, 3), 1. / 3.);
+    assert_eq!(halton(2, 3), 2. / 3.);
+    assert_eq!(halton(3, 3), 1. / 9.);
+  }
+
+  #[test]
+  fn halton_samples_should_cover_square_more_evenly_than_random() {
+    // squared deviation of bucket counts from an even 4x4 spread of 256 samples
+    fn unevenness(sampler: Sampler) -> usize {
+      let mut random = Random::with_seed(7);
+      let mut buckets = [[0usize; 4]; 4];
+
+      for index in 0..256 {
+        let (u, v) = sampler.sample_2d(index, &mut random);
+
+        buckets[(u * 4.) as usize][(v * 4.) as usize] += 1;
+      }
+
+      buckets.iter().flatten().map(|&count| count.abs_diff(16).pow(2)).sum()
+    }
+
+    assert!(unevenness(Sampler::Halton) < unevenness(Sampler::Random));
+  }
+
+  #[test]
+  fn cone_samples_stay_within_the_cone() {
+    let mut random = Random::with_seed(3);
+    let axis = vec3(1., 2., -1.).normalize();
+
+    for _ in 0..1000 {
+      let direction = sample_cone(axis, 0.3, random.next_f64(), random.next_f64());
+
+      assert!(direction.magnitude().is_approx(1.));
+      assert!(direction.angle_between(axis) <= 0.3 + 1e-9);
+    }
+
+    assert_eq!(sample_cone(axis, 0., 0.5, 0.5), axis);
+  }
+
+  #[test]
+  fn gaussian_filter_weights_the_center_most_heavily() {
+    let samples = [(0.5, 0.5), (0.25, 0.5), (0.05, 0.05), (0.95, 0.5)];
+    let weights = Filter::Gaussian.weights(&samples);
+
+    assert!(weights[0] > weights[1]);
+    assert!(weights[1] > weights[3]);
+    assert!(weights[3] > weights[2]);
+    assert!(weights.iter().sum::<f64>().is_approx(1.));
+  }
+
+  #[test]
+  fn box_filter_weights_samples_equally() {
+    let weights = Filter::Box.weights(&[(0.1, 0.1), (0.5, 0.5), (0.9, 0.2), (0.3, 0.7)]);
+
+    for weight in weights {
+      assert!(weight.is_approx(0.25));
+    }
+  }
+}
@@ -1,6 +1,6 @@
 //! Transformation matrices for vectors and points.
 
-use crate::maths::{Matrix4x4, Vector};
+use crate::maths::{Matrix4x4, Point, Quaternion, vec3, vec4, Vector};
 
 impl Matrix4x4 {
   /// Creates a new translation matrix.
@@ -64,25 +64,96 @@ impl Matrix4x4 {
   }
 
   /// Creates a new view transformation that looks at the given point..
-  pub fn look_at(from: Vector, to: Vector, up: Vector) -> Self {
+  pub fn look_at(from: Point, to: Point, up: Vector) -> Self {
     let forward = (to - from).normalize();
     let left = forward.cross(up.normalize());
     let true_up = left.cross(forward);
 
-    let orientation = Self::create(&[
-      left.x, left.y, left.z, 0.0,
-      true_up.x, true_up.y, true_up.z, 0.0,
-      -forward.x, -forward.y, -forward.z, 0.0,
-      0.0, 0.0, 0.0, 1.0,
-    ]);
+    let orientation = Self::from_rows([left, true_up, -forward, vec4(0., 0., 0., 1.)]);
 
     orientation * Self::translate(-from.x, -from.y, -from.z)
   }
+
+  /// Creates an object transform placed at the given point and turned to face another.
+  ///
+  /// The object's local -z axis points at the target, with local +y as close to the given up
+  /// vector as possible. This is the inverse of `look_at`, which transforms the world instead.
+  pub fn face_towards(from: Point, to: Point, up: Vector) -> Self {
+    let forward = (to - from).normalize();
+    let left = forward.cross(up.normalize()).normalize();
+    let true_up = left.cross(forward);
+
+    Self::from_columns([left, true_up, -forward, vec4(from.x, from.y, from.z, 1.)])
+  }
+
+  /// Decomposes this matrix into its translation, rotation and scale components.
+  ///
+  /// This inverts a `translate * rotate * scale` composition, as built by the scene loader.
+  /// Matrices containing shear (or a projection) can't be represented this way; for those
+  /// the result is a best-effort approximation and won't recompose to the original matrix.
+  /// A negative determinant (a mirroring transform) is reported as a negative x scale.
+  pub fn decompose(&self) -> (Vector, Quaternion, Vector) {
+    let translation = vec3(self[(0, 3)], self[(1, 3)], self[(2, 3)]);
+
+    // the length of each basis column is the scale along that axis
+    let column = |index: usize| vec3(self[(0, index)], self[(1, index)], self[(2, index)]);
+
+    let mut scale = vec3(
+      column(0).magnitude(),
+      column(1).magnitude(),
+      column(2).magnitude(),
+    );
+
+    if self.determinant() < 0. {
+      scale.x = -scale.x;
+    }
+
+    // remove the scale to leave a pure rotation
+    let mut rotation = Self::identity();
+
+    for row in 0..3 {
+      for index in 0..3 {
+        rotation[(row, index)] = self[(row, index)] / scale[index];
+      }
+    }
+
+    (translation, Quaternion::from_rotation_matrix(&rotation), scale)
+  }
+
+  /// Interpolates between this transform and another by the given amount.
+  ///
+  /// Both transforms are decomposed, so translation and scale are interpolated linearly and
+  /// rotation spherically; the same limitations on shear as `decompose` apply.
+  pub fn interpolate(&self, other: &Self, t: f64) -> Self {
+    let (from_translation, from_rotation, from_scale) = self.decompose();
+    let (to_translation, to_rotation, to_scale) = other.decompose();
+
+    let translation = from_translation + (to_translation - from_translation) * t;
+    let rotation = from_rotation.slerp(to_rotation, t);
+    let scale = from_scale + (to_scale - from_scale) * t;
+
+    Self::translate(translation.x, translation.y, translation.z)
+      * rotation.to_matrix()
+      * Self::scale(scale.x, scale.y, scale.z)
+  }
+}
+
+/// Transforms an object-space normal to world space by the given normal matrix.
+///
+/// The normal matrix is the inverse-transpose of the object's transform, which keeps normals
+/// perpendicular to the surface under non-uniform scaling. Any translation leaks into w, so it's
+/// zeroed before normalizing.
+pub fn transform_normal(normal_transform: Matrix4x4, object_normal: Vector) -> Vector {
+  let mut world_normal = normal_transform * object_normal;
+
+  world_normal.w = 0.;
+
+  world_normal.normalize()
 }
 
 #[cfg(test)]
 mod tests {
-  use crate::maths::{PI, point, vec3};
+  use crate::maths::{ApproxEq, PI, point, vec3};
 
   use super::*;
 
@@ -268,6 +339,62 @@ mod tests {
     assert_eq!(transform * p, point(15., 0., 7.));
   }
 
+  #[test]
+  fn decompose_recovers_translation_rotation_and_scale() {
+    let rotation = Matrix4x4::rotate_y(PI / 4.) * Matrix4x4::rotate_x(PI / 3.);
+    let transform = Matrix4x4::translate(1., -2., 3.) * rotation * Matrix4x4::scale(2., 3., 4.);
+
+    let (translation, quaternion, scale) = transform.decompose();
+
+    assert_eq!(translation, vec3(1., -2., 3.));
+    assert_eq!(quaternion.to_matrix(), rotation);
+    assert_eq!(scale, vec3(2., 3., 4.));
+  }
+
+  #[test]
+  fn decompose_of_identity_is_inert() {
+    let (translation, rotation, scale) = Matrix4x4::IDENTITY.decompose();
+
+    assert_eq!(translation, vec3(0., 0., 0.));
+    assert_eq!(rotation, Quaternion::IDENTITY);
+    assert_eq!(scale, vec3(1., 1., 1.));
+  }
+
+  #[test]
+  fn decompose_reports_mirroring_as_negative_x_scale() {
+    let transform = Matrix4x4::scale(-2., 1., 1.);
+
+    let (_, rotation, scale) = transform.decompose();
+
+    assert_eq!(rotation, Quaternion::IDENTITY);
+    assert_eq!(scale, vec3(-2., 1., 1.));
+  }
+
+  #[test]
+  fn decompose_of_sheared_matrix_does_not_recompose() {
+    let transform = Matrix4x4::shear(1., 0., 0., 0., 0., 0.);
+
+    let (translation, rotation, scale) = transform.decompose();
+    let recomposed = Matrix4x4::translate(translation.x, translation.y, translation.z)
+      * rotation.to_matrix()
+      * Matrix4x4::scale(scale.x, scale.y, scale.z);
+
+    assert_ne!(recomposed, transform);
+  }
+
+  #[test]
+  fn interpolate_blends_translation_rotation_and_scale() {
+    let from = Matrix4x4::translate(0., 0., 0.);
+    let to = Matrix4x4::translate(4., 2., 0.) * Matrix4x4::rotate_y(PI / 2.) * Matrix4x4::scale(3., 3., 3.);
+
+    assert_eq!(from.interpolate(&to, 0.), from);
+    assert_eq!(from.interpolate(&to, 1.), to);
+    assert_eq!(
+      from.interpolate(&to, 0.5),
+      Matrix4x4::translate(2., 1., 0.) * Matrix4x4::rotate_y(PI / 4.) * Matrix4x4::scale(2., 2., 2.)
+    );
+  }
+
   #[test]
   fn look_at_default_orientation() {
     let from = point(0., 0., 0.);
@@ -316,4 +443,31 @@ mod tests {
       0.000000, 0.00000, 0.00000, 1.00000
     ]));
   }
-}
\ No newline at end of file
+
+  #[test]
+  fn face_towards_points_negative_z_at_target() {
+    let from = point(1., 3., 2.);
+    let to = point(4., -2., 8.);
+    let up = vec3(1., 1., 0.);
+
+    let transform = Matrix4x4::face_towards(from, to, up);
+
+    assert_eq!(transform * point(0., 0., 0.), from);
+    assert_eq!(transform * vec3(0., 0., -1.), (to - from).normalize());
+    assert!((transform * vec3(0., 1., 0.)).dot(to - from).is_approx(0.));
+    assert!((transform * vec3(1., 0., 0.)).magnitude().is_approx(1.));
+  }
+
+  #[test]
+  fn transform_normal_ignores_translation_and_stays_perpendicular() {
+    let transform = Matrix4x4::translate(3., -2., 5.) * Matrix4x4::scale(1., 0.5, 1.);
+    let normal_transform = transform.invert().unwrap().transpose();
+
+    let normal = transform_normal(normal_transform, vec3(1., 1., 0.));
+    let tangent = transform * vec3(1., -1., 0.);
+
+    assert_eq!(normal.w, 0.);
+    assert!(normal.magnitude().is_approx(1.));
+    assert!(normal.dot(tangent).is_approx(0.));
+  }
+}
@@ -1,16 +1,16 @@
 //! Tuple types for points and vectors.
 
-use std::ops::{Add, Div, Index, IndexMut, Mul, Neg, Sub};
+use std::fmt::{Display, Formatter};
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign};
 
 use crate::maths::Matrix4x4;
 
 use super::ApproxEq;
 
-pub type Point = Vector;
-
-/// Creates a new point; an (X, Y, Z) tuple with the W component at 1.
-pub const fn point(x: f64, y: f64, z: f64) -> Vector {
-  Vector { x, y, z, w: 1. }
+/// Creates a new point; an (X, Y, Z) position with an implicit W component of 1.
+pub const fn point(x: f64, y: f64, z: f64) -> Point {
+  Point { x, y, z }
 }
 
 /// Creates a new vector; an (X, Y, Z) tuple with the W component at 0.
@@ -33,21 +33,18 @@ pub struct Vector {
 }
 
 impl Vector {
+  pub const ZERO: Self = vec4(0., 0., 0., 0.);
+
   /// Creates a new vector with the given components.
   pub fn new(x: f64, y: f64, z: f64, w: f64) -> Self {
     Self { x, y, z, w }
   }
 
-  /// Does this vector represent a vector between two points?
+  /// Does this vector represent a direction (W component at 0)?
   pub fn is_vector(&self) -> bool {
     self.w.is_approx(0.)
   }
 
-  /// Does this vector represent a single point in space?
-  pub fn is_point(&self) -> bool {
-    self.w.is_approx(1.)
-  }
-
   /// Computes the magnitude of this vector; the length essentially.
   pub fn magnitude(&self) -> f64 {
     let x2 = self.x * self.x;
@@ -58,6 +55,18 @@ impl Vector {
     (x2 + y2 + z2 + w2).sqrt()
   }
 
+  /// Computes the distance between this vector and another, treating both as positions.
+  pub fn distance(&self, other: Self) -> f64 {
+    (*self - other).magnitude()
+  }
+
+  /// Computes the squared distance between this vector and another; cheaper for comparisons.
+  pub fn distance_squared(&self, other: Self) -> f64 {
+    let delta = *self - other;
+
+    delta.dot(delta)
+  }
+
   /// Normalizes the vector to the range (-1, 1) for all components.
   pub fn normalize(&self) -> Self {
     let magnitude = self.magnitude();
@@ -90,13 +99,51 @@ impl Vector {
     let y = self.z * other.x - self.x * other.z;
     let z = self.x * other.y - self.y * other.x;
 
-    return vec3(x, y, z);
+    vec3(x, y, z)
+  }
+
+  /// Converts the vector to single-precision [x, y, z, w] components, for graphics APIs.
+  pub fn as_f32_array(&self) -> [f32; 4] {
+    [self.x as f32, self.y as f32, self.z as f32, self.w as f32]
   }
 
   /// Reflects a vector about the given normal.
   pub fn reflect(self, normal: Self) -> Self {
     self - normal * 2. * self.dot(normal)
   }
+
+  /// Computes the angle (in radians) between this vector and another.
+  pub fn angle_between(&self, other: Self) -> f64 {
+    // clamp to guard against rounding pushing the cosine outside [-1, 1]
+    let cosine = self.normalize().dot(other.normalize());
+
+    cosine.clamp(-1., 1.).acos()
+  }
+
+  /// Projects this vector onto another, yielding the component parallel to it.
+  pub fn project_onto(&self, other: Self) -> Self {
+    other * (self.dot(other) / other.dot(other))
+  }
+
+  /// Rejects this vector from another, yielding the component perpendicular to it.
+  pub fn reject_from(&self, other: Self) -> Self {
+    *self - self.project_onto(other)
+  }
+}
+
+impl Display for Vector {
+  /// Formats the vector as (x, y, z, w), honoring any requested precision.
+  fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(formatter, "(")?;
+    Display::fmt(&self.x, formatter)?;
+    write!(formatter, ", ")?;
+    Display::fmt(&self.y, formatter)?;
+    write!(formatter, ", ")?;
+    Display::fmt(&self.z, formatter)?;
+    write!(formatter, ", ")?;
+    Display::fmt(&self.w, formatter)?;
+    write!(formatter, ")")
+  }
 }
 
 impl PartialEq for Vector {
@@ -202,6 +249,180 @@ impl Div<f64> for Vector {
   }
 }
 
+impl AddAssign for Vector {
+  fn add_assign(&mut self, rhs: Self) {
+    *self = *self + rhs;
+  }
+}
+
+impl SubAssign for Vector {
+  fn sub_assign(&mut self, rhs: Self) {
+    *self = *self - rhs;
+  }
+}
+
+impl MulAssign<f64> for Vector {
+  fn mul_assign(&mut self, rhs: f64) {
+    *self = *self * rhs;
+  }
+}
+
+impl Sum for Vector {
+  fn sum<I: Iterator<Item=Self>>(iter: I) -> Self {
+    iter.fold(Self::ZERO, |sum, vector| sum + vector)
+  }
+}
+
+/// A single position in 3-space.
+///
+/// Points are distinct from vectors so that only the meaningful operations are possible;
+/// two points can be subtracted to find the vector between them, and a point can be
+/// offset by a vector, but points can't be added together, scaled or normalized.
+///
+/// Adding two points doesn't compile:
+///
+/// ```compile_fail,E0369
+/// use raytracer::maths::point;
+///
+/// let _ = point(1., 2., 3.) + point(4., 5., 6.);
+/// ```
+///
+/// Nor does normalizing a point:
+///
+/// ```compile_fail,E0599
+/// use raytracer::maths::point;
+///
+/// let _ = point(1., 2., 3.).normalize();
+/// ```
+///
+/// Whereas the difference of two points is a vector, which can be:
+///
+/// ```
+/// use raytracer::maths::{point, vec3};
+///
+/// assert_eq!((point(4., 2., 3.) - point(1., 2., 3.)).normalize(), vec3(1., 0., 0.));
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct Point {
+  pub x: f64,
+  pub y: f64,
+  pub z: f64,
+}
+
+impl Point {
+  pub const ORIGIN: Self = point(0., 0., 0.);
+
+  /// Creates a new point with the given components.
+  pub fn new(x: f64, y: f64, z: f64) -> Self {
+    Self { x, y, z }
+  }
+
+  /// Computes the distance between this point and another.
+  pub fn distance(&self, other: Self) -> f64 {
+    (*self - other).magnitude()
+  }
+
+  /// Computes the squared distance between this point and another; cheaper for comparisons.
+  pub fn distance_squared(&self, other: Self) -> f64 {
+    let delta = *self - other;
+
+    delta.dot(delta)
+  }
+}
+
+impl Display for Point {
+  /// Formats the point as (x, y, z), honoring any requested precision.
+  fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(formatter, "(")?;
+    Display::fmt(&self.x, formatter)?;
+    write!(formatter, ", ")?;
+    Display::fmt(&self.y, formatter)?;
+    write!(formatter, ", ")?;
+    Display::fmt(&self.z, formatter)?;
+    write!(formatter, ")")
+  }
+}
+
+impl PartialEq for Point {
+  fn eq(&self, other: &Self) -> bool {
+    // equality for points is approximate by default for the floating point fields.
+    let x = self.x.is_approx(other.x);
+    let y = self.y.is_approx(other.y);
+    let z = self.z.is_approx(other.z);
+
+    x && y && z
+  }
+}
+
+impl Index<usize> for Point {
+  type Output = f64;
+
+  fn index(&self, index: usize) -> &Self::Output {
+    match index {
+      0 => &self.x,
+      1 => &self.y,
+      2 => &self.z,
+      _ => panic!("Index out of range!")
+    }
+  }
+}
+
+impl IndexMut<usize> for Point {
+  fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+    match index {
+      0 => &mut self.x,
+      1 => &mut self.y,
+      2 => &mut self.z,
+      _ => panic!("Index out of range!")
+    }
+  }
+}
+
+impl Sub for Point {
+  type Output = Vector;
+
+  /// The vector from the other point to this one.
+  fn sub(self, rhs: Self) -> Self::Output {
+    vec3(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
+  }
+}
+
+impl Add<Vector> for Point {
+  type Output = Self;
+
+  fn add(self, rhs: Vector) -> Self::Output {
+    point(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
+  }
+}
+
+impl Sub<Vector> for Point {
+  type Output = Self;
+
+  fn sub(self, rhs: Vector) -> Self::Output {
+    point(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
+  }
+}
+
+impl Mul<Point> for Matrix4x4 {
+  type Output = Point;
+
+  /// Transforms a point by a 4x4 affine matrix.
+  fn mul(self, rhs: Point) -> Self::Output {
+    let mut result = point(0., 0., 0.);
+
+    for row in 0..3 {
+      let x = self[(row, 0)] * rhs.x;
+      let y = self[(row, 1)] * rhs.y;
+      let z = self[(row, 2)] * rhs.z;
+      let w = self[(row, 3)];
+
+      result[row] = x + y + z + w;
+    }
+
+    result
+  }
+}
+
 impl Mul<Vector> for Matrix4x4 {
   type Output = Vector;
 
@@ -224,6 +445,8 @@ impl Mul<Vector> for Matrix4x4 {
 
 #[cfg(test)]
 mod tests {
+  use crate::maths::PI;
+
   use super::*;
 
   #[test]
@@ -235,19 +458,15 @@ mod tests {
     assert_eq!(tuple.z, 3.1);
     assert_eq!(tuple.w, 0.0);
     assert!(tuple.is_vector());
-    assert!(!tuple.is_point());
   }
 
   #[test]
   fn vector_should_create_valid_points() {
-    let tuple = point(4.3, -4.2, 3.1);
+    let p = point(4.3, -4.2, 3.1);
 
-    assert_eq!(tuple.x, 4.3);
-    assert_eq!(tuple.y, -4.2);
-    assert_eq!(tuple.z, 3.1);
-    assert_eq!(tuple.w, 1.0);
-    assert!(!tuple.is_vector());
-    assert!(tuple.is_point());
+    assert_eq!(p.x, 4.3);
+    assert_eq!(p.y, -4.2);
+    assert_eq!(p.z, 3.1);
   }
 
   #[test]
@@ -255,14 +474,20 @@ mod tests {
     let a = vec3(3., -2., 5.);
     let b = vec3(-2., 3., 1.);
     let c = vec3(3., -2., 5.);
-    let d = point(3., -2., 5.);
 
     assert_eq!(a, c);
     assert_eq!(c, a);
     assert_ne!(a, b);
     assert_ne!(b, a);
-    assert_ne!(a, d);
-    assert_ne!(b, d);
+  }
+
+  #[test]
+  fn points_should_exhibit_equality() {
+    let a = point(3., -2., 5.);
+    let b = point(-2., 3., 1.);
+
+    assert_eq!(a, point(3., -2., 5.));
+    assert_ne!(a, b);
   }
 
   #[test]
@@ -304,6 +529,22 @@ mod tests {
     assert_eq!(a - b, point(-2., -4., -6.));
   }
 
+  #[test]
+  fn vectors_should_add_to_point() {
+    let a = point(3., 2., 1.);
+    let b = vec3(5., 6., 7.);
+
+    assert_eq!(a + b, point(8., 8., 8.));
+  }
+
+  #[test]
+  fn points_should_transform_by_matrix() {
+    let transform = Matrix4x4::translate(1., 2., 3.) * Matrix4x4::scale(2., 2., 2.);
+
+    assert_eq!(transform * point(1., 1., 1.), point(3., 4., 5.));
+    assert_eq!(transform * Point::ORIGIN, point(1., 2., 3.));
+  }
+
   #[test]
   fn vectors_should_multiply_by_a_scalar() {
     let a = vec4(1., -2., 3., -4.);
@@ -350,6 +591,24 @@ mod tests {
     assert_eq!(14f64.sqrt(), vec3(-1., -2., -3.).magnitude());
   }
 
+  #[test]
+  fn vectors_should_convert_to_f32_arrays() {
+    assert_eq!(vec3(1., -2., 0.5).as_f32_array(), [1f32, -2., 0.5, 0.]);
+    assert_eq!(vec4(1., 2., 3., 1.).as_f32_array(), [1f32, 2., 3., 1.]);
+  }
+
+  #[test]
+  fn vectors_should_compute_distance() {
+    assert_eq!(5., vec3(0., 0., 0.).distance(vec3(3., 4., 0.)));
+    assert_eq!(25., vec3(0., 0., 0.).distance_squared(vec3(3., 4., 0.)));
+  }
+
+  #[test]
+  fn points_should_compute_distance() {
+    assert_eq!(5., point(0., 0., 0.).distance(point(3., 4., 0.)));
+    assert_eq!(25., point(3., 4., 0.).distance_squared(Point::ORIGIN));
+  }
+
   #[test]
   fn vectors_should_normalize_unit_x() {
     assert_eq!(vec3(4., 0., 0.).normalize(), vec3(1., 0., 0.));
@@ -407,4 +666,59 @@ mod tests {
 
     assert_eq!(reflection, vec3(1., 0., 0.));
   }
-}
\ No newline at end of file
+
+  #[test]
+  fn angle_between_perpendicular_vectors_is_right_angle() {
+    let a = vec3(1., 0., 0.);
+    let b = vec3(0., 3., 0.);
+
+    assert!(a.angle_between(b).is_approx(PI / 2.));
+  }
+
+  #[test]
+  fn angle_between_parallel_vectors_is_zero() {
+    let a = vec3(1., 1., 0.);
+    let b = vec3(2., 2., 0.);
+
+    assert!(a.angle_between(b).is_approx(0.));
+    assert!(a.angle_between(-b).is_approx(PI));
+  }
+
+  #[test]
+  fn vectors_should_project_and_reject() {
+    let vector = vec3(1., 1., 0.);
+    let axis = vec3(2., 0., 0.);
+
+    assert_eq!(vector.project_onto(axis), vec3(1., 0., 0.));
+    assert_eq!(vector.reject_from(axis), vec3(0., 1., 0.));
+  }
+
+  #[test]
+  fn vectors_and_points_should_display() {
+    assert_eq!(vec3(1., 2.5, -3.).to_string(), "(1, 2.5, -3, 0)");
+    assert_eq!(format!("{:.1}", vec3(1., 2., 3.)), "(1.0, 2.0, 3.0, 0.0)");
+    assert_eq!(point(1., 2., 3.).to_string(), "(1, 2, 3)");
+  }
+
+  #[test]
+  fn vectors_should_assign_in_place() {
+    let mut vector = vec3(1., 2., 3.);
+
+    vector += vec3(1., 1., 1.);
+    assert_eq!(vector, vec3(2., 3., 4.));
+
+    vector -= vec3(2., 2., 2.);
+    assert_eq!(vector, vec3(0., 1., 2.));
+
+    vector *= 3.;
+    assert_eq!(vector, vec3(0., 3., 6.));
+  }
+
+  #[test]
+  fn vectors_should_sum() {
+    let axes = [vec3(1., 0., 0.), vec3(0., 1., 0.), vec3(0., 0., 1.)];
+
+    assert_eq!(axes.into_iter().sum::<Vector>(), vec4(1., 1., 1., 0.));
+    assert_eq!(std::iter::empty::<Vector>().sum::<Vector>(), Vector::ZERO);
+  }
+}
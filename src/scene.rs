@@ -2,34 +2,82 @@
 
 use std::ops::{Deref, DerefMut};
 
+pub use accumulators::*;
 pub use cameras::*;
+pub use environments::*;
+pub use groups::*;
+pub use instances::*;
 pub use lighting::*;
 pub use materials::*;
 pub use shapes::*;
+pub use stats::*;
 
-use crate::maths::{ApproxEq, Color, Matrix4x4, Point, Ray, Vector};
+use crate::maths::{ApproxEq, BoundingBox, Color, EPSILON, Matrix4x4, PI, point, Point, Random, Ray, RayCache, sample_cone, vec3, Vector};
 
+mod accumulators;
 mod cameras;
+mod environments;
+mod groups;
+mod instances;
 mod lighting;
 mod materials;
 mod shapes;
+mod stats;
 
 /// An object in the scene that can be ray-traced.
-pub trait Traceable {
+pub trait Traceable: Send + Sync {
   /// Returns the material for the object.
   fn material(&self) -> &Material;
 
   /// Calculates the distances of intersection for the given ray.
-  fn intersect(&self, world_ray: Ray) -> HitList;
+  fn intersect(&self, world_ray: Ray) -> HitList<'_>;
+
+  /// Intersects the given ray, reusing rays from the cache that were already moved into the
+  /// same object space. By default the cache is ignored.
+  fn intersect_cached(&self, world_ray: Ray, _cache: &mut RayCache) -> HitList<'_> {
+    self.intersect(world_ray)
+  }
 
   /// Computes the normal vector at a given world point on the surface of the object.
-  fn normal_at(&self, world_point: Vector) -> Vector;
+  fn normal_at(&self, world_point: Point) -> Vector;
+
+  /// Computes the normal vector at a given world point at the given shutter time.
+  ///
+  /// Only moving objects depend on the time; by default this is the same as `normal_at`.
+  fn normal_at_time(&self, world_point: Point, _time: f64) -> Vector {
+    self.normal_at(world_point)
+  }
 
   /// Transforms the given world point to object space.
-  fn world_to_object(&self, world_point: Vector) -> Vector;
+  fn world_to_object(&self, world_point: Point) -> Point;
+
+  /// Transforms the given world point to object space at the given shutter time.
+  ///
+  /// Only moving objects depend on the time; by default this is the same as `world_to_object`.
+  fn world_to_object_at_time(&self, world_point: Point, _time: f64) -> Point {
+    self.world_to_object(world_point)
+  }
 
   /// Transforms the given object point to world space.
-  fn object_to_world(&self, object_point: Vector) -> Vector;
+  fn object_to_world(&self, object_point: Point) -> Point;
+
+  /// Computes the bounds of the object in world space.
+  fn bounds(&self) -> BoundingBox;
+
+  /// Places the object inside a parent space by pre-multiplying the given parent transform.
+  ///
+  /// Panics if the combined transform can't be inverted; see `try_apply_parent_transform`.
+  fn apply_parent_transform(&mut self, parent: Matrix4x4) {
+    self
+      .try_apply_parent_transform(parent)
+      .expect("Failed to apply parent transform");
+  }
+
+  /// Places the object inside a parent space, failing if the combined transform can't be inverted.
+  ///
+  /// Very small scales can multiply out to a singular matrix even when each one is invertible
+  /// alone. A group may have moved some of its children by the time this fails.
+  fn try_apply_parent_transform(&mut self, parent: Matrix4x4) -> anyhow::Result<()>;
 }
 
 /// A node in a scene with associated material and transform.
@@ -38,6 +86,9 @@ pub struct SceneNode<S> {
   material: Material,
   transform: Matrix4x4,
   inverse_transform: Matrix4x4,
+  normal_transform: Matrix4x4,
+  end_transform: Option<Matrix4x4>,
+  is_identity: bool,
 }
 
 impl<S> SceneNode<S> {
@@ -48,23 +99,135 @@ impl<S> SceneNode<S> {
       material: Material::default(),
       transform: Matrix4x4::identity(),
       inverse_transform: Matrix4x4::identity(),
+      normal_transform: Matrix4x4::identity(),
+      end_transform: None,
+      is_identity: true,
     }
   }
 
   /// Sets the transform for this node.
+  ///
+  /// Panics if the transform is singular (e.g. a zero scale), since rays can't be moved into the
+  /// object's space; use `try_with_transform` to handle that case instead.
   pub fn with_transform(self, transform: Matrix4x4) -> Self {
-    // pre-compute the inverse transform
+    self
+      .try_with_transform(transform)
+      .expect("Failed to invert node transform")
+  }
+
+  /// Sets the transform for this node, failing if the transform can't be inverted.
+  pub fn try_with_transform(self, transform: Matrix4x4) -> anyhow::Result<Self> {
+    // pre-compute the inverse transform, and the inverse-transpose for normals
     let inverse_transform = transform
-      .invert()
-      .unwrap_or(Matrix4x4::identity());
+      .try_invert()
+      .ok_or_else(|| anyhow::anyhow!("Cannot transform a node by a singular matrix"))?;
+    let normal_transform = inverse_transform.transpose();
+
+    let is_identity = self.end_transform.is_none() && transform.is_identity();
+
+    Ok(Self { transform, inverse_transform, normal_transform, is_identity, ..self })
+  }
+
+  /// Pre-multiplies the given transform onto this node's transform (`transform * current`).
+  ///
+  /// The given transform is applied after the existing one, in world space.
+  pub fn pre_transform(self, transform: Matrix4x4) -> Self {
+    let end_transform = self.end_transform.map(|end_transform| transform * end_transform);
+    let transform = transform * self.transform;
+
+    Self { end_transform, ..self.with_transform(transform) }
+  }
 
-    Self { transform, inverse_transform, ..self }
+  /// Post-multiplies the given transform onto this node's transform (`current * transform`).
+  ///
+  /// The given transform is applied before the existing one, in object space.
+  pub fn post_transform(self, transform: Matrix4x4) -> Self {
+    let end_transform = self.end_transform.map(|end_transform| end_transform * transform);
+    let transform = self.transform * transform;
+
+    Self { end_transform, ..self.with_transform(transform) }
+  }
+
+  /// Applies the given transform after the existing one; reads naturally when chained.
+  ///
+  /// Equivalent to `pre_transform`, so `.with_transform(scale).then_transform(translate)`
+  /// scales the object and then translates it.
+  pub fn then_transform(self, transform: Matrix4x4) -> Self {
+    self.pre_transform(transform)
+  }
+
+  /// Turns this node in place so that its local -z axis points at the given target.
+  ///
+  /// The node keeps its current position and scale; any existing rotation is replaced.
+  pub fn looking_at(self, target: Point, up: Vector) -> Self {
+    let (translation, _, scale) = self.transform.decompose();
+    let position = Point::ORIGIN + translation;
+
+    let transform = Matrix4x4::face_towards(position, target, up) * Matrix4x4::scale(scale.x, scale.y, scale.z);
+
+    self.with_transform(transform)
   }
 
   /// Sets the material for this node.
   pub fn with_material(self, material: Material) -> Self {
     Self { material, ..self }
   }
+
+  /// Moves this node from its transform to the given end transform over the shutter interval.
+  ///
+  /// Rays cast at time 0 see the start transform and rays cast at time 1 see the end
+  /// transform; times in between interpolate the two, producing motion blur.
+  pub fn with_motion(self, end_transform: Matrix4x4) -> Self {
+    Self { end_transform: Some(end_transform), is_identity: false, ..self }
+  }
+
+  /// The object-to-world transform of this node.
+  pub fn transform(&self) -> Matrix4x4 {
+    self.transform
+  }
+
+  /// The cached world-to-object transform, computed once when the transform is set.
+  pub fn inverse_transform(&self) -> Matrix4x4 {
+    self.inverse_transform
+  }
+
+  /// The cached transform for normals (the inverse-transpose), computed once when the transform is set.
+  pub fn normal_transform(&self) -> Matrix4x4 {
+    self.normal_transform
+  }
+
+  /// Transforms the given world ray into object space at the ray's shutter time.
+  ///
+  /// Static nodes with an identity transform skip the matrix multiplies entirely. Returns `None`
+  /// when a moving node is flattened at the ray's time, and so has no surface to hit.
+  fn object_ray(&self, world_ray: Ray, cache: &mut RayCache) -> Option<Ray> {
+    if self.is_identity {
+      return Some(world_ray);
+    }
+
+    Some(cache.transform(world_ray, self.inverse_transform_at(world_ray.time)?))
+  }
+
+  /// The world-to-object transform at the given shutter time.
+  ///
+  /// Moving nodes can pass through a singular transform (e.g. a scale flipping sign), in which
+  /// case there's no inverse at that time.
+  fn inverse_transform_at(&self, time: f64) -> Option<Matrix4x4> {
+    match self.end_transform {
+      Some(end_transform) => self.transform
+        .interpolate(&end_transform, time.clamp(0., 1.))
+        .try_invert(),
+      None => Some(self.inverse_transform),
+    }
+  }
+
+  /// The world-to-object transform for querying a surface point at the given shutter time.
+  ///
+  /// A flattened node can't be hit, so there's never a surface point to query at such a time;
+  /// the start transform's inverse stands in rather than panicking.
+  fn surface_inverse_transform_at(&self, time: f64) -> Matrix4x4 {
+    self.inverse_transform_at(time).unwrap_or(self.inverse_transform)
+  }
 }
 
 impl<S> Traceable for SceneNode<S> where S: Shape {
@@ -72,48 +235,160 @@ impl<S> Traceable for SceneNode<S> where S: Shape {
     &self.material
   }
 
-  fn intersect(&self, world_ray: Ray) -> HitList {
+  fn intersect(&self, world_ray: Ray) -> HitList<'_> {
+    self.intersect_cached(world_ray, &mut RayCache::new())
+  }
+
+  fn intersect_cached(&self, world_ray: Ray, cache: &mut RayCache) -> HitList<'_> {
     let mut results = HitList::new();
-    let object_ray = self.inverse_transform * world_ray;
+
+    let Some(object_ray) = self.object_ray(world_ray, cache) else {
+      return results;
+    };
+
+    // rays without a direction can't hit anything, and would otherwise produce NaN distances
+    if object_ray.is_degenerate() {
+      return results;
+    }
+
+    // the back of a closed, opaque surface is always hidden behind its front
+    if self.material.culls_backfaces() && self.object.is_back_facing(object_ray) {
+      return results;
+    }
 
     for distance in self.object.intersect(object_ray) {
-      results.push(self, distance);
+      let uv = self.object.uv_at(object_ray.position(distance));
+
+      results.push_hit(Hit::new(self, distance).with_uv(uv));
     }
 
     results
   }
 
-  fn normal_at(&self, world_point: Vector) -> Vector {
+  fn normal_at(&self, world_point: Point) -> Vector {
     let object_point = self.inverse_transform * world_point;
 
-    self.object.normal_at(object_point, self.inverse_transform)
+    self.object.normal_at(object_point, self.normal_transform)
   }
 
-  fn world_to_object(&self, world_point: Vector) -> Vector {
+  fn normal_at_time(&self, world_point: Point, time: f64) -> Vector {
+    if self.end_transform.is_none() {
+      return self.normal_at(world_point);
+    }
+
+    // moving objects have a different transform at every time, so nothing can be cached
+    let inverse_transform = self.surface_inverse_transform_at(time);
+    let object_point = inverse_transform * world_point;
+
+    self.object.normal_at(object_point, inverse_transform.transpose())
+  }
+
+  fn world_to_object(&self, world_point: Point) -> Point {
     self.inverse_transform * world_point
   }
 
-  fn object_to_world(&self, object_point: Vector) -> Vector {
+  fn world_to_object_at_time(&self, world_point: Point, time: f64) -> Point {
+    self.surface_inverse_transform_at(time) * world_point
+  }
+
+  fn object_to_world(&self, object_point: Point) -> Point {
     self.transform * object_point
   }
+
+  fn bounds(&self) -> BoundingBox {
+    let bounds = self.object.bounds();
+
+    // moving objects sweep between their start and end placements
+    match self.end_transform {
+      Some(end_transform) => bounds.transform(self.transform).union(bounds.transform(end_transform)),
+      None => bounds.transform(self.transform),
+    }
+  }
+
+  fn try_apply_parent_transform(&mut self, parent: Matrix4x4) -> anyhow::Result<()> {
+    let transform = parent * self.transform;
+    let inverse_transform = transform
+      .try_invert()
+      .ok_or_else(|| anyhow::anyhow!("Failed to invert node transform under its parent"))?;
+
+    self.end_transform = self.end_transform.map(|end_transform| parent * end_transform);
+    self.transform = transform;
+    self.inverse_transform = inverse_transform;
+    self.normal_transform = inverse_transform.transpose();
+    self.is_identity = self.end_transform.is_none() && transform.is_identity();
+
+    Ok(())
+  }
+}
+
+/// What the scene outputs for each ray.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum RenderMode {
+  /// Fully lit and shaded colors.
+  #[default]
+  Shaded,
+  /// A flat color per top-level object, with no lighting; for compositing mattes.
+  ///
+  /// Each object's color is derived from its index in the scene, so it's stable between renders.
+  ObjectId,
+}
+
+/// The top-level object found by `Scene::pick`, and where the ray struck it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PickResult {
+  /// The index of the object, in the order it was added to the scene.
+  pub index: usize,
+  pub point: Point,
+  /// The surface normal, facing back towards the ray.
+  pub normal: Vector,
+  pub distance: f64,
 }
 
 /// A scene that can be rendered via ray tracing.
 pub struct Scene {
   ambient_color: Color,
+  background_color: Color,
+  environment: Option<Box<dyn Environment>>,
+  environment_ambient: bool,
   nodes: Vec<Box<dyn Traceable>>,
   lights: Vec<PointLight>,
+  sphere_lights: Vec<SphereLight>,
+  counters: RenderCounters,
+  max_depth: usize,
+  roulette_depth: Option<usize>,
+  light_samples: Option<usize>,
+  fog: Option<(Color, f64)>,
+  render_mode: RenderMode,
 }
 
 impl Scene {
   const MAX_DEPTH: usize = 5;
 
+  /// The number of rays averaged for a blurred reflection or refraction.
+  const GLOSSY_SAMPLES: usize = 8;
+
+  /// The number of points sampled on each emissive sphere when shading.
+  const EMISSIVE_SAMPLES: usize = 16;
+
+  /// The lowest chance of survival under russian roulette; keeps survivor boosts bounded.
+  const MIN_SURVIVAL: f64 = 0.05;
+
   /// Create a new scene.
   pub fn new() -> Self {
     Self {
       ambient_color: Color::BLACK,
+      background_color: Color::BLACK,
+      environment: None,
+      environment_ambient: false,
       nodes: Vec::new(),
       lights: Vec::new(),
+      sphere_lights: Vec::new(),
+      counters: RenderCounters::default(),
+      max_depth: Self::MAX_DEPTH,
+      roulette_depth: None,
+      light_samples: None,
+      fog: None,
+      render_mode: RenderMode::Shaded,
     }
   }
 
@@ -132,69 +407,381 @@ impl Scene {
     self.lights.push(light);
   }
 
+  /// Adds key, fill and rim lights around the given target, for a quick studio setup.
+  ///
+  /// The lights assume a camera looking down +z at the target: the key light sits high to the
+  /// front-left at full intensity, the fill lower to the front-right at half, and the rim above
+  /// and behind the target at 70%, to pick out its silhouette.
+  pub fn add_three_point_lighting(&mut self, target: Point, intensity: Color) {
+    const LIGHTS: [(Vector, f64); 3] = [
+      (vec3(-10., 10., -10.), 1.),
+      (vec3(10., 3., -8.), 0.5),
+      (vec3(2., 10., 10.), 0.7),
+    ];
+
+    for (offset, scale) in LIGHTS {
+      self.add_light(PointLight::new(target + offset, intensity * scale));
+    }
+  }
+
+  /// Add an emissive sphere to the scene, which also lights other objects with its emission.
+  ///
+  /// The sphere is assumed to be uniformly scaled. It lights the scene from points sampled across
+  /// its surface, so it casts soft shadows.
+  pub fn add_emissive(&mut self, sphere: SceneNode<Sphere>) {
+    let center = sphere.object_to_world(Point::ORIGIN);
+    let radius = sphere.object_to_world(point(1., 0., 0.)).distance(center);
+
+    self.sphere_lights.push(SphereLight::new(center, radius, sphere.material().emission));
+    self.add_object(sphere);
+  }
+
+  /// The ambient color added to every lit surface.
+  pub fn ambient_color(&self) -> Color {
+    self.ambient_color
+  }
+
+  /// Sets the ambient color added to every lit surface.
+  pub fn set_ambient_color(&mut self, color: Color) {
+    self.ambient_color = color;
+  }
+
+  /// The background color seen by rays that miss every object.
+  pub fn background_color(&self) -> Color {
+    self.background_color
+  }
+
+  /// Sets the background color seen by rays that miss every object.
+  pub fn set_background_color(&mut self, color: Color) {
+    self.background_color = color;
+  }
+
+  /// The environment seen by rays that miss every object, if any.
+  pub fn environment(&self) -> Option<&dyn Environment> {
+    self.environment.as_deref()
+  }
+
+  /// Sets the environment seen by rays that miss every object; it replaces the background color.
+  pub fn set_environment(&mut self, environment: impl Environment + 'static) {
+    self.environment = Some(Box::new(environment));
+  }
+
+  /// Lights surfaces with ambient light from the environment, in the direction of their normal.
+  ///
+  /// Objects then pick up the colors of their surroundings; this is added to the ambient color.
+  pub fn set_environment_ambient(&mut self, enabled: bool) {
+    self.environment_ambient = enabled;
+  }
+
+  /// The maximum number of bounces traced for reflection and refraction.
+  pub fn max_depth(&self) -> usize {
+    self.max_depth
+  }
+
+  /// Sets the maximum number of bounces traced for reflection and refraction.
+  pub fn set_max_depth(&mut self, max_depth: usize) {
+    self.max_depth = max_depth;
+  }
+
+  /// Enables russian roulette termination for bounces at or beyond the given depth.
+  ///
+  /// Dim paths are randomly cut short, in proportion to how little they can still contribute,
+  /// and the survivors are brightened to compensate; on average the image is unchanged.
+  pub fn set_russian_roulette(&mut self, start_depth: Option<usize>) {
+    self.roulette_depth = start_depth;
+  }
+
+  /// Shades each point with the given number of lights, chosen at random, instead of every light.
+  ///
+  /// Brighter and closer lights are chosen more often, and each sample is weighted by how
+  /// likely it was to be chosen; on average the image is unchanged, but it becomes noisier.
+  pub fn with_light_sampling(self, count: usize) -> Self {
+    Self { light_samples: Some(count.max(1)), ..self }
+  }
+
+  /// What the scene outputs for each ray.
+  pub fn render_mode(&self) -> RenderMode {
+    self.render_mode
+  }
+
+  /// Sets what the scene outputs for each ray.
+  pub fn set_render_mode(&mut self, render_mode: RenderMode) {
+    self.render_mode = render_mode;
+  }
+
+  /// The flat color used for the object at the given index in `RenderMode::ObjectId`.
+  ///
+  /// Hues are spaced by the golden ratio, so neighbouring indices are easy to tell apart.
+  pub fn object_id_color(index: usize) -> Color {
+    const GOLDEN_RATIO_CONJUGATE: f64 = 0.618_033_988_749_895;
+
+    Color::from_hsv(index as f64 * GOLDEN_RATIO_CONJUGATE, 0.75, 1.)
+  }
+
+  /// Fades surfaces towards the given color with distance, at the given density.
+  ///
+  /// The fog covers `1 - e^(-density * distance)` of each surface; rays that miss see only fog.
+  /// A density of zero (or less) leaves the scene without fog.
+  pub fn with_fog(self, color: Color, density: f64) -> Self {
+    Self { fog: (density > 0.).then_some((color, density)), ..self }
+  }
+
+  /// The objects in the scene, in the order they were added.
+  pub fn objects(&self) -> &[Box<dyn Traceable>] {
+    &self.nodes
+  }
+
+  /// The point lights in the scene, in the order they were added.
+  pub fn lights(&self) -> &[PointLight] {
+    &self.lights
+  }
+
+  /// Statistics on the rays traced through the scene since the last reset.
+  pub fn stats(&self) -> RenderStats {
+    self.counters.snapshot()
+  }
+
+  /// Resets the statistics on rays traced through the scene.
+  pub fn reset_stats(&self) {
+    self.counters.reset();
+  }
+
   /// Computes the color of the scene at the given ray.
   pub fn trace(&self, ray: Ray) -> Color {
-    self.trace_inner(ray, 0)
+    self.counters.add_primary_ray();
+
+    match self.render_mode {
+      RenderMode::Shaded => self.trace_inner(ray, 0, 1.),
+      RenderMode::ObjectId => self.trace_object_id(ray),
+    }
+  }
+
+  /// Computes the flat color of the top-level object nearest along the given ray, or black.
+  fn trace_object_id(&self, ray: Ray) -> Color {
+    self.closest_node_hit(ray).map_or(Color::BLACK, |(index, _)| Self::object_id_color(index))
+  }
+
+  /// Finds the top-level object nearest along the given ray, without shading it.
+  ///
+  /// This is what a viewer would call to select the object under the mouse.
+  pub fn pick(&self, ray: Ray) -> Option<PickResult> {
+    let (index, hit) = self.closest_node_hit(ray)?;
+
+    let point = ray.position(hit.distance);
+    let mut normal = hit.normal_at(point, ray.time);
+
+    // report the face the ray actually struck
+    if normal.dot(ray.direction) > 0. {
+      normal = -normal;
+    }
+
+    Some(PickResult { index, point, normal, distance: hit.distance })
+  }
+
+  /// Finds the closest hit along the given ray, along with the index of the top-level object hit.
+  fn closest_node_hit(&self, ray: Ray) -> Option<(usize, Hit<'_>)> {
+    self.counters.add_intersection_tests(self.nodes.len());
+
+    self.nodes
+      .iter()
+      .enumerate()
+      .filter_map(|(index, object)| Some((index, object.intersect(ray).closest_hit()?)))
+      .min_by(|(_, a), (_, b)| a.distance.total_cmp(&b.distance))
   }
 
   /// Computes the color of the scene at the given ray.
-  fn trace_inner(&self, ray: Ray, depth: usize) -> Color {
-    if depth >= Self::MAX_DEPTH {
-      return self.ambient_color;
+  ///
+  /// The throughput is the fraction of this ray's color that will reach the camera.
+  fn trace_inner(&self, ray: Ray, depth: usize, throughput: f64) -> Color {
+    self.trace_with_distance(ray, depth, throughput).0
+  }
+
+  /// Computes the color of the scene at the given ray, and the distance to the surface it hit.
+  ///
+  /// Rays that hit nothing report an infinite distance.
+  fn trace_with_distance(&self, ray: Ray, depth: usize, throughput: f64) -> (Color, f64) {
+    if depth >= self.max_depth {
+      return (self.background_color, f64::INFINITY);
+    }
+
+    let survival = match self.roulette_depth {
+      Some(start_depth) if depth >= start_depth => throughput.clamp(Self::MIN_SURVIVAL, 1.),
+      _ => 1.,
+    };
+
+    if survival < 1. && Self::roulette_sample(ray, depth) >= survival {
+      return (Color::BLACK, f64::INFINITY);
     }
 
     let hits = self.intersect(ray);
 
-    if let Some(hit) = hits.closest_hit() {
-      self.apply_lighting(ray, &hit, &hits, depth)
+    // survivors carry their boost forward, so later bounces aren't cut short any more eagerly
+    let (color, distance) = if let Some(hit) = hits.closest_hit() {
+      (self.apply_lighting(ray, &hit, &hits, depth, throughput / survival), hit.distance)
     } else {
-      self.ambient_color
+      (self.miss_color(ray), f64::INFINITY)
+    };
+
+    let color = match self.fog {
+      Some((fog_color, _)) if distance.is_infinite() => fog_color,
+      Some((fog_color, density)) => {
+        let fog = 1. - (-density * distance * ray.direction.magnitude()).exp();
+
+        color + (fog_color - color) * fog
+      }
+      None => color,
+    };
+
+    (color / survival, distance)
+  }
+
+  /// Draws a random number in [0, 1) for deciding whether the given ray survives.
+  fn roulette_sample(ray: Ray, depth: usize) -> f64 {
+    let components = [
+      ray.origin.x, ray.origin.y, ray.origin.z,
+      ray.direction.x, ray.direction.y, ray.direction.z,
+    ];
+
+    Self::random_for(&components, depth as u64).next_f64()
+  }
+
+  /// Creates a random generator seeded from the given values.
+  ///
+  /// Deriving randomness from the geometry keeps renders reproducible across threads.
+  fn random_for(values: &[f64], salt: u64) -> Random {
+    let seed = values
+      .iter()
+      .fold(salt, |seed, value| (seed ^ value.to_bits()).wrapping_mul(0x0000_0100_0000_01b3));
+
+    Random::with_seed(seed)
+  }
+
+  /// Computes the color seen by a ray that misses every object.
+  fn miss_color(&self, ray: Ray) -> Color {
+    match &self.environment {
+      Some(environment) => environment.sample(ray.direction),
+      None => self.background_color,
     }
   }
 
   /// Intersects the given ray with the entire scene.
-  fn intersect(&self, ray: Ray) -> HitList {
+  ///
+  /// Hits are ordered by distance. Hits at the same distance keep the order their objects were
+  /// added to the scene, so refraction through coincident surfaces is reproducible.
+  fn intersect(&self, ray: Ray) -> HitList<'_> {
     let mut results = HitList::new();
 
+    self.counters.add_intersection_tests(self.nodes.len());
+
     for object in &self.nodes {
       results.append(object.intersect(ray))
     }
 
-    // sort results by distance in-place
-    results.sort_by(|a, b| {
-      a.distance.partial_cmp(&b.distance).unwrap()
-    });
+    // a stable sort, so ties stay in insertion order
+    results.sort_by(|a, b| a.distance.total_cmp(&b.distance));
 
     results
   }
 
+  /// Finds the closest object hit by the given ray strictly between the given distances.
+  ///
+  /// Hits beyond `t_max` are ignored, so a blocker past a light doesn't count as occluding it.
+  pub fn intersect_closest(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
+    self.intersect(ray).closest_between(t_min, t_max)
+  }
+
   /// Calculates lighting for the given ray intersection.
-  fn apply_lighting(&self, ray: Ray, hit: &Hit, hits: &HitList, depth: usize) -> Color {
+  fn apply_lighting(&self, ray: Ray, hit: &Hit, hits: &HitList, depth: usize, throughput: f64) -> Color {
     let mut surface = self.ambient_color;
 
-    let lighting_data = LightingData::calculate(ray, &hit, &hits);
-    let in_shadow = self.is_shadowed(lighting_data.over_position);
+    let lighting_data = LightingData::calculate(ray, hit, hits);
 
     // calculate direct surface lighting
-    for light in &self.lights {
-      surface = surface + phong_lighting(
+    let material = lighting_data.object.material();
+
+    if let Some(environment) = self.environment.as_deref().filter(|_| self.environment_ambient) {
+      let irradiance = environment.irradiance(lighting_data.normal);
+
+      surface += irradiance * material.ambient_color_at(lighting_data.object_position) * material.ambient;
+    }
+
+    let lighting = match material.shading_model {
+      ShadingModel::Phong => phong_lighting,
+      ShadingModel::CookTorrance { .. } => cook_torrance_lighting,
+    };
+
+    let light_contribution = |light: &PointLight| {
+      let transmission = self.shadow_transmission(lighting_data.over_position, light, lighting_data.time);
+      let shade = |in_shadow| lighting(
         light,
-        &lighting_data.object.material(),
+        material,
         lighting_data.over_position,
         lighting_data.object_position,
         lighting_data.eye,
         lighting_data.normal,
         in_shadow,
       );
+
+      if transmission == Color::WHITE {
+        shade(false)
+      } else if transmission == Color::BLACK {
+        shade(true)
+      } else {
+        // light filtered through transparent occluders only tints the direct contribution
+        let shadowed = shade(true);
+
+        shadowed + (shade(false) - shadowed) * transmission
+      }
+    };
+
+    match self.light_samples {
+      Some(count) if count < self.lights.len() => {
+        surface += self.sample_lights(lighting_data.over_position, count, light_contribution);
+      }
+      _ => {
+        for light in &self.lights {
+          surface += light_contribution(light);
+        }
+      }
+    }
+
+    for (index, sphere_light) in self.sphere_lights.iter().enumerate() {
+      let position = lighting_data.over_position;
+      let mut random = Self::random_for(&[position.x, position.y, position.z], index as u64);
+
+      for _ in 0..Self::EMISSIVE_SAMPLES {
+        if let Some(light) = sphere_light.sample_from(position, random.next_f64(), random.next_f64(), Self::EMISSIVE_SAMPLES) {
+          surface += light_contribution(&light);
+        }
+      }
     }
 
     // calculate reflective properties
-    let reflected = self.reflected_color(&lighting_data, depth);
-    let refracted = self.refracted_color(&lighting_data, depth);
+    let reflected = self.reflected_color(&lighting_data, depth, throughput);
+    let refracted = self.refracted_color(&lighting_data, depth, throughput);
 
     // combine the results
-    let material = hit.object.material();
-    if material.reflectivity > 0. && material.transparency > 0. {
+    if material.conserve_energy {
+      // refraction takes what reflection leaves, and the surface takes what's left after both
+      let reflectance = Self::reflectance(&lighting_data);
+      let reflectance = reflectance.r.max(reflectance.g).max(reflectance.b).clamp(0., 1.);
+      let transmittance = material.transparency.clamp(0., 1.);
+
+      return surface * (1. - reflectance) * (1. - transmittance)
+        + material.emission
+        + reflected
+        + refracted * (1. - reflectance);
+    }
+
+    let surface = surface + material.emission;
+
+    if material.uses_fresnel() {
+      // the reflected color is already weighted by the fresnel term
+      let reflectance = Self::shlick(&lighting_data);
+
+      surface + reflected + refracted * (1. - reflectance)
+    }
+    else if material.reflectivity > 0. && material.transparency > 0. {
       let reflectance = Self::shlick(&lighting_data);
 
       surface + reflected * reflectance + refracted * (1. - reflectance)
@@ -204,45 +791,155 @@ impl Scene {
     }
   }
 
-  /// Determines if the given point is in shadow.
-  fn is_shadowed(&self, point: Point) -> bool {
-    for light in &self.lights {
-      let light_vector = light.position - point;
+  /// Estimates the total light arriving at the given point from a random subset of the lights.
+  ///
+  /// Lights are chosen in proportion to their brightness over squared distance, and each
+  /// contribution is divided by the chance of choosing it, so the estimate is unbiased.
+  fn sample_lights(&self, point: Point, count: usize, contribution: impl Fn(&PointLight) -> Color) -> Color {
+    let weights: Vec<f64> = self.lights
+      .iter()
+      .map(|light| {
+        let offset = light.position - point;
 
-      let distance = light_vector.magnitude();
-      let direction = light_vector.normalize();
+        light.intensity.luminance() / offset.dot(offset).max(EPSILON)
+      })
+      .collect();
 
-      let ray = Ray::new(point, direction);
+    let total: f64 = weights.iter().sum();
 
-      if let Some(hit) = self.intersect(ray).closest_hit() {
-        if hit.distance < distance {
-          return true;
-        }
+    if total <= 0. {
+      return Color::BLACK;
+    }
+
+    let mut random = Self::random_for(&[point.x, point.y, point.z], count as u64);
+    let mut color = Color::BLACK;
+
+    for _ in 0..count {
+      let mut target = random.next_f64() * total;
+
+      // walk the cumulative weights; fall back to the last lit light against rounding
+      let index = weights
+        .iter()
+        .position(|&weight| {
+          target -= weight;
+          weight > 0. && target < 0.
+        })
+        .or_else(|| weights.iter().rposition(|&weight| weight > 0.))
+        .unwrap();
+
+      color += contribution(&self.lights[index]) / (weights[index] / total);
+    }
+
+    color / count as f64
+  }
+
+  /// Determines if the given point is in shadow from any light at the given shutter time.
+  fn is_shadowed(&self, point: Point, time: f64) -> bool {
+    self.lights
+      .iter()
+      .any(|light| self.shadow_transmission(point, light, time) != Color::WHITE)
+  }
+
+  /// Determines the fraction of the given light that reaches the given point, per channel.
+  ///
+  /// Opaque occluders block the light entirely; transparent ones filter it by their surface
+  /// color and transparency at each surface the shadow ray passes through.
+  fn shadow_transmission(&self, point: Point, light: &PointLight, time: f64) -> Color {
+    let light_vector = light.position - point;
+
+    let distance = light_vector.magnitude();
+    let direction = light_vector.normalize();
+
+    let ray = Ray::new(point, direction).with_time(time);
+
+    self.counters.add_shadow_ray();
+
+    let mut transmission = Color::WHITE;
+
+    for hit in self.intersect(ray).iter() {
+      if hit.distance <= 0. || hit.distance >= distance {
+        continue;
+      }
+
+      let material = hit.object.material();
+      let object_point = hit.world_to_object(ray.position(hit.distance), time);
+
+      transmission = transmission * material.texture.sample_at(object_point) * material.transparency;
+
+      if transmission == Color::BLACK {
+        break;
       }
     }
 
-    false
+    transmission
   }
 
   /// Determines the reflected color of the given ray.
-  fn reflected_color(&self, lighting_data: &LightingData, depth: usize) -> Color {
+  fn reflected_color(&self, lighting_data: &LightingData, depth: usize, throughput: f64) -> Color {
     let material = lighting_data.object.material();
+    let reflectivity = Self::reflectance(lighting_data);
 
-    if material.reflectivity.is_approx(0.) {
+    if reflectivity == Color::BLACK {
       return Color::BLACK;
     }
 
-    let reflect_ray = Ray::new(
-      lighting_data.over_position,
-      lighting_data.reflect_direction,
-    );
+    let weight = reflectivity.r.max(reflectivity.g).max(reflectivity.b);
+
+    let trace_reflection = |direction: Vector| {
+      let reflect_ray = Ray::new(lighting_data.over_position, direction).with_time(lighting_data.time);
+
+      self.counters.add_reflection_ray();
+
+      if material.reflection_falloff.is_infinite() {
+        return self.trace_inner(reflect_ray, depth + 1, throughput * weight);
+      }
+
+      // reflections lose contrast the further the reflected light has travelled
+      let (color, distance) = self.trace_with_distance(reflect_ray, depth + 1, throughput * weight);
+
+      color * (-distance / material.reflection_falloff).exp()
+    };
+
+    let normal = lighting_data.normal;
+    let roughness = material.reflection_roughness;
+
+    self.trace_rough(lighting_data, lighting_data.reflect_direction, normal, roughness, depth, trace_reflection) * reflectivity
+  }
+
+  /// Averages the given trace over directions jittered within a cone around the given axis.
+  ///
+  /// The cone widens with roughness, up to a hemisphere; rough surfaces are traced with several
+  /// directions, which are kept on the same side of the surface as the given side vector.
+  fn trace_rough(&self, lighting_data: &LightingData, axis: Vector, side: Vector, roughness: f64, depth: usize, trace: impl Fn(Vector) -> Color) -> Color {
+    if roughness <= 0. {
+      return trace(axis);
+    }
 
-    self.trace_inner(reflect_ray, depth + 1) * material.reflectivity
+    // only the first bounce is split, so the cost doesn't grow exponentially with depth
+    let samples = if depth == 0 { Self::GLOSSY_SAMPLES } else { 1 };
+    let half_angle = roughness * PI / 2.;
+
+    let position = lighting_data.world_position;
+    let mut random = Self::random_for(&[position.x, position.y, position.z, axis.x, axis.y, axis.z], depth as u64);
+    let mut color = Color::BLACK;
+
+    for _ in 0..samples {
+      let mut direction = sample_cone(axis, half_angle, random.next_f64(), random.next_f64());
+
+      // directions that cross to the wrong side of the surface are folded back
+      if direction.dot(side) < 0. {
+        direction = direction.reflect(side);
+      }
+
+      color += trace(direction);
+    }
+
+    color / samples as f64
   }
 
   /// Determines the refracted color of the given ray.
-  fn refracted_color(&self, lighting_data: &LightingData, depth: usize) -> Color {
-    if depth >= Self::MAX_DEPTH {
+  fn refracted_color(&self, lighting_data: &LightingData, depth: usize, throughput: f64) -> Color {
+    if depth >= self.max_depth {
       return Color::BLACK;
     }
 
@@ -253,8 +950,10 @@ impl Scene {
       return Color::BLACK;
     }
 
+    // a thin sheet's two faces bend light equally and oppositely, so it passes straight through
+    let n_ratio = if material.thin { 1. } else { n1 / n2 };
+
     // TODO: add some more tests for this section
-    let n_ratio = n1 / n2;
     let cos_i = lighting_data.eye.dot(lighting_data.normal);
     let sin_t2 = n_ratio * n_ratio * (1. - cos_i * cos_i);
 
@@ -263,12 +962,32 @@ impl Scene {
     }
 
     let cos_t = (1. - sin_t2).sqrt();
-    let direction = lighting_data.normal * (n_ratio + cos_i - cos_t) - lighting_data.eye * n_ratio;
+    let direction = lighting_data.normal * (n_ratio * cos_i - cos_t) - lighting_data.eye * n_ratio;
 
-    let ray = Ray::new(lighting_data.under_position, direction);
-    let color = self.trace_inner(ray, depth + 1);
+    let trace_refraction = |direction: Vector| {
+      let ray = Ray::new(lighting_data.under_position, direction).with_time(lighting_data.time);
+
+      self.counters.add_refraction_ray();
+      self.trace_inner(ray, depth + 1, throughput * material.transparency)
+    };
+
+    let normal = lighting_data.normal;
+    let roughness = material.refraction_roughness;
+
+    self.trace_rough(lighting_data, direction, -normal, roughness, depth, trace_refraction) * material.transparency
+  }
+
+  /// Determines how strongly the surface reflects, per channel, as weighted into the reflected color.
+  fn reflectance(lighting_data: &LightingData) -> Color {
+    let material = lighting_data.object.material();
+
+    if material.uses_fresnel() {
+      Color::WHITE * Self::shlick(lighting_data)
+    } else {
+      let cos_theta = lighting_data.eye.dot(lighting_data.normal);
 
-    return color * material.transparency;
+      material.reflectivity_at(lighting_data.object_position) * material.reflection_tint_at(cos_theta)
+    }
   }
 
   /// Finds the Shlick approximation
@@ -291,7 +1010,13 @@ impl Scene {
     let r0 = (n1 - n2) / (n1 + n2);
     let r02 = r0 * r0;
 
-    return r02 + (1. - r02) * (1. - cos).powi(5);
+    r02 + (1. - r02) * (1. - cos).powi(5)
+  }
+}
+
+impl Default for Scene {
+  fn default() -> Self {
+    Self::new()
   }
 }
 
@@ -316,6 +1041,11 @@ impl<'a> HitList<'a> {
     self.hits.push(Hit::new(object, distance));
   }
 
+  /// Adds an existing hit to the set.
+  pub fn push_hit(&mut self, hit: Hit<'a>) {
+    self.hits.push(hit);
+  }
+
   /// Appends all items from the given other set to this set.
   pub fn append(&mut self, mut other: Self) {
     self.hits.append(&mut other.hits);
@@ -323,20 +1053,43 @@ impl<'a> HitList<'a> {
 
   /// Finds the closest hit intersection.
   pub fn closest_hit(&self) -> Option<Hit<'a>> {
-    let mut closest_t = f64::MAX;
-    let mut closest_object = None;
+    self.closest_between(0., f64::MAX)
+  }
+
+  /// Finds the closest hit intersection strictly between the given distances.
+  pub fn closest_between(&self, t_min: f64, t_max: f64) -> Option<Hit<'a>> {
+    let mut closest_t = t_max;
+    let mut closest_hit = None;
 
     for hit in &self.hits {
       let t = hit.distance;
-      if t > 0. && t < closest_t {
+      if t > t_min && t < closest_t {
         closest_t = t;
-        closest_object = Some(hit.object);
+        closest_hit = Some(hit);
       }
     }
 
-    closest_object.map(|object| {
-      Hit::new(object, closest_t)
-    })
+    closest_hit.cloned()
+  }
+
+  /// Finds the (up to) n closest hit intersections, nearest first.
+  pub fn closest_n(&self, n: usize) -> Vec<Hit<'a>> {
+    let mut hits: Vec<_> = self.hits
+      .iter()
+      .filter(|hit| hit.distance > 0.)
+      .cloned()
+      .collect();
+
+    hits.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+    hits.truncate(n);
+
+    hits
+  }
+}
+
+impl Default for HitList<'_> {
+  fn default() -> Self {
+    Self::new()
   }
 }
 
@@ -345,21 +1098,60 @@ impl<'a> HitList<'a> {
 pub struct Hit<'a> {
   pub object: &'a dyn Traceable,
   pub distance: f64,
+  pub uv: Option<(f64, f64)>,
+  pub instance: Option<&'a Instance>,
 }
 
 impl<'a> Hit<'a> {
   /// Creates a new intersection
   pub fn new(object: &'a dyn Traceable, distance: f64) -> Self {
-    Self { object, distance }
+    Self { object, distance, uv: None, instance: None }
   }
-}
 
-impl PartialEq for Hit<'_> {
-  fn eq(&self, other: &Self) -> bool {
+  /// Determines if both hits are on the same surface, wherever along the ray they fall.
+  pub fn same_surface(&self, other: &Hit) -> bool {
     let ptr_a = self.object as *const _;
     let ptr_b = other.object as *const _;
 
-    self.distance.is_approx(other.distance) && ptr_a == ptr_b
+    // shared geometry can be hit through several instances, which are distinct surfaces
+    let same_instance = match (self.instance, other.instance) {
+      (Some(a), Some(b)) => std::ptr::eq(a, b),
+      (None, None) => true,
+      _ => false,
+    };
+
+    std::ptr::addr_eq(ptr_a, ptr_b) && same_instance
+  }
+
+  /// Attaches the surface (u, v) texture coordinates at the point of intersection.
+  pub fn with_uv(self, uv: Option<(f64, f64)>) -> Self {
+    Self { uv, ..self }
+  }
+
+  /// Computes the world space normal at the given world point on the hit surface.
+  ///
+  /// This accounts for any instance the hit came through.
+  pub fn normal_at(&self, world_point: Point, time: f64) -> Vector {
+    match self.instance {
+      Some(instance) => instance.normal_to_world(self.object.normal_at_time(instance.world_to_instance(world_point), time)),
+      None => self.object.normal_at_time(world_point, time),
+    }
+  }
+
+  /// Transforms the given world point into the hit object's space.
+  ///
+  /// This accounts for any instance the hit came through.
+  pub fn world_to_object(&self, world_point: Point, time: f64) -> Point {
+    match self.instance {
+      Some(instance) => self.object.world_to_object_at_time(instance.world_to_instance(world_point), time),
+      None => self.object.world_to_object_at_time(world_point, time),
+    }
+  }
+}
+
+impl PartialEq for Hit<'_> {
+  fn eq(&self, other: &Self) -> bool {
+    self.distance.is_approx(other.distance) && self.same_surface(other)
   }
 }
 
@@ -379,10 +1171,136 @@ impl<'a> DerefMut for HitList<'a> {
 
 #[cfg(test)]
 mod tests {
-  use crate::maths::{Matrix4x4, point, rgb, vec3};
+  use crate::graphics::{CheckerPattern, GradientPattern, StripedPattern};
+  use crate::maths::{point, rgb, vec3};
 
   use super::*;
 
+  #[test]
+  fn moving_sphere_is_hit_at_interpolated_position() {
+    let sphere = Sphere::new().with_motion(Matrix4x4::translate(4., 0., 0.));
+    let ray = Ray::new(point(2., 0., -5.), vec3(0., 0., 1.));
+
+    assert_eq!(sphere.intersect(ray.with_time(0.)).len(), 0);
+    assert_eq!(sphere.intersect(ray.with_time(0.5)).len(), 2);
+    assert_eq!(sphere.intersect(ray.with_time(1.)).len(), 0);
+  }
+
+  #[test]
+  fn moving_sphere_flattened_at_some_time_is_missed_then() {
+    let sphere = Sphere::new().with_motion(Matrix4x4::scale(-1., 1., 1.));
+    let ray = Ray::new(point(-5., 0., 0.), vec3(1., 0., 0.));
+
+    // halfway through flipping, the sphere is a disc with no inverse, so it can't be hit
+    assert_eq!(sphere.intersect(ray.with_time(0.)).len(), 2);
+    assert_eq!(sphere.intersect(ray.with_time(0.5)).len(), 0);
+    assert_eq!(sphere.intersect(ray.with_time(1.)).len(), 2);
+  }
+
+  #[test]
+  fn pre_and_post_transforms_compose_in_different_orders() {
+    let scale = Matrix4x4::scale(2., 2., 2.);
+    let translate = Matrix4x4::translate(5., 0., 0.);
+
+    // pre-multiplying scales first, then translates the scaled object
+    let pre = Sphere::new().with_transform(scale).pre_transform(translate);
+    // post-multiplying translates first (in object space), then scales everything
+    let post = Sphere::new().with_transform(scale).post_transform(translate);
+
+    assert_eq!(pre.transform(), translate * scale);
+    assert_eq!(post.transform(), scale * translate);
+    assert_eq!(pre.object_to_world(point(1., 0., 0.)), point(7., 0., 0.));
+    assert_eq!(post.object_to_world(point(1., 0., 0.)), point(12., 0., 0.));
+    assert_eq!(post.inverse_transform(), (scale * translate).invert().unwrap());
+  }
+
+  #[test]
+  fn singular_transforms_are_reported_rather_than_ignored() {
+    let flatten = Matrix4x4::scale(1., 0., 1.);
+
+    assert!(Sphere::new().try_with_transform(flatten).is_err());
+    assert!(Sphere::new().try_with_transform(Matrix4x4::scale(2., 2., 2.)).is_ok());
+  }
+
+  #[test]
+  #[should_panic(expected = "Failed to invert node transform")]
+  fn with_transform_panics_on_singular_transforms() {
+    let _ = Sphere::new().with_transform(Matrix4x4::scale(0., 0., 0.));
+  }
+
+  #[test]
+  fn then_transform_applies_transforms_in_reading_order() {
+    let node = Sphere::new()
+      .with_transform(Matrix4x4::scale(2., 2., 2.))
+      .then_transform(Matrix4x4::rotate_z(PI / 2.))
+      .then_transform(Matrix4x4::translate(10., 5., 7.));
+
+    assert_eq!(node.object_to_world(point(1., 0., 0.)), point(10., 7., 7.));
+  }
+
+  #[test]
+  fn identity_node_intersects_same_as_transformed_path() {
+    let fast = Sphere::new();
+    let mut slow = Sphere::new();
+    slow.is_identity = false;
+
+    let ray = Ray::new(point(0.2, 0.3, -5.), vec3(0., 0., 1.));
+
+    let fast_hits: Vec<_> = fast.intersect(ray).iter().map(|hit| hit.distance).collect();
+    let slow_hits: Vec<_> = slow.intersect(ray).iter().map(|hit| hit.distance).collect();
+
+    assert!(fast.is_identity);
+    assert_eq!(fast_hits, slow_hits);
+  }
+
+  #[test]
+  fn identity_node_skips_inverse_transform() {
+    let mut sphere = Sphere::new();
+
+    // a bogus inverse would move the sphere out of the ray's path, if it were applied
+    sphere.inverse_transform = Matrix4x4::translate(100., 0., 0.);
+
+    let ray = Ray::new(point(0., 0., -5.), vec3(0., 0., 1.));
+
+    assert_eq!(sphere.intersect(ray).len(), 2);
+    assert!(!Sphere::new().with_transform(Matrix4x4::translate(1., 0., 0.)).is_identity);
+    assert!(!Sphere::new().with_motion(Matrix4x4::translate(1., 0., 0.)).is_identity);
+  }
+
+  #[test]
+  fn node_looking_at_target_faces_it() {
+    let target = point(4., 2., 10.);
+    let node = Sphere::new()
+      .with_transform(Matrix4x4::translate(1., 2., 3.) * Matrix4x4::rotate_y(PI / 3.) * Matrix4x4::scale(2., 2., 2.))
+      .looking_at(target, vec3(0., 1., 0.));
+
+    let position = node.object_to_world(Point::ORIGIN);
+    let forward = node.object_to_world(point(0., 0., -1.)) - position;
+
+    assert_eq!(position, point(1., 2., 3.));
+    assert!(forward.magnitude().is_approx(2.));
+    assert_eq!(forward.normalize(), (target - position).normalize());
+  }
+
+  #[test]
+  fn degenerate_rays_should_not_intersect() {
+    let ray = Ray::new(point(0., 0., 0.), vec3(0., 0., 0.));
+
+    assert!(Sphere::new().intersect(ray).is_empty());
+    assert!(Cube::new().intersect(ray).is_empty());
+    assert!(Plane::new(vec3(0., 1., 0.)).intersect(ray).is_empty());
+    assert!(Cylinder::new(-1., 1., true).intersect(ray).is_empty());
+    assert!(Cone::new(-1., 1., true).intersect(ray).is_empty());
+  }
+
+  #[test]
+  fn moving_node_normal_follows_interpolated_position() {
+    let sphere = Sphere::new().with_motion(Matrix4x4::translate(4., 0., 0.));
+
+    assert_eq!(sphere.normal_at_time(point(2., 1., 0.), 0.5), vec3(0., 1., 0.));
+    assert_eq!(sphere.world_to_object_at_time(point(2., 1., 0.), 0.5), point(0., 1., 0.));
+  }
+
   #[test]
   fn hit_list_should_return_closest_hit() {
     let sphere = &Sphere::new().with_transform(Matrix4x4::translate(0., 0., -5.));
@@ -433,6 +1351,25 @@ mod tests {
     assert_eq!(set.closest_hit().unwrap().distance, 2.);
   }
 
+  #[test]
+  fn hit_list_should_return_closest_n_positive_hits_in_order() {
+    let sphere = &Sphere::new();
+
+    let mut set = HitList::new();
+
+    set.push(sphere, 5.);
+    set.push(sphere, -1.);
+    set.push(sphere, 7.);
+    set.push(sphere, -3.);
+    set.push(sphere, 2.);
+
+    let distances = |hits: Vec<Hit>| hits.iter().map(|hit| hit.distance).collect::<Vec<_>>();
+
+    assert_eq!(distances(set.closest_n(2)), vec![2., 5.]);
+    assert_eq!(distances(set.closest_n(10)), vec![2., 5., 7.]);
+    assert!(set.closest_n(0).is_empty());
+  }
+
   #[test]
   fn intersect_scene_with_ray_should_return_all_intersections() {
     let scene = create_test_scene();
@@ -447,6 +1384,38 @@ mod tests {
     assert_eq!(set[3].distance, 6.);
   }
 
+  #[test]
+  fn coincident_hits_are_ordered_by_insertion() {
+    let mut scene = Scene::new();
+
+    for _ in 0..3 {
+      scene.add_object(Sphere::new());
+    }
+
+    let ray = Ray::new(point(0., 0., -5.), vec3(0., 0., 1.));
+    let hits = scene.intersect(ray);
+    let expected: Vec<_> = [4., 6.]
+      .into_iter()
+      .flat_map(|distance| scene.nodes.iter().map(move |node| Hit::new(node.deref(), distance)))
+      .collect();
+
+    assert_eq!(hits.len(), 6);
+    assert!(hits.iter().eq(expected.iter()));
+  }
+
+  #[test]
+  fn intersect_closest_ignores_hits_beyond_t_max() {
+    let mut scene = Scene::new();
+
+    scene.add_object(Sphere::new().with_transform(Matrix4x4::translate(0., 0., 5.)));
+
+    let ray = Ray::new(point(0., 0., -5.), vec3(0., 0., 1.));
+
+    assert!(scene.intersect_closest(ray, 0., 8.).is_none());
+    assert_eq!(scene.intersect_closest(ray, 0., 12.).unwrap().distance, 9.);
+    assert_eq!(scene.intersect_closest(ray, 9., 12.).unwrap().distance, 11.);
+  }
+
   #[test]
   fn apply_lighting_to_an_intersection_from_outside() {
     let scene = create_test_scene();
@@ -454,12 +1423,14 @@ mod tests {
     let ray = Ray::new(point(0., 0., -5.), vec3(0., 0., 1.));
     let object = scene.nodes[0].deref();
 
-    let hit = Hit::new(object.deref(), 4.);
+    let hit = Hit::new(object, 4.);
     let hits = HitList::from(&[hit]);
 
-    let color = scene.apply_lighting(ray, &hits[0], &hits, 0);
+    let color = scene.apply_lighting(ray, &hits[0], &hits, 0, 1.);
 
-    assert_eq!(color, rgb(0.38012764, 0.47515953, 0.28509575));
+    // the light is a point (w = 1); it used to be built with vec3, and its w = 0 leaked into the
+    // light direction, which gave 0.38012764 rather than the book's 0.38066
+    assert_eq!(color, rgb(0.38065882, 0.47582352, 0.28549411));
   }
 
   #[test]
@@ -474,9 +1445,27 @@ mod tests {
     let hit = Hit::new(object, 0.5);
     let hits = HitList::from(&[hit]);
 
-    let color = scene.apply_lighting(ray, &hits[0], &hits, 0);
+    let color = scene.apply_lighting(ray, &hits[0], &hits, 0, 1.);
 
-    assert_eq!(color, rgb(0.1, 0.1, 0.1));
+    // the light shares the inside of the sphere with the eye, so it isn't shadowed
+    assert_eq!(color, rgb(0.90495, 0.90495, 0.90495));
+  }
+
+  #[test]
+  fn pick_finds_the_object_under_the_ray() {
+    let mut scene = Scene::new();
+
+    scene.add_object(Sphere::new().with_transform(Matrix4x4::translate(-3., 0., 0.)));
+    scene.add_object(Sphere::new());
+
+    let picked = scene.pick(Ray::new(point(0., 0., -5.), vec3(0., 0., 1.))).unwrap();
+
+    assert_eq!(picked.index, 1);
+    assert_eq!(picked.point, point(0., 0., -1.));
+    assert_eq!(picked.normal, vec3(0., 0., -1.));
+    assert_eq!(picked.distance, 4.);
+
+    assert_eq!(scene.pick(Ray::new(point(0., 0., -5.), vec3(0., 1., 0.))), None);
   }
 
   #[test]
@@ -484,13 +1473,210 @@ mod tests {
     let mut scene = create_test_scene();
     let ray = Ray::new(point(0., 0., -5.), vec3(0., 1., 0.));
 
-    scene.ambient_color = Color::RED;
+    scene.background_color = Color::RED;
 
     let color = scene.trace(ray);
 
     assert_eq!(color, Color::RED);
   }
 
+  #[test]
+  fn missed_rays_sample_the_environment() {
+    let mut scene = Scene::new();
+
+    scene.set_background_color(Color::RED);
+    scene.set_environment(GradientSky::new(Color::WHITE, Color::BLUE));
+
+    assert_eq!(scene.trace(Ray::new(point(0., 0., 0.), vec3(0., 1., 0.))), Color::BLUE);
+    assert_eq!(scene.trace(Ray::new(point(0., 0., 0.), vec3(1., 0., 0.))), Color::WHITE);
+  }
+
+  #[test]
+  fn reflective_sphere_reflects_the_environment() {
+    let mut scene = Scene::new();
+    let mirror = Material::default()
+      .with_color(Color::BLACK)
+      .with_ambient(0.)
+      .with_diffuse(0.)
+      .with_specular(0.)
+      .with_reflective(1.);
+
+    scene.set_environment(GradientSky::new(Color::WHITE, Color::BLUE));
+    scene.add_object(Sphere::new().with_material(mirror));
+
+    // looking straight down onto the top of the sphere reflects the zenith
+    let color = scene.trace(Ray::new(point(0., 5., 0.), vec3(0., -1., 0.)));
+
+    assert_eq!(color, Color::BLUE);
+  }
+
+  #[test]
+  fn ambient_color_is_added_to_lit_surfaces() {
+    let mut scene = create_test_scene();
+    let ray = Ray::new(point(0., 0., -5.), vec3(0., 0., 1.));
+
+    scene.set_ambient_color(rgb(0.1, 0.2, 0.3));
+
+    let color = scene.trace(ray);
+
+    assert_eq!(color, rgb(0.48065882, 0.67582352, 0.58549411));
+  }
+
+  #[test]
+  fn environment_ambient_tints_surfaces_by_their_normal() {
+    let mut scene = Scene::new();
+
+    scene.add_object(Plane::new(vec3(0., 1., 0.)));
+    scene.set_environment(GradientSky::new(Color::BLUE, Color::RED));
+
+    let from_above = Ray::new(point(0., 1., -1.), vec3(0., -1., 1.).normalize());
+    let from_below = Ray::new(point(0., -1., -1.), vec3(0., 1., 1.).normalize());
+
+    assert_eq!(scene.trace(from_above), Color::BLACK);
+
+    scene.set_environment_ambient(true);
+
+    let up = scene.trace(from_above);
+    let down = scene.trace(from_below);
+
+    assert_eq!(up, Color::RED * 0.1);
+    assert_eq!(down, Color::BLUE * 0.1);
+  }
+
+  #[test]
+  fn fog_blends_distant_surfaces_towards_fog_color() {
+    let build_scene = || {
+      let mut scene = Scene::new();
+      let material = Material::default().with_color(Color::WHITE).with_ambient(1.).with_diffuse(0.).with_specular(0.);
+
+      scene.add_light(PointLight::new(point(0., 0., -10.), rgb(1., 1., 1.)));
+      scene.add_object(Sphere::new().with_material(material.clone()));
+      scene.add_object(Sphere::new().with_material(material).with_transform(Matrix4x4::translate(10., 0., 200.)));
+
+      scene
+    };
+
+    let scene = build_scene().with_fog(Color::BLUE, 0.01);
+
+    let near = scene.trace(Ray::new(point(0., 0., -5.), vec3(0., 0., 1.)));
+    let far = scene.trace(Ray::new(point(10., 0., -5.), vec3(0., 0., 1.)));
+    let missed = scene.trace(Ray::new(point(0., 0., -5.), vec3(0., 1., 0.)));
+
+    assert_eq!(build_scene().trace(Ray::new(point(0., 0., -5.), vec3(0., 0., 1.))), Color::WHITE);
+
+    // the near sphere is 4 units away, the far one 204
+    assert!(near.r > 0.95 && near.b > 0.99, "{:?}", near);
+    assert!(far.r < 0.15 && far.b > 0.99, "{:?}", far);
+    assert_eq!(missed, Color::BLUE);
+  }
+
+  #[test]
+  fn missed_rays_without_fog_density_see_the_background() {
+    let mut scene = Scene::new().with_fog(Color::BLUE, 0.);
+
+    scene.set_background_color(Color::RED);
+
+    assert_eq!(scene.trace(Ray::new(point(0., 0., -5.), vec3(0., 1., 0.))), Color::RED);
+  }
+
+  #[test]
+  fn object_id_mode_renders_flat_colors_per_object() {
+    let mut scene = Scene::new();
+
+    scene.add_light(PointLight::new(point(-10., 10., -10.), rgb(1., 1., 1.)));
+    scene.add_object(Sphere::new().with_transform(Matrix4x4::translate(0., 1., 0.)));
+    scene.add_object(
+      Group::new()
+        .with_child(Plane::new(vec3(0., 1., 0.)))
+        .with_transform(Matrix4x4::translate(0., -1., 0.)),
+    );
+
+    scene.set_render_mode(RenderMode::ObjectId);
+
+    let sphere = Scene::object_id_color(0);
+    let floor = Scene::object_id_color(1);
+
+    assert_ne!(sphere, floor);
+
+    // the sphere is shaded the same all over, and the floor isn't darkened by its shadow
+    assert_eq!(scene.trace(Ray::new(point(0., 1., -5.), vec3(0., 0., 1.))), sphere);
+    assert_eq!(scene.trace(Ray::new(point(0.9, 1.3, -5.), vec3(0., 0., 1.))), sphere);
+    assert_eq!(scene.trace(Ray::new(point(0., 5., 0.), vec3(0., -1., 0.))), sphere);
+    assert_eq!(scene.trace(Ray::new(point(1., 5., 1.5), vec3(0., -1., 0.))), floor);
+    assert_eq!(scene.trace(Ray::new(point(0.5, 0., -5.), vec3(0., -1., 5.).normalize())), floor);
+    assert_eq!(scene.trace(Ray::new(point(0., 0., -5.), vec3(0., 1., 0.))), Color::BLACK);
+  }
+
+  #[test]
+  fn three_point_lighting_adds_key_fill_and_rim_lights() {
+    let mut scene = Scene::new();
+    let target = point(1., 2., 3.);
+
+    scene.add_three_point_lighting(target, rgb(1., 1., 1.));
+
+    assert_eq!(scene.lights.len(), 3);
+
+    let [key, fill, rim] = [&scene.lights[0], &scene.lights[1], &scene.lights[2]];
+
+    assert_ne!(key.position, fill.position);
+    assert_ne!(key.position, rim.position);
+    assert_ne!(fill.position, rim.position);
+    assert!(key.intensity.luminance() > fill.intensity.luminance());
+    assert!(rim.position.z > target.z);
+  }
+
+  #[test]
+  fn emissive_sphere_lights_nearby_surfaces() {
+    let build_scene = |emissive: bool| {
+      let mut scene = Scene::new();
+      let lamp = Sphere::new()
+        .with_material(Material::default().with_emission(Color::WHITE))
+        .with_transform(Matrix4x4::translate(0., 1., 0.) * Matrix4x4::scale(0.5, 0.5, 0.5));
+
+      scene.add_object(Plane::new(vec3(0., 1., 0.)).with_transform(Matrix4x4::translate(0., -1., 0.)));
+
+      if emissive {
+        scene.add_emissive(lamp);
+      } else {
+        scene.add_object(lamp);
+      }
+
+      scene
+    };
+
+    let below = Ray::new(point(0.5, 0., -2.), vec3(0., -1., 2.).normalize());
+    let far_away = Ray::new(point(20., 0., -2.), vec3(0., -1., 2.).normalize());
+    let at_lamp = Ray::new(point(0., 1., -5.), vec3(0., 0., 1.));
+
+    assert_eq!(build_scene(false).trace(below), Color::BLACK);
+
+    let scene = build_scene(true);
+    let lit = scene.trace(below);
+    let dim = scene.trace(far_away);
+
+    assert!(lit.r > 0.5, "{:?}", lit);
+    assert!(dim.r < lit.r / 2., "{:?}", dim);
+
+    // the lamp itself glows with its emission
+    assert!(scene.trace(at_lamp).r >= 1.);
+  }
+
+  #[test]
+  fn sphere_light_samples_only_its_visible_cap() {
+    let light = SphereLight::new(point(0., 0., 5.), 1., Color::WHITE);
+    let mut random = Random::with_seed(11);
+
+    for _ in 0..100 {
+      let sample = light.sample_from(Point::ORIGIN, random.next_f64(), random.next_f64(), 4).unwrap();
+
+      assert!((sample.position.distance(light.center) - 1.).abs() < 0.001);
+      assert!(sample.position.z < 5.);
+      assert_eq!(sample.intensity, Color::WHITE / 4.);
+    }
+
+    assert!(light.sample_from(point(0., 0., 4.), 0.5, 0.5, 4).is_none());
+  }
+
   #[test]
   fn color_of_material_is_used_when_ray_hits() {
     let scene = create_test_scene();
@@ -498,7 +1684,8 @@ mod tests {
 
     let color = scene.trace(ray);
 
-    assert_eq!(color, rgb(0.38012764, 0.47515953, 0.28509575));
+    // matches the book now the light's position is a true point; see the test above
+    assert_eq!(color, rgb(0.38065882, 0.47582352, 0.28549411));
   }
 
   #[test]
@@ -506,7 +1693,7 @@ mod tests {
     let scene = create_test_scene();
     let point = point(0., 10., 10.);
 
-    assert!(!scene.is_shadowed(point));
+    assert!(!scene.is_shadowed(point, 0.));
   }
 
   #[test]
@@ -514,7 +1701,7 @@ mod tests {
     let scene = create_test_scene();
     let point = point(10., -10., 10.);
 
-    assert!(scene.is_shadowed(point));
+    assert!(scene.is_shadowed(point, 0.));
   }
 
   #[test]
@@ -522,7 +1709,7 @@ mod tests {
     let scene = create_test_scene();
     let point = point(-20., 20., -20.);
 
-    assert!(!scene.is_shadowed(point));
+    assert!(!scene.is_shadowed(point, 0.));
   }
 
   #[test]
@@ -530,7 +1717,30 @@ mod tests {
     let scene = create_test_scene();
     let point = point(-2., 2., -2.);
 
-    assert!(!scene.is_shadowed(point));
+    assert!(!scene.is_shadowed(point, 0.));
+  }
+
+  #[test]
+  fn transparent_occluders_tint_shadows_by_their_color() {
+    let mut scene = Scene::new();
+    scene.add_light(PointLight::new(point(0., 10., 0.), Color::WHITE));
+    scene.add_object(
+      Sphere::new()
+        .with_material(Material::default().with_color(Color::RED).with_transparency(0.9))
+        .with_transform(Matrix4x4::translate(0., 5., 0.)),
+    );
+    scene.add_object(Plane::new(vec3(0., 1., 0.)));
+
+    // the shadow ray crosses both surfaces of the sphere
+    let transmission = scene.shadow_transmission(point(0., 0.0001, 0.), &scene.lights[0], 0.);
+
+    assert_eq!(transmission, rgb(0.81, 0., 0.));
+
+    let ray = Ray::new(point(0., 1., -3.), vec3(0., -1., 3.).normalize());
+    let shadow = scene.trace(ray);
+
+    assert!(shadow.r > shadow.g);
+    assert_eq!(shadow.g, shadow.b);
   }
 
   #[test]
@@ -546,7 +1756,7 @@ mod tests {
     let hit = Hit::new(scene.nodes[1].deref(), 4.);
     let hits = HitList::from(&[hit]);
 
-    let color = scene.apply_lighting(ray, &hits[0], &hits, 0);
+    let color = scene.apply_lighting(ray, &hits[0], &hits, 0, 1.);
 
     assert_eq!(color, rgb(0.1, 0.1, 0.1));
   }
@@ -562,7 +1772,7 @@ mod tests {
 
     let lighting_data = LightingData::calculate(ray, &hits[0], &hits);
 
-    let color = scene.reflected_color(&lighting_data, 0);
+    let color = scene.reflected_color(&lighting_data, 0, 1.);
 
     assert_eq!(color, Color::BLACK);
   }
@@ -586,9 +1796,266 @@ mod tests {
 
     let lighting_data = LightingData::calculate(ray, &hits[0], &hits);
 
-    let color = scene.reflected_color(&lighting_data, 0);
+    let color = scene.reflected_color(&lighting_data, 0, 1.);
+
+    // the light's w used to be 0 rather than 1, which dimmed this to 0.19007981
+    assert_eq!(color, rgb(0.19034665, 0.23793331, 0.14275998));
+  }
+
+  #[test]
+  fn metal_reflections_take_on_the_metal_color() {
+    let mut scene = create_test_scene();
+    let gold = Material::metal(rgb(1., 0.7, 0.2));
+
+    for material in [Material::default().with_reflective(1.), gold.clone()] {
+      scene.add_object(
+        Plane::new(vec3(0., 1., 0.))
+          .with_material(material)
+          .with_transform(Matrix4x4::translate(0., -1., 0.)),
+      );
+    }
+
+    let ray = Ray::new(point(0.5, 0., -3.), vec3(0., -2f64.sqrt() / 2., 2f64.sqrt() / 2.));
+
+    let colors: Vec<_> = [2, 3]
+      .into_iter()
+      .map(|index| {
+        let hits = HitList::from(&[Hit::new(scene.nodes[index].deref(), 2f64.sqrt())]);
+        let lighting_data = LightingData::calculate(ray, &hits[0], &hits);
+
+        scene.reflected_color(&lighting_data, 0, 1.)
+      })
+      .collect();
+
+    let mirror = colors[0];
+    let metal = colors[1];
+    let tint = metal / mirror;
+
+    assert_ne!(mirror, Color::BLACK);
+    assert_eq!(metal, mirror * gold.reflection_tint_at(2f64.sqrt() / 2.));
+    assert!(tint.r > tint.g && tint.g > tint.b);
+  }
+
+  #[test]
+  fn shlick_should_follow_water_ior_from_normal_to_grazing() {
+    let shape = Plane::new(vec3(0., 1., 0.)).with_material(Material::dielectric(1.33));
+
+    let reflectance = |ray: Ray| {
+      let distance = shape.intersect(ray)[0].distance;
+      let hits = HitList::from(&[Hit::new(&shape, distance)]);
+      let lighting_data = LightingData::calculate(ray, &hits[0], &hits);
+
+      Scene::shlick(&lighting_data)
+    };
+
+    let normal = reflectance(Ray::new(point(0., 1., 0.), vec3(0., -1., 0.)));
+    let grazing = reflectance(Ray::new(point(0., 0.01, -10.), vec3(0., -0.001, 1.).normalize()));
+
+    assert!((normal - 0.02).abs() < 0.001);
+    assert!(grazing > 0.95);
+  }
+
+  #[test]
+  fn energy_conserving_glass_never_outshines_the_incoming_light() {
+    let glass = |conserve_energy| {
+      let mut scene = Scene::new();
+
+      scene.set_environment(GradientSky::new(Color::WHITE, Color::WHITE));
+      scene.add_light(PointLight::new(point(-10., 10., -10.), Color::WHITE));
+      scene.add_object(Sphere::new().with_material(Material::default()
+        .with_specular(0.)
+        .with_reflective(0.9)
+        .with_transparency(0.9)
+        .with_refractivity(1.5)
+        .with_conserve_energy(conserve_energy)));
+
+      scene
+    };
+
+    let brightest = |scene: &Scene| {
+      (0..10)
+        .map(|i| scene.trace(Ray::new(point(i as f64 * 0.1, 0., -5.), vec3(0., 0., 1.))))
+        .map(|color| color.r.max(color.g).max(color.b))
+        .fold(0., f64::max)
+    };
+
+    // the light arriving from every direction is exactly white
+    assert!(brightest(&glass(false)) > 1.);
+    assert!(brightest(&glass(true)) <= 1. + EPSILON);
+  }
+
+  #[test]
+  fn reflected_color_should_use_fresnel_reflectance_for_dielectrics() {
+    let mut scene = create_test_scene();
+
+    scene.add_object(
+      Plane::new(vec3(0., 1., 0.))
+        .with_material(Material::default().with_reflective(1.))
+        .with_transform(Matrix4x4::translate(0., -1., 0.)),
+    );
+    scene.add_object(
+      Plane::new(vec3(0., 1., 0.))
+        .with_material(Material::dielectric(1.33))
+        .with_transform(Matrix4x4::translate(0., -1., 0.)),
+    );
+
+    let ray = Ray::new(point(0.5, 0., -3.), vec3(0., -2f64.sqrt() / 2., 2f64.sqrt() / 2.));
+
+    let colors: Vec<_> = [2, 3]
+      .into_iter()
+      .map(|index| {
+        let hits = HitList::from(&[Hit::new(scene.nodes[index].deref(), 2f64.sqrt())]);
+        let lighting_data = LightingData::calculate(ray, &hits[0], &hits);
+
+        scene.reflected_color(&lighting_data, 0, 1.)
+      })
+      .collect();
+
+    let mirror = colors[0];
+    let water = colors[1];
+
+    assert_ne!(mirror, Color::BLACK);
+    let reflectance = Material::dielectric(1.33).fresnel_reflectance(2f64.sqrt() / 2.);
+
+    assert_eq!(water, mirror * reflectance);
+  }
+
+  #[test]
+  fn plane_lit_from_below_shades_like_plane_lit_from_above() {
+    let shade = |side: f64| {
+      let mut scene = Scene::new();
+
+      scene.add_light(PointLight::new(point(-10., 10. * side, -10.), rgb(1., 1., 1.)));
+      scene.add_object(Plane::new(vec3(0., 1., 0.)));
+
+      scene.trace(Ray::new(point(0., side, -1.), vec3(0., -side, 1.).normalize()))
+    };
+
+    let above = shade(1.);
+    let below = shade(-1.);
+
+    assert!(above.r > 0.5);
+    assert_eq!(below, above);
+  }
+
+  #[test]
+  fn reflection_falloff_fades_distant_reflections() {
+    let build_scene = |falloff: f64, sphere_height: f64| {
+      let mut scene = Scene::new();
+
+      scene.add_light(PointLight::new(point(-10., 10., -10.), rgb(1., 1., 1.)));
+      scene.add_object(
+        Plane::new(vec3(0., 1., 0.))
+          .with_material(Material::default().with_reflective(1.).with_reflection_falloff(falloff)),
+      );
+      scene.add_object(
+        Sphere::new()
+          .with_material(Material::default().with_ambient(1.).with_diffuse(0.).with_specular(0.))
+          .with_transform(Matrix4x4::translate(0., sphere_height, 0.) * Matrix4x4::scale(100., 0.1, 100.)),
+      );
+
+      scene
+    };
+
+    let reflection = |scene: &Scene| {
+      let ray = Ray::new(point(0., 0.05, -1.), vec3(0., -2f64.sqrt() / 2., 2f64.sqrt() / 2.));
+      let hits = HitList::from(&[Hit::new(scene.nodes[0].deref(), 0.05 * 2f64.sqrt())]);
+      let lighting_data = LightingData::calculate(ray, &hits[0], &hits);
+
+      scene.reflected_color(&lighting_data, 0, 1.)
+    };
+
+    // without falloff, the same surface reflects identically at any distance
+    assert_eq!(reflection(&build_scene(f64::INFINITY, 1.)), reflection(&build_scene(f64::INFINITY, 5.)));
+
+    let near = reflection(&build_scene(2., 1.));
+    let far = reflection(&build_scene(2., 5.));
+
+    assert_ne!(far, Color::BLACK);
+    assert!(far.r < near.r);
+    assert!(near.r < reflection(&build_scene(f64::INFINITY, 1.)).r);
+  }
+
+  #[test]
+  fn rough_reflections_blur_a_reflected_edge() {
+    let build_scene = |roughness: f64| {
+      let mut scene = Scene::new();
+
+      scene.add_light(PointLight::new(point(0., 3., -10.), rgb(1., 1., 1.)));
+
+      // a black and white ceiling, split along x = 0, seen in a mirrored floor
+      scene.add_object(
+        Plane::new(vec3(0., -1., 0.))
+          .with_material(Material::default()
+            .with_pattern(StripedPattern::new(Color::WHITE, Color::BLACK).with_scale(100.))
+            .with_ambient(1.)
+            .with_diffuse(0.)
+            .with_specular(0.))
+          .with_transform(Matrix4x4::translate(0., 5., 0.)),
+      );
+      scene.add_object(
+        Plane::new(vec3(0., 1., 0.))
+          .with_material(Material::default()
+            .with_ambient(0.)
+            .with_diffuse(0.)
+            .with_specular(0.)
+            .with_reflective(1.)
+            .with_reflection_roughness(roughness)),
+      );
+
+      scene
+    };
+
+    // the ceiling is 5 units above the floor, so a roughness of 0.3 (a 27 degree cone) blurs the
+    // edge at most 5 * tan(27) ~= 2.55 units either side, whichever directions are sampled
+    let sample_edge = |scene: &Scene| -> Vec<f64> {
+      (-10..=10)
+        .map(|step| scene.trace(Ray::new(point(step as f64 * 0.4, 1., 0.), vec3(0., -1., 0.))).r)
+        .collect()
+    };
+
+    let smooth = sample_edge(&build_scene(0.));
+    let rough = sample_edge(&build_scene(0.3));
+
+    let is_grey = |value: &f64| *value > 0.05 && *value < 0.95;
+
+    assert!(smooth.iter().all(|value| *value == 0. || *value == 1.), "{:?}", smooth);
+    assert!(rough[9..=11].iter().all(is_grey), "{:?}", rough);
+
+    // beyond the cone's reach, the blur makes no difference
+    assert!(rough[..4].iter().all(|value| *value == 0.), "{:?}", rough);
+    assert!(rough[17..].iter().all(|value| *value == 1.), "{:?}", rough);
+  }
+
+  #[test]
+  fn reflected_color_follows_reflectivity_map() {
+    let mut scene = create_test_scene();
+
+    scene.add_object(
+      Plane::new(vec3(0., 1., 0.))
+        .with_material(Material::default()
+          .with_reflective(1.)
+          .with_reflectivity_map(CheckerPattern::new(Color::WHITE, Color::BLACK)))
+        .with_transform(Matrix4x4::translate(0., -1., 0.)),
+    );
 
-    assert_eq!(color, rgb(0.19007981, 0.23759975, 0.14255986));
+    let object = scene.nodes[2].deref();
+    let direction = vec3(0., -2f64.sqrt() / 2., 2f64.sqrt() / 2.);
+
+    let colors: Vec<_> = [-0.5, 0.5]
+      .into_iter()
+      .map(|x| {
+        let ray = Ray::new(point(x, 0., -3.), direction);
+        let hits = HitList::from(&[Hit::new(object, 2f64.sqrt())]);
+        let lighting_data = LightingData::calculate(ray, &hits[0], &hits);
+
+        scene.reflected_color(&lighting_data, 0, 1.)
+      })
+      .collect();
+
+    // both rays reflect towards the spheres, but only one lands on a reflective cell
+    assert_eq!(colors[0], Color::BLACK);
+    assert_ne!(colors[1], Color::BLACK);
   }
 
   #[test]
@@ -604,7 +2071,7 @@ mod tests {
 
     let lighting_data = LightingData::calculate(ray, &hits[0], &hits);
 
-    let color = scene.refracted_color(&lighting_data, 5);
+    let color = scene.refracted_color(&lighting_data, 5, 1.);
 
     assert_eq!(color, Color::BLACK);
   }
@@ -631,7 +2098,7 @@ mod tests {
 
     let lighting_data = LightingData::calculate(ray, &hits[0], &hits);
 
-    let color = scene.refracted_color(&lighting_data, Scene::MAX_DEPTH);
+    let color = scene.refracted_color(&lighting_data, Scene::MAX_DEPTH, 1.);
 
     assert_eq!(color, Color::BLACK);
   }
@@ -658,16 +2125,267 @@ mod tests {
 
     let lighting_data = LightingData::calculate(ray, &hits[0], &hits);
 
-    let color = scene.refracted_color(&lighting_data, 5);
+    let color = scene.refracted_color(&lighting_data, 5, 1.);
 
     assert_eq!(color, Color::BLACK);
   }
 
+  #[test]
+  fn refracted_rays_bend_by_snells_law() {
+    let mut scene = Scene::new();
+
+    scene.add_light(PointLight::new(point(0., 0., -10.), rgb(1., 1., 1.)));
+
+    // a wall whose brightness is its x coordinate, set inside a block of glass whose face is at z = 0
+    scene.add_object(
+      Plane::new(vec3(0., 1., 0.))
+        .with_material(Material::default()
+          .with_pattern(GradientPattern::new(Color::BLACK, Color::WHITE).with_scale(20.))
+          .with_ambient(1.)
+          .with_diffuse(0.)
+          .with_specular(0.))
+        .with_transform(Matrix4x4::translate(0., 0., 10.) * Matrix4x4::rotate_x(PI / 2.)),
+    );
+    scene.add_object(
+      Cube::new()
+        .with_material(Material::default()
+          .with_ambient(0.)
+          .with_diffuse(0.)
+          .with_specular(0.)
+          .with_transparency(1.)
+          .with_refractivity(1.5))
+        .with_transform(Matrix4x4::translate(0., 0., 10.) * Matrix4x4::scale(20., 20., 10.)),
+    );
+
+    // entering at 30 degrees, sin(t) = sin(30) / 1.5 = 1/3, so tan(t) = 1 / sqrt(8)
+    let ray = Ray::new(point(0., 0., -5.), vec3(0.5, 0., 3f64.sqrt() / 2.));
+    let expected = 5. * (PI / 6.).tan() + 10. / 8f64.sqrt();
+
+    let color = scene.trace(ray);
+
+    assert!((color.r * 20. - expected).abs() < 1e-3, "{} != {}", color.r * 20., expected);
+  }
+
+  #[test]
+  fn refracted_rays_are_counted_apart_from_reflections() {
+    let mut scene = Scene::new();
+
+    scene.add_object(
+      Sphere::new()
+        .with_material(Material::default()
+          .with_transparency(1.)
+          .with_refractivity(1.5)),
+    );
+
+    scene.trace(Ray::new(point(0., 0., -5.), vec3(0., 0., 1.)));
+
+    let stats = scene.stats();
+
+    assert_eq!(stats.refraction_rays, 2);
+    assert_eq!(stats.reflection_rays, 0);
+  }
+
+  #[test]
+  fn thin_glass_pane_shows_what_is_behind_it_undistorted() {
+    let build_scene = |pane: Option<Material>| {
+      let mut scene = Scene::new();
+
+      scene.add_light(PointLight::new(point(0., 0., -10.), rgb(1., 1., 1.)));
+
+      // a finely striped wall, seen at an angle through a pane that only filters light
+      scene.add_object(
+        Plane::new(vec3(0., 1., 0.))
+          .with_material(Material::default()
+            .with_pattern(StripedPattern::new(Color::WHITE, Color::BLACK).with_scale(0.25))
+            .with_ambient(1.)
+            .with_diffuse(0.)
+            .with_specular(0.))
+          .with_transform(Matrix4x4::translate(0., 0., 10.) * Matrix4x4::rotate_x(PI / 2.)),
+      );
+
+      if let Some(material) = pane {
+        scene.add_object(
+          Plane::new(vec3(0., 1., 0.))
+            .with_material(material)
+            .with_transform(Matrix4x4::rotate_x(PI / 2.)),
+        );
+      }
+
+      scene
+    };
+
+    let glass = Material::default()
+      .with_ambient(0.)
+      .with_diffuse(0.)
+      .with_specular(0.)
+      .with_transparency(1.)
+      .with_refractivity(1.5);
+
+    let clear = build_scene(None);
+    let thin = build_scene(Some(glass.clone().with_thin(true)));
+    let thick = build_scene(Some(glass));
+
+    // the rays land clear of the stripe edges, so the pane's tiny surface offset can't flip one
+    let mismatches = |scene: &Scene| {
+      (0..20)
+        .map(|index| Ray::new(point(-3.06, 0., -5.), vec3(0.3 + index as f64 * 0.01, 0., 1.)))
+        .filter(|&ray| scene.trace(ray) != clear.trace(ray))
+        .count()
+    };
+
+    assert_eq!(mismatches(&thin), 0);
+    assert!(mismatches(&thick) > 0);
+  }
+
+  #[test]
+  fn frosted_glass_blurs_what_is_seen_through_it() {
+    let build_scene = |roughness: f64| {
+      let mut scene = Scene::new();
+
+      scene.add_light(PointLight::new(point(0., 0., -10.), rgb(1., 1., 1.)));
+
+      // a finely striped wall, seen through a glass sphere that doesn't bend light
+      scene.add_object(
+        Plane::new(vec3(0., 1., 0.))
+          .with_material(Material::default()
+            .with_pattern(StripedPattern::new(Color::WHITE, Color::BLACK).with_scale(0.25))
+            .with_ambient(1.)
+            .with_diffuse(0.)
+            .with_specular(0.))
+          .with_transform(Matrix4x4::translate(0., 0., 10.) * Matrix4x4::rotate_x(PI / 2.)),
+      );
+      scene.add_object(
+        Sphere::new()
+          .with_material(Material::default()
+            .with_ambient(0.)
+            .with_diffuse(0.)
+            .with_specular(0.)
+            .with_transparency(1.)
+            .with_refractivity(1.)
+            .with_refraction_roughness(roughness)),
+      );
+
+      scene
+    };
+
+    let sample_wall = |scene: &Scene| -> Vec<f64> {
+      (-10..=10)
+        .map(|step| scene.trace(Ray::new(point(step as f64 * 0.04 + 0.015, 0., -5.), vec3(0., 0., 1.))).r)
+        .collect()
+    };
+
+    let mut wall = build_scene(0.);
+    wall.nodes.pop();
+
+    let clear = sample_wall(&build_scene(0.));
+    let frosted = sample_wall(&build_scene(0.2));
+
+    let is_grey = |value: &f64| *value > 0.05 && *value < 0.95;
+
+    // clear glass with an index of 1 transmits the wall unchanged
+    assert_eq!(clear, sample_wall(&wall));
+    assert!(frosted.iter().filter(|value| is_grey(value)).count() >= 10, "{:?}", frosted);
+  }
+
+  #[test]
+  fn russian_roulette_matches_full_depth_on_average_with_fewer_rays() {
+    let build_scene = |roulette_depth: Option<usize>| {
+      let mut scene = Scene::new();
+      let mirror = Material::default().with_color(rgb(0.2, 0.2, 0.2)).with_reflective(0.9);
+
+      scene.set_max_depth(20);
+      scene.set_russian_roulette(roulette_depth);
+      scene.add_light(PointLight::new(point(-2., 0.5, -5.), rgb(1., 1., 1.)));
+      scene.add_object(
+        Sphere::new()
+          .with_material(Material::default().with_color(rgb(1., 0.2, 0.2)))
+          .with_transform(Matrix4x4::scale(0.5, 0.5, 0.5)),
+      );
+      scene.add_object(
+        Plane::new(vec3(0., 1., 0.))
+          .with_material(mirror.clone())
+          .with_transform(Matrix4x4::translate(0., -1.5, 0.)),
+      );
+      scene.add_object(
+        Plane::new(vec3(0., -1., 0.))
+          .with_material(mirror)
+          .with_transform(Matrix4x4::translate(0., 1.5, 0.)),
+      );
+
+      scene
+    };
+
+    let render = |scene: &Scene| {
+      let mut random = Random::with_seed(42);
+      let mut total = Color::BLACK;
+
+      for _ in 0..4000 {
+        let target = point(random.range(-3., 3.), random.range(-4., 4.), 5.);
+        let origin = point(0., 0., -5.);
+
+        total += scene.trace(Ray::new(origin, (target - origin).normalize()));
+      }
+
+      (total.luminance() / 4000., scene.stats().reflection_rays)
+    };
+
+    let (full, full_rays) = render(&build_scene(None));
+    let (roulette, roulette_rays) = render(&build_scene(Some(2)));
+
+    assert!((roulette - full).abs() / full < 0.05, "{} vs {}", roulette, full);
+    assert!(roulette_rays < full_rays * 3 / 4, "{} vs {}", roulette_rays, full_rays);
+  }
+
+  #[test]
+  fn light_sampling_converges_to_all_lights() {
+    let build_scene = |light_samples: Option<usize>| {
+      let mut scene = Scene::new();
+
+      for index in 0..16 {
+        let angle = index as f64 * PI / 8.;
+        let color = rgb(0.2 + 0.05 * index as f64, 0.4, 1. - 0.05 * index as f64);
+
+        scene.add_light(PointLight::new(point(angle.cos() * 6., 2. + (index % 4) as f64, angle.sin() * 6.), color * 0.25));
+      }
+
+      scene.add_object(Sphere::new().with_transform(Matrix4x4::translate(0., 1., 0.)));
+      scene.add_object(Plane::new(vec3(0., 1., 0.)));
+
+      match light_samples {
+        Some(count) => scene.with_light_sampling(count),
+        None => scene,
+      }
+    };
+
+    let render = |scene: &Scene| {
+      let mut random = Random::with_seed(7);
+      let mut total = Color::BLACK;
+
+      for _ in 0..4000 {
+        let target = point(random.range(-4., 4.), 0., random.range(-4., 4.));
+        let origin = point(0., 6., -8.);
+
+        total += scene.trace(Ray::new(origin, (target - origin).normalize()));
+      }
+
+      (total / 4000., scene.stats().shadow_rays)
+    };
+
+    let (full, full_shadow_rays) = render(&build_scene(None));
+    let (sampled, sampled_shadow_rays) = render(&build_scene(Some(4)));
+
+    for (full, sampled) in [(full.r, sampled.r), (full.g, sampled.g), (full.b, sampled.b)] {
+      assert!((sampled - full).abs() / full < 0.03, "{} vs {}", sampled, full);
+    }
+
+    assert!(sampled_shadow_rays * 3 < full_shadow_rays);
+  }
+
   /// Creates a default scene with two spheres a single light source.
   fn create_test_scene() -> Scene {
     let mut scene = Scene::new();
 
-    scene.add_light(PointLight::new(vec3(-10., 10., -10.), Color::WHITE));
+    scene.add_light(PointLight::new(point(-10., 10., -10.), Color::WHITE));
 
     scene.add_object(
       Sphere::new()
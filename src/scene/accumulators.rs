@@ -0,0 +1,121 @@
+//! Progressive rendering, which refines a noisy image by averaging passes over time.
+
+use crate::graphics::Canvas;
+use crate::maths::Color;
+use crate::scene::{Camera, Scene};
+
+/// Holds the running average of many single-sample passes over an image.
+///
+/// Depth of field, area lights and glossy surfaces are noisy at one sample per pixel; each
+/// call to `add_sample` traces another pass and blends it in, so a preview sharpens over time.
+pub struct Accumulator {
+  average: Canvas,
+  samples: usize,
+}
+
+impl Accumulator {
+  /// Creates an empty accumulator for an image of the given size.
+  pub fn new(width: u32, height: u32) -> Self {
+    Self {
+      average: Canvas::new(width, height),
+      samples: 0,
+    }
+  }
+
+  /// Traces one more sample per pixel and blends it into the running average.
+  ///
+  /// Panics if the camera's image size differs from the accumulator's.
+  pub fn add_sample(&mut self, camera: &Camera, scene: &Scene) {
+    assert!(
+      camera.width() as usize == self.average.width() && camera.height() as usize == self.average.height(),
+      "Camera renders {}x{} images, but the accumulator is {}x{}",
+      camera.width(),
+      camera.height(),
+      self.average.width(),
+      self.average.height(),
+    );
+
+    let sample = camera.render_sample(scene, self.samples as u64);
+
+    self.samples += 1;
+
+    // an incremental mean, so every pass is weighted equally
+    let weight = 1. / self.samples as f64;
+
+    for y in 0..self.average.height() {
+      for x in 0..self.average.width() {
+        let average = self.average.get_pixel(x, y);
+        let color = sample.as_slice()[x + y * sample.width()];
+
+        self.average.set_pixel(x, y, average + (color - average) * weight);
+      }
+    }
+  }
+
+  /// The number of passes blended into the average so far.
+  pub fn samples(&self) -> usize {
+    self.samples
+  }
+
+  /// The averaged image so far.
+  pub fn canvas(&self) -> &Canvas {
+    &self.average
+  }
+
+  /// Discards every pass, e.g. after the camera or scene changes.
+  pub fn reset(&mut self) {
+    self.average.fill(Color::BLACK);
+    self.samples = 0;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::maths::Matrix4x4;
+  use crate::scene::{Cube, Material, Projection};
+
+  use super::*;
+
+  #[test]
+  fn accumulated_edge_converges_on_its_coverage() {
+    let mut scene = Scene::new();
+
+    // a glowing cube covers the right half of the only pixel
+    scene.add_object(
+      Cube::new()
+        .with_material(Material::default()
+          .with_color(Color::BLACK)
+          .with_ambient(0.)
+          .with_diffuse(0.)
+          .with_specular(0.)
+          .with_emission(Color::WHITE))
+        .with_transform(Matrix4x4::translate(100., 0., -10.) * Matrix4x4::scale(100., 100., 1.)),
+    );
+
+    let mut camera = Camera::new(1, 1, 1.).with_projection(Projection::Orthographic { height: 2. });
+    camera.transform = Matrix4x4::identity();
+
+    let mut accumulator = Accumulator::new(1, 1);
+    let mut error = |passes: usize| {
+      while accumulator.samples() < passes {
+        accumulator.add_sample(&camera, &scene);
+      }
+
+      (accumulator.canvas().as_slice()[0].r - 0.5).abs()
+    };
+
+    let coarse = error(4);
+    let fine = error(1024);
+
+    assert!(fine < 0.05);
+    assert!(fine < coarse);
+  }
+
+  #[test]
+  #[should_panic(expected = "Camera renders 4x2 images, but the accumulator is 2x2")]
+  fn mismatched_camera_sizes_are_rejected() {
+    let mut accumulator = Accumulator::new(2, 2);
+
+    accumulator.add_sample(&Camera::new(4, 2, 1.), &Scene::new());
+  }
+}
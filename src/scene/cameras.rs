@@ -1,6 +1,32 @@
-use crate::graphics::Canvas;
-use crate::maths::{Matrix4x4, point, Ray, vec3};
-use crate::scene::Scene;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use crate::graphics::{Canvas, OutputSink};
+use crate::maths::{Color, Filter, Matrix4x4, point, Point, Random, Ray, rgb, Sampler, StoragePrecision, vec3};
+use crate::scene::{RenderStats, Scene};
+
+/// How a camera projects the scene onto its image.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Projection {
+  /// Rays diverge from a single eye point, so distant objects appear smaller.
+  #[default]
+  Perspective,
+  /// Rays run parallel along the view direction, so size doesn't change with distance.
+  ///
+  /// The height is the extent of the view in world units; the width follows the aspect ratio.
+  Orthographic { height: f64 },
+}
+
+/// A surface normal projected onto the image, for drawing debug overlays.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct NormalSegment {
+  /// The pixel position of the surface point.
+  pub start: (f64, f64),
+  /// The pixel position of the tip of the normal.
+  pub end: (f64, f64),
+  /// The color encoding the normal's direction.
+  pub color: Color,
+}
 
 /// A camera for orientating a view transform.
 #[derive(Clone)]
@@ -11,6 +37,13 @@ pub struct Camera {
   half_height: f64,
   field_of_view: f64,
   pixel_size: f64,
+  samples: usize,
+  sampler: Sampler,
+  filter: Filter,
+  shutter: f64,
+  exposure: f64,
+  vignette: f64,
+  projection: Projection,
   pub transform: Matrix4x4,
 }
 
@@ -44,14 +77,96 @@ impl Camera {
       half_height,
       field_of_view,
       pixel_size: (half_width * 2.) / width as f64,
+      samples: 1,
+      sampler: Sampler::Random,
+      filter: Filter::Box,
+      shutter: 0.,
+      exposure: 0.,
+      vignette: 0.,
+      projection: Projection::Perspective,
       transform: Matrix4x4::look_at(from, to, up),
     }
   }
 
+  /// The width of the rendered image in pixels.
+  pub fn width(&self) -> u32 {
+    self.width
+  }
+
+  /// The height of the rendered image in pixels.
+  pub fn height(&self) -> u32 {
+    self.height
+  }
+
+  /// The vertical or horizontal field of view (whichever is larger), in radians.
+  pub fn field_of_view(&self) -> f64 {
+    self.field_of_view
+  }
+
+  /// The projection used to cast rays from the camera.
+  pub fn projection(&self) -> Projection {
+    self.projection
+  }
+
+  /// Switches the projection used for subsequent renders, keeping the transform and resolution.
+  pub fn set_projection(&mut self, projection: Projection) {
+    self.projection = projection;
+  }
+
+  /// Sets the projection used to cast rays from the camera.
+  pub fn with_projection(self, projection: Projection) -> Self {
+    Self { projection, ..self }
+  }
+
+  /// Sets the number of jittered samples traced and averaged per pixel.
+  pub fn with_samples(self, samples: usize) -> Self {
+    Self { samples: samples.max(1), ..self }
+  }
+
+  /// Sets how jittered samples are placed within each pixel.
+  pub fn with_sampler(self, sampler: Sampler) -> Self {
+    Self { sampler, ..self }
+  }
+
+  /// Sets the reconstruction filter used to weight samples by where they fall within each pixel.
+  pub fn with_filter(self, filter: Filter) -> Self {
+    Self { filter, ..self }
+  }
+
+  /// Sets how long the shutter stays open; each sample is cast at a random time within it.
+  ///
+  /// Moving objects blur over the interval, and move over times [0, 1].
+  pub fn with_shutter(self, shutter: f64) -> Self {
+    Self { shutter: shutter.max(0.), ..self }
+  }
+
+  /// Sets the exposure compensation in stops (EV); each stop doubles the brightness.
+  pub fn with_exposure(self, exposure: f64) -> Self {
+    Self { exposure, ..self }
+  }
+
+  /// Sets how strongly the image darkens towards its corners, from 0 (none) to 1 (full cos⁴ falloff).
+  pub fn with_vignette(self, vignette: f64) -> Self {
+    Self { vignette: vignette.clamp(0., 1.), ..self }
+  }
+
   /// Creates a ray for the given pixel (x, y) on the camera.
   pub fn ray_for_pixel(&self, x: usize, y: usize) -> Ray {
-    let x_offset = (x as f64 + 0.5) * self.pixel_size;
-    let y_offset = (y as f64 + 0.5) * self.pixel_size;
+    self.ray_for_position(x as f64 + 0.5, y as f64 + 0.5)
+  }
+
+  /// Yields the primary ray through the center of every pixel, row by row, without tracing them.
+  pub fn ray_grid(&self) -> impl Iterator<Item=(usize, usize, Ray)> + '_ {
+    let width = self.width as usize;
+    let height = self.height as usize;
+
+    (0..height).flat_map(move |y| (0..width).map(move |x| (x, y, self.ray_for_pixel(x, y))))
+  }
+
+  /// Creates a ray through the given (fractional) pixel position on the camera.
+  fn ray_for_position(&self, x: f64, y: f64) -> Ray {
+    let x_offset = x * self.pixel_size;
+    let y_offset = y * self.pixel_size;
 
     let world_x = self.half_width - x_offset;
     let world_y = self.half_height - y_offset;
@@ -61,33 +176,354 @@ impl Camera {
       .invert()
       .expect("Failed to invert camera transform");
 
-    let pixel = inverse * point(world_x, world_y, -1.);
-    let origin = inverse * point(0., 0., 0.);
-    let direction = (pixel - origin).normalize();
+    match self.projection {
+      Projection::Perspective => {
+        let pixel = inverse * point(world_x, world_y, -1.);
+        let origin = inverse * point(0., 0., 0.);
+        let direction = (pixel - origin).normalize();
+
+        Ray::new(origin, direction)
+      }
+      Projection::Orthographic { height } => {
+        // rescale the image plane to cover the requested height
+        let scale = height / (self.half_height * 2.);
+
+        let origin = inverse * point(world_x * scale, world_y * scale, 0.);
+        let direction = (inverse * vec3(0., 0., -1.)).normalize();
+
+        Ray::new(origin, direction)
+      }
+    }
+  }
+
+  /// Projects the given world point onto the image, as a (fractional) pixel position.
+  ///
+  /// Returns nothing for points behind a perspective camera.
+  pub fn project_point(&self, world_point: Point) -> Option<(f64, f64)> {
+    let camera_point = self.transform * world_point;
+
+    let (world_x, world_y) = match self.projection {
+      Projection::Perspective => {
+        if camera_point.z >= 0. {
+          return None;
+        }
+
+        (camera_point.x / -camera_point.z, camera_point.y / -camera_point.z)
+      }
+      Projection::Orthographic { height } => {
+        let scale = height / (self.half_height * 2.);
+
+        (camera_point.x / scale, camera_point.y / scale)
+      }
+    };
+
+    Some(((self.half_width - world_x) / self.pixel_size, (self.half_height - world_y) / self.pixel_size))
+  }
+
+  /// Finds the surface normals seen on a sparse grid of pixels, as screen-space segments.
+  ///
+  /// Each segment runs from a hit point along its (unflipped) normal for the given world-space
+  /// length, colored by the normal's direction, so back-facing or broken normals stand out.
+  pub fn normal_segments(&self, scene: &Scene, length: f64) -> Vec<NormalSegment> {
+    const SPACING: usize = 8;
+
+    let mut segments = Vec::new();
+
+    for (x, y, ray) in self.ray_grid() {
+      if x % SPACING != SPACING / 2 || y % SPACING != SPACING / 2 {
+        continue;
+      }
+
+      let Some(hit) = scene.intersect_closest(ray, 0., f64::MAX) else {
+        continue;
+      };
+
+      let position = ray.position(hit.distance);
+      let normal = hit.normal_at(position, ray.time);
+      let color = rgb(normal.x + 1., normal.y + 1., normal.z + 1.) * 0.5;
+
+      if let (Some(start), Some(end)) = (self.project_point(position), self.project_point(position + normal * length)) {
+        segments.push(NormalSegment { start, end, color });
+      }
+    }
 
-    Ray::new(origin, direction)
+    segments
+  }
+
+  /// Renders the given scene with its surface normals drawn over it, for debugging normals.
+  pub fn render_with_normal_overlay(&self, scene: &Scene, length: f64) -> Canvas {
+    let mut canvas = self.render(scene);
+
+    for segment in self.normal_segments(scene, length) {
+      canvas.draw_line(segment.start, segment.end, segment.color);
+    }
+
+    canvas
   }
 
   /// Renders an image of the given scene through the lens of the camera.
   pub fn render(&self, scene: &Scene) -> Canvas {
+    self.render_with_storage(scene)
+  }
+
+  /// Renders an image of the given scene into a canvas storing its pixels at the precision `P`.
+  ///
+  /// Tracing is done in `f64` either way; `render_with_storage::<f32>` halves the memory of a
+  /// large image, at a tiny cost in accuracy, but doesn't make the render any faster.
+  pub fn render_with_storage<P: StoragePrecision>(&self, scene: &Scene) -> Canvas<P> {
+    let mut canvas = Canvas::with_precision(self.width, self.height);
+
+    self.render_to_sink(scene, &mut canvas);
+
+    canvas
+  }
+
+  /// Renders the given scene row by row into the given sink, flushing it once complete.
+  pub fn render_to_sink(&self, scene: &Scene, sink: &mut impl OutputSink) {
+    for y in 0..self.height as usize {
+      for x in 0..self.width as usize {
+        sink.write_pixel(x, y, self.render_pixel(scene, x as isize, y as isize));
+      }
+    }
+
+    sink.flush();
+  }
+
+  /// Renders the given scene row by row until the time budget runs out.
+  ///
+  /// Returns the canvas, with any unrendered rows left black, and whether every row was rendered.
+  pub fn render_with_timeout(&self, scene: &Scene, timeout: Duration) -> (Canvas, bool) {
+    let deadline = Instant::now() + timeout;
     let mut canvas = Canvas::new(self.width, self.height);
 
     for y in 0..self.height as usize {
+      if Instant::now() >= deadline {
+        return (canvas, false);
+      }
+
       for x in 0..self.width as usize {
-        let ray = self.ray_for_pixel(x, y);
-        let color = scene.trace(ray);
+        canvas.set_pixel(x, y, self.render_pixel(scene, x as isize, y as isize));
+      }
+    }
+
+    (canvas, true)
+  }
+
+  /// Renders the given region of the image, starting at pixel (x, y).
+  pub fn render_region(&self, scene: &Scene, x: u32, y: u32, width: u32, height: u32) -> Canvas {
+    self.render_region_with_overscan(scene, x, y, width, height, 0)
+  }
+
+  /// Renders the given region of the image, with an extra border of overscan pixels on every side.
+  ///
+  /// The border gives post-processing, like blurs, valid neighbours at the edges of a tile; crop
+  /// it away afterwards with `Canvas::crop(overscan, overscan, width, height)`. Border pixels past
+  /// the edges of the image are rendered as if the image continued.
+  pub fn render_region_with_overscan(&self, scene: &Scene, x: u32, y: u32, width: u32, height: u32, overscan: u32) -> Canvas {
+    let mut canvas = Canvas::new(width + overscan * 2, height + overscan * 2);
 
-        canvas.set_pixel(x, y, color);
+    let left = x as isize - overscan as isize;
+    let top = y as isize - overscan as isize;
+
+    for row in 0..canvas.height() {
+      for column in 0..canvas.width() {
+        let color = self.render_pixel(scene, left + column as isize, top + row as isize);
+
+        canvas.set_pixel(column, row, color);
       }
     }
 
     canvas
   }
+
+  /// Renders only the given pixels of the image, pairing each with its color.
+  ///
+  /// Each pixel is traced exactly as a full render would trace it, so this is handy for
+  /// isolating a single problematic pixel.
+  pub fn render_pixels(&self, scene: &Scene, pixels: &[(usize, usize)]) -> Vec<((usize, usize), Color)> {
+    pixels
+      .iter()
+      .map(|&(x, y)| ((x, y), self.render_pixel(scene, x as isize, y as isize)))
+      .collect()
+  }
+
+  /// Renders a quick, rough image of the given scene for checking composition.
+  ///
+  /// The scene is traced at 1/scale of the camera's resolution and upscaled back to full size.
+  pub fn render_preview(&self, scene: &Scene, scale: u32) -> Canvas {
+    let scale = scale.max(1);
+    let width = (self.width / scale).max(1);
+    let height = (self.height / scale).max(1);
+
+    // only the size-derived fields change; everything else carries over from this camera
+    let sized = Self::new(width, height, self.field_of_view);
+    let preview = Self {
+      width,
+      height,
+      half_width: sized.half_width,
+      half_height: sized.half_height,
+      pixel_size: sized.pixel_size,
+      ..self.clone()
+    };
+
+    preview.render(scene).resize_nearest(self.width, self.height)
+  }
+
+  /// Renders an image of the given scene, along with statistics on the work performed.
+  pub fn render_with_stats(&self, scene: &Scene) -> (Canvas, RenderStats) {
+    scene.reset_stats();
+
+    let canvas = self.render(scene);
+
+    (canvas, scene.stats())
+  }
+
+  /// Computes the color of the given pixel, with the camera's exposure and vignette applied.
+  ///
+  /// Pixels outside the image are rendered as if the image plane continued past its edges.
+  fn render_pixel(&self, scene: &Scene, x: isize, y: isize) -> Color {
+    self.develop(self.sample_pixel(scene, x, y), x, y)
+  }
+
+  /// Renders a single jittered sample of every pixel, for progressive refinement.
+  ///
+  /// Each pass jitters pixels differently, so averaging passes converges on the anti-aliased
+  /// image; the camera's own sample count and filter are ignored.
+  pub fn render_sample(&self, scene: &Scene, pass: u64) -> Canvas {
+    Canvas::from_fn(self.width, self.height, |x, y| {
+      let pixel_index = x as u64 + y as u64 * self.width as u64;
+      let mut random = Random::with_seed(pixel_index ^ pass.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+
+      // halton offsets follow the pass, so every pixel walks the same well-spread sequence
+      let (dx, dy) = self.sampler.sample_2d(pass, &mut random);
+      let time = random.range(0., self.shutter);
+      let ray = self.ray_for_position(x as f64 + dx, y as f64 + dy).with_time(time);
+
+      self.develop(scene.trace(ray), x as isize, y as isize)
+    })
+  }
+
+  /// Applies the camera's exposure and vignetting to the traced color of the given pixel.
+  fn develop(&self, color: Color, x: isize, y: isize) -> Color {
+    if self.exposure == 0. && self.vignette == 0. {
+      return color;
+    }
+
+    color * self.exposure.exp2() * self.vignette_at(x as f64 + 0.5, y as f64 + 0.5)
+  }
+
+  /// Computes the darkening factor from vignetting at the given (fractional) pixel position.
+  ///
+  /// Natural vignetting falls off with the 4th power of the cosine of the angle from the optical axis.
+  fn vignette_at(&self, x: f64, y: f64) -> f64 {
+    let offset_x = self.half_width - x * self.pixel_size;
+    let offset_y = self.half_height - y * self.pixel_size;
+
+    // the image plane sits at distance 1, so cos² = 1 / (1 + r²)
+    let cos2 = 1. / (1. + offset_x * offset_x + offset_y * offset_y);
+
+    1. - self.vignette * (1. - cos2 * cos2)
+  }
+
+  /// Computes the color of the given pixel, averaging over the camera's samples.
+  fn sample_pixel(&self, scene: &Scene, x: isize, y: isize) -> Color {
+    if self.samples == 1 && self.shutter == 0. {
+      return scene.trace(self.ray_for_position(x as f64 + 0.5, y as f64 + 0.5));
+    }
+
+    // seed each pixel separately so that renders are reproducible; overscan pixels past the
+    // edges have negative coordinates, so the index wraps rather than overflowing
+    let pixel_index = (x as i64).wrapping_add((y as i64).wrapping_mul(self.width as i64)) as u64;
+    let mut random = Random::with_seed(pixel_index);
+    let mut offsets = Vec::with_capacity(self.samples);
+    let mut colors = Vec::with_capacity(self.samples);
+
+    for sample in 0..self.samples as u64 {
+      let (dx, dy) = if self.samples > 1 {
+        // each pixel takes its own run of the sequence, so neighbours don't share a pattern
+        self.sampler.sample_2d(pixel_index.wrapping_mul(self.samples as u64).wrapping_add(sample), &mut random)
+      } else {
+        (0.5, 0.5)
+      };
+
+      let time = random.range(0., self.shutter);
+      let ray = self.ray_for_position(x as f64 + dx, y as f64 + dy).with_time(time);
+
+      offsets.push((dx, dy));
+      colors.push(scene.trace(ray));
+    }
+
+    self.filter
+      .weights(&offsets)
+      .into_iter()
+      .zip(colors)
+      .fold(Color::BLACK, |color, (weight, sample)| color + sample * weight)
+  }
+
+  /// Renders a red-cyan anaglyph of the given scene for viewing with 3D glasses.
+  ///
+  /// The left and right eyes are offset by half the separation along the camera's local x
+  /// axis; the left eye's luminance goes to the red channel and the right eye's to green and blue.
+  pub fn render_stereo(&self, scene: &Scene, eye_separation: f64) -> Canvas {
+    let mut left = self.with_eye_offset(eye_separation / 2.).render(scene);
+    let mut right = self.with_eye_offset(-eye_separation / 2.).render(scene);
+
+    let mut canvas = Canvas::new(self.width, self.height);
+
+    for y in 0..self.height as usize {
+      for x in 0..self.width as usize {
+        let red = left.get_pixel(x, y).luminance();
+        let cyan = right.get_pixel(x, y).luminance();
+
+        canvas.set_pixel(x, y, rgb(red, cyan, cyan));
+      }
+    }
+
+    canvas
+  }
+
+  /// Creates a copy of this camera shifted along its local x axis.
+  ///
+  /// Positive offsets move towards the left of the rendered image.
+  fn with_eye_offset(&self, offset: f64) -> Self {
+    let transform = Matrix4x4::translate(-offset, 0., 0.) * self.transform;
+
+    Self { transform, ..self.clone() }
+  }
+
+  /// Renders the given scene directly into a .png stream, one row at a time.
+  ///
+  /// Unlike `render`, this never holds the full floating point canvas in memory, which
+  /// makes it suitable for very large output images.
+  pub fn render_to_png_stream(&self, scene: &Scene, writer: impl Write) -> anyhow::Result<()> {
+    let mut encoder = png::Encoder::new(writer, self.width, self.height);
+
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    let mut stream = writer.stream_writer()?;
+    let mut row = vec![0u8; self.width as usize * 4];
+
+    for y in 0..self.height as usize {
+      for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+        pixel.copy_from_slice(&self.render_pixel(scene, x as isize, y as isize).to_rgba8());
+      }
+
+      stream.write_all(&row)?;
+    }
+
+    stream.finish()?;
+    writer.finish()?;
+
+    Ok(())
+  }
 }
 
 #[cfg(test)]
 mod tests {
   use crate::maths::{ApproxEq, PI, vec3};
+  use crate::scene::{Material, Plane, PointLight, Sphere};
 
   use super::*;
 
@@ -127,6 +563,41 @@ mod tests {
     assert_eq!(ray.direction, vec3(0.66519, 0.33259, -0.66851));
   }
 
+  #[test]
+  fn project_point_inverts_ray_for_pixel() {
+    let mut camera = Camera::new(201, 101, PI / 2.);
+    camera.transform = Matrix4x4::rotate_y(PI / 4.) * Matrix4x4::translate(0., -2., 5.);
+
+    for (x, y) in [(100, 50), (0, 0), (37, 80)] {
+      let ray = camera.ray_for_pixel(x, y);
+      let (px, py) = camera.project_point(ray.position(3.)).unwrap();
+
+      assert!((px - (x as f64 + 0.5)).abs() < 1e-6);
+      assert!((py - (y as f64 + 0.5)).abs() < 1e-6);
+    }
+
+    assert!(camera.project_point(camera.ray_for_pixel(100, 50).position(-1.)).is_none());
+  }
+
+  #[test]
+  fn ray_grid_is_symmetric_about_the_center() {
+    let mut camera = Camera::new(5, 3, PI / 2.);
+    camera.transform = Matrix4x4::identity();
+
+    let rays: Vec<_> = camera.ray_grid().collect();
+
+    assert_eq!(rays.len(), 15);
+    assert_eq!((rays[7].0, rays[7].1), (2, 1));
+    assert_eq!(rays[7].2.direction, vec3(0., 0., -1.));
+
+    let corner = |index: usize| rays[index].2.direction;
+    let [top_left, top_right, bottom_left, bottom_right] = [corner(0), corner(4), corner(10), corner(14)];
+
+    assert_eq!(top_right, vec3(-top_left.x, top_left.y, top_left.z));
+    assert_eq!(bottom_left, vec3(top_left.x, -top_left.y, top_left.z));
+    assert_eq!(bottom_right, vec3(-top_left.x, -top_left.y, top_left.z));
+  }
+
   #[test]
   fn construct_ray_when_camera_is_transformed() {
     let mut camera = Camera::new(201, 101, PI / 2.);
@@ -136,4 +607,521 @@ mod tests {
     assert_eq!(ray.origin, point(0., 2., -5.));
     assert_eq!(ray.direction, vec3(2f64.sqrt() / 2., 0., -2f64.sqrt() / 2.));
   }
+
+  #[test]
+  fn switching_projection_changes_rays_from_converging_to_parallel() {
+    let mut camera = Camera::new(201, 101, PI / 2.);
+    camera.transform = Matrix4x4::rotate_y(PI / 4.) * Matrix4x4::translate(0., -2., 5.);
+
+    let perspective = [camera.ray_for_pixel(0, 0), camera.ray_for_pixel(200, 100)];
+
+    assert_ne!(perspective[0].direction, perspective[1].direction);
+    assert_eq!(perspective[0].origin, perspective[1].origin);
+
+    camera.set_projection(Projection::Orthographic { height: 4. });
+
+    let orthographic = [camera.ray_for_pixel(0, 0), camera.ray_for_pixel(200, 100), camera.ray_for_pixel(100, 50)];
+
+    assert_eq!(orthographic[0].direction, orthographic[1].direction);
+    assert_eq!(orthographic[2].origin, point(0., 2., -5.));
+    assert_eq!(orthographic[2].direction, vec3(2f64.sqrt() / 2., 0., -2f64.sqrt() / 2.));
+    assert!((orthographic[0].origin - orthographic[1].origin).magnitude() > 4.);
+
+    camera.set_projection(Projection::Perspective);
+
+    assert_eq!(camera.ray_for_pixel(0, 0).direction, perspective[0].direction);
+  }
+
+  #[test]
+  fn orthographic_view_covers_the_requested_height() {
+    let mut camera = Camera::new(100, 50, PI / 2.)
+      .with_projection(Projection::Orthographic { height: 10. });
+
+    camera.transform = Matrix4x4::identity();
+
+    let top = camera.ray_for_position(50., 0.);
+    let bottom = camera.ray_for_position(50., 50.);
+
+    assert!(top.origin.y.is_approx(5.));
+    assert!(bottom.origin.y.is_approx(-5.));
+  }
+
+  #[test]
+  fn fast_moving_sphere_is_motion_blurred() {
+    let mut scene = Scene::new();
+    let material = Material::default()
+      .with_ambient(1.)
+      .with_diffuse(0.)
+      .with_specular(0.);
+
+    scene.add_light(PointLight::new(point(0., 0., -10.), rgb(1., 1., 1.)));
+    scene.add_object(
+      Sphere::new()
+        .with_material(material)
+        .with_transform(Matrix4x4::translate(-1., 0., 0.))
+        .with_motion(Matrix4x4::translate(1., 0., 0.))
+    );
+
+    let mut camera = Camera::new(40, 20, PI / 3.);
+    camera.transform = Matrix4x4::look_at(point(0., 0., -8.), point(0., 0., 0.), vec3(0., 1., 0.));
+
+    let count_partial = |mut canvas: Canvas| {
+      (0..canvas.width())
+        .filter(|&x| (0.05..0.95).contains(&canvas.get_pixel(x, 10).r))
+        .count()
+    };
+
+    // without a shutter the sphere is crisp, at its start position
+    let crisp = camera.clone().with_samples(16).render(&scene);
+    let blurred = camera.clone().with_samples(16).with_shutter(1.).render(&scene);
+
+    assert!(count_partial(crisp) <= 2);
+    assert!(count_partial(blurred) >= 6);
+  }
+
+  #[test]
+  fn halton_sampler_antialiases_edges() {
+    let mut scene = Scene::new();
+    let material = Material::default()
+      .with_ambient(1.)
+      .with_diffuse(0.)
+      .with_specular(0.);
+
+    scene.add_light(PointLight::new(point(0., 0., -10.), rgb(1., 1., 1.)));
+    scene.add_object(Sphere::new().with_material(material));
+
+    let mut camera = Camera::new(40, 20, PI / 3.);
+    camera.transform = Matrix4x4::look_at(point(0., 0., -8.), point(0., 0., 0.), vec3(0., 1., 0.));
+
+    let count_partial = |mut canvas: Canvas| {
+      (0..canvas.width())
+        .filter(|&x| (0.05..0.95).contains(&canvas.get_pixel(x, 10).r))
+        .count()
+    };
+
+    let aliased = camera.clone().render(&scene);
+    let smoothed = camera.clone().with_samples(16).with_sampler(Sampler::Halton).render(&scene);
+
+    assert_eq!(count_partial(aliased), 0);
+    assert!(count_partial(smoothed) >= 2);
+  }
+
+  #[test]
+  fn preview_render_traces_at_reduced_resolution() {
+    let mut scene = Scene::new();
+
+    scene.add_light(PointLight::new(point(-10., 10., -10.), rgb(1., 1., 1.)));
+    scene.add_object(Sphere::new());
+
+    let mut camera = Camera::new(8, 8, PI / 3.);
+    camera.transform = Matrix4x4::look_at(point(0., 0., -3.), point(0., 0., 0.), vec3(0., 1., 0.));
+
+    scene.reset_stats();
+    let mut preview = camera.render_preview(&scene, 4);
+
+    assert_eq!(scene.stats().primary_rays, 2 * 2);
+    assert_eq!(preview.width(), 8);
+    assert_eq!(preview.height(), 8);
+
+    let mut low_res = Camera { transform: camera.transform, ..Camera::new(2, 2, PI / 3.) }.render(&scene);
+
+    for y in 0..8 {
+      for x in 0..8 {
+        assert_eq!(preview.get_pixel(x, y), low_res.get_pixel(x / 4, y / 4));
+      }
+    }
+  }
+
+  #[test]
+  fn preview_render_keeps_the_camera_settings() {
+    let mut scene = Scene::new();
+
+    scene.add_light(PointLight::new(point(-10., 10., -10.), rgb(1., 1., 1.)));
+    scene.add_object(Sphere::new());
+
+    let mut camera = Camera::new(8, 8, PI / 3.)
+      .with_projection(Projection::Orthographic { height: 3. })
+      .with_exposure(1.)
+      .with_vignette(0.5);
+    camera.transform = Matrix4x4::look_at(point(0., 0., -3.), point(0., 0., 0.), vec3(0., 1., 0.));
+
+    // at full scale the preview is exactly the render, projection, exposure and all
+    assert_eq!(camera.render_preview(&scene, 1).max_diff(&camera.render(&scene)), 0.);
+
+    let mut preview = camera.render_preview(&scene, 2);
+    let mut low_res = Camera::new(4, 4, PI / 3.)
+      .with_projection(Projection::Orthographic { height: 3. })
+      .with_exposure(1.)
+      .with_vignette(0.5);
+    low_res.transform = camera.transform;
+
+    let mut low_res = low_res.render(&scene);
+
+    for y in 0..8 {
+      for x in 0..8 {
+        assert_eq!(preview.get_pixel(x, y), low_res.get_pixel(x / 2, y / 2));
+      }
+    }
+  }
+
+  #[test]
+  fn exposure_scales_pixel_brightness() {
+    let mut scene = Scene::new();
+
+    scene.add_light(PointLight::new(point(-10., 10., -10.), rgb(1., 1., 1.)));
+    scene.add_object(Sphere::new());
+
+    let mut camera = Camera::new(5, 5, PI / 3.);
+    camera.transform = Matrix4x4::look_at(point(0., 0., -5.), point(0., 0., 0.), vec3(0., 1., 0.));
+
+    let mut normal = camera.clone().render(&scene);
+    let mut doubled = camera.clone().with_exposure(1.).render(&scene);
+
+    assert_ne!(normal.get_pixel(2, 2), Color::BLACK);
+    assert_eq!(doubled.get_pixel(2, 2), normal.get_pixel(2, 2) * 2.);
+  }
+
+  #[test]
+  fn vignette_darkens_corners() {
+    let mut scene = Scene::new();
+
+    scene.set_background_color(Color::WHITE);
+
+    let camera = Camera::new(21, 21, PI / 2.).with_vignette(1.);
+    let mut canvas = camera.render(&scene);
+
+    let center = canvas.get_pixel(10, 10);
+    let corner = canvas.get_pixel(0, 0);
+
+    assert!(center.r > 0.99);
+    assert!(corner.r < center.r);
+    assert!(corner.r < 0.3);
+  }
+
+  #[test]
+  fn render_with_timeout_returns_partial_results() {
+    let mut scene = Scene::new();
+
+    scene.add_light(PointLight::new(point(-10., 10., -10.), rgb(1., 1., 1.)));
+    scene.add_object(Plane::new(vec3(0., 1., 0.)).with_transform(Matrix4x4::translate(0., -1., 0.)));
+
+    for index in 0..50 {
+      scene.add_object(Sphere::new().with_transform(Matrix4x4::translate(index as f64 * 0.1 - 2.5, 0., 0.)));
+    }
+
+    let camera = Camera::new(64, 48, PI / 3.).with_samples(64);
+
+    let (partial, completed) = camera.render_with_timeout(&scene, Duration::ZERO);
+    let black = partial.as_slice().iter().filter(|&&pixel| pixel == Color::BLACK).count();
+
+    assert!(!completed);
+    assert_eq!(black, 64 * 48);
+
+    let camera = Camera::new(8, 6, PI / 3.);
+    let (canvas, completed) = camera.render_with_timeout(&scene, Duration::from_secs(60));
+
+    assert!(completed);
+    assert_eq!(canvas.fingerprint(), camera.render(&scene).fingerprint());
+  }
+
+  #[test]
+  fn render_to_sink_delivers_every_pixel_once() {
+    #[derive(Default)]
+    struct RecordingSink {
+      writes: Vec<(usize, usize, Color)>,
+      flushes: usize,
+    }
+
+    impl OutputSink for RecordingSink {
+      fn write_pixel(&mut self, x: usize, y: usize, color: Color) {
+        self.writes.push((x, y, color));
+      }
+
+      fn flush(&mut self) {
+        self.flushes += 1;
+      }
+    }
+
+    let mut scene = Scene::new();
+
+    scene.add_light(PointLight::new(point(-10., 10., -10.), rgb(1., 1., 1.)));
+    scene.add_object(Sphere::new());
+
+    let camera = Camera::new(9, 5, PI / 3.);
+    let mut sink = RecordingSink::default();
+
+    camera.render_to_sink(&scene, &mut sink);
+
+    assert_eq!(sink.writes.len(), 9 * 5);
+    assert_eq!(sink.flushes, 1);
+
+    let mut canvas = camera.render(&scene);
+    let mut seen = [false; 9 * 5];
+
+    for (x, y, color) in sink.writes {
+      assert!(!seen[x + y * 9], "pixel ({}, {}) written twice", x, y);
+      assert_eq!(color, canvas.get_pixel(x, y));
+
+      seen[x + y * 9] = true;
+    }
+  }
+
+  #[test]
+  fn overscanned_region_matches_full_render_inside_the_image() {
+    let mut scene = Scene::new();
+
+    scene.add_light(PointLight::new(point(-10., 10., -10.), rgb(1., 1., 1.)));
+    scene.add_object(Sphere::new());
+    scene.add_object(Plane::new(vec3(0., 1., 0.)).with_transform(Matrix4x4::translate(0., -1., 0.)));
+
+    let camera = Camera::new(16, 12, PI / 3.).with_samples(4).with_vignette(0.5);
+    let mut full = camera.render(&scene);
+
+    let mut region = camera.render_region_with_overscan(&scene, 6, 4, 5, 3, 2);
+
+    assert_eq!(region.width(), 9);
+    assert_eq!(region.height(), 7);
+
+    // the border holds the neighbouring pixels of the full image
+    for y in 0..7 {
+      for x in 0..9 {
+        assert_eq!(region.get_pixel(x, y), full.get_pixel(x + 4, y + 2));
+      }
+    }
+
+    let mut cropped = region.crop(2, 2, 5, 3);
+
+    assert_eq!(cropped.width(), 5);
+    assert_eq!(cropped.height(), 3);
+
+    for y in 0..3 {
+      for x in 0..5 {
+        assert_eq!(cropped.get_pixel(x, y), full.get_pixel(x + 6, y + 4));
+      }
+    }
+
+    assert_eq!(cropped.fingerprint(), camera.render_region(&scene, 6, 4, 5, 3).fingerprint());
+  }
+
+  #[test]
+  fn overscan_continues_past_the_edges_of_the_image() {
+    let mut scene = Scene::new();
+
+    scene.add_light(PointLight::new(point(-10., 10., -10.), rgb(1., 1., 1.)));
+    scene.add_object(Plane::new(vec3(0., 1., 0.)).with_transform(Matrix4x4::translate(0., -1., 0.)));
+
+    let camera = Camera::new(8, 6, PI / 3.);
+    let mut region = camera.render_region_with_overscan(&scene, 0, 0, 8, 6, 3);
+    let mut wider = Camera::new(14, 12, PI / 3.);
+
+    // a camera with the same pixel size, covering the overscanned area
+    wider.pixel_size = camera.pixel_size;
+    wider.half_width = camera.half_width + 3. * camera.pixel_size;
+    wider.half_height = camera.half_height + 3. * camera.pixel_size;
+
+    let mut expected = wider.render(&scene);
+
+    // the floor below the horizon continues into the bottom border
+    assert_ne!(region.get_pixel(7, 11), Color::BLACK);
+    assert_eq!(region.get_pixel(0, 11), expected.get_pixel(0, 11));
+    assert_eq!(region.get_pixel(13, 0), expected.get_pixel(13, 0));
+    assert_eq!(region.crop(3, 3, 8, 6).fingerprint(), camera.render(&scene).fingerprint());
+  }
+
+  #[test]
+  fn supersampled_overscan_past_the_edges_should_not_overflow() {
+    let mut scene = Scene::new();
+
+    scene.add_light(PointLight::new(point(-10., 10., -10.), rgb(1., 1., 1.)));
+    scene.add_object(Sphere::new());
+
+    for sampler in [Sampler::Random, Sampler::Halton] {
+      let camera = Camera::new(8, 8, PI / 3.).with_samples(4).with_sampler(sampler);
+      let region = camera.render_region_with_overscan(&scene, 0, 0, 4, 4, 2);
+
+      assert_eq!(region.width(), 8);
+      assert_eq!(region.height(), 8);
+      assert_eq!(region.crop(2, 2, 4, 4).fingerprint(), camera.render_region(&scene, 0, 0, 4, 4).fingerprint());
+    }
+  }
+
+  #[test]
+  fn render_pixels_matches_full_render_at_those_pixels() {
+    let mut scene = Scene::new();
+
+    scene.add_light(PointLight::new(point(-10., 10., -10.), rgb(1., 1., 1.)));
+    scene.add_object(Sphere::new());
+    scene.add_object(Plane::new(vec3(0., 1., 0.)).with_transform(Matrix4x4::translate(0., -1., 0.)));
+
+    let camera = Camera::new(16, 12, PI / 3.).with_samples(4);
+    let mut full = camera.render(&scene);
+
+    let requested = [(8, 6), (0, 11), (15, 0)];
+    let pixels = camera.render_pixels(&scene, &requested);
+
+    assert_eq!(pixels.iter().map(|&(position, _)| position).collect::<Vec<_>>(), requested);
+
+    for ((x, y), color) in pixels {
+      assert_eq!(color, full.get_pixel(x, y));
+    }
+  }
+
+  #[test]
+  fn normal_overlay_points_out_of_a_sphere_silhouette() {
+    let mut scene = Scene::new();
+
+    scene.add_light(PointLight::new(point(-10., 10., -10.), rgb(1., 1., 1.)));
+    scene.add_object(Sphere::new());
+
+    let mut camera = Camera::new(64, 64, PI / 3.);
+    camera.transform = Matrix4x4::look_at(point(0., 0., -3.), point(0., 0., 0.), vec3(0., 1., 0.));
+
+    let segments = camera.normal_segments(&scene, 1.);
+    let from_center = |(x, y): (f64, f64)| ((x - 32.) * (x - 32.) + (y - 32.) * (y - 32.)).sqrt();
+    let misses = |(x, y): (f64, f64)| scene.intersect_closest(camera.ray_for_position(x, y), 0., f64::MAX).is_none();
+
+    assert!(!segments.is_empty());
+    assert!(segments.iter().all(|segment| from_center(segment.end) >= from_center(segment.start) - 1e-6));
+    assert!(segments.iter().any(|segment| misses(segment.end)));
+
+    // the overlay is drawn over the background beyond the silhouette
+    let mut plain = camera.render(&scene);
+    let mut overlay = camera.render_with_normal_overlay(&scene, 1.);
+    let (x, y) = segments.iter().map(|segment| segment.end).find(|&end| misses(end)).unwrap();
+
+    assert_eq!(plain.get_pixel(x as usize, y as usize), Color::BLACK);
+    assert_ne!(overlay.get_pixel(x as usize, y as usize), Color::BLACK);
+  }
+
+  #[test]
+  fn render_with_stats_counts_rays() {
+    let mut scene = Scene::new();
+
+    scene.add_light(PointLight::new(point(-10., 10., -10.), rgb(1., 1., 1.)));
+    scene.add_object(Sphere::new());
+    scene.add_object(Plane::new(vec3(0., 1., 0.)).with_transform(Matrix4x4::translate(0., -1., 0.)));
+
+    let camera = Camera::new(11, 7, PI / 3.);
+    let (_, stats) = camera.render_with_stats(&scene);
+
+    assert_eq!(stats.primary_rays, 11 * 7);
+    assert!(stats.shadow_rays > 0);
+    assert!(stats.intersection_tests >= 2 * 11 * 7);
+    assert_eq!(stats.reflection_rays, 0);
+    assert_eq!(stats.refraction_rays, 0);
+
+    // stats are reset for each render
+    let (_, again) = camera.render_with_stats(&scene);
+
+    assert_eq!(again, stats);
+  }
+
+  #[test]
+  fn eye_offset_moves_camera_along_local_x_axis() {
+    let mut camera = Camera::new(201, 101, PI / 2.);
+    camera.transform = Matrix4x4::rotate_y(PI / 2.);
+
+    let left = camera.with_eye_offset(0.5).ray_for_pixel(100, 50);
+    let right = camera.with_eye_offset(-0.5).ray_for_pixel(100, 50);
+
+    assert_eq!(left.direction, right.direction);
+    assert_eq!(left.origin - right.origin, vec3(0., 0., 1.));
+  }
+
+  #[test]
+  fn stereo_render_composites_eyes_into_anaglyph() {
+    let mut scene = Scene::new();
+
+    scene.add_light(PointLight::new(point(-10., 10., -10.), rgb(1., 1., 1.)));
+    scene.add_object(Sphere::new().with_material(Material::default().with_color(rgb(0.8, 1.0, 0.6))));
+
+    let mut camera = Camera::new(24, 16, PI / 3.);
+    camera.transform = Matrix4x4::look_at(point(0., 0., -4.), point(0., 0., 0.), vec3(0., 1., 0.));
+
+    let mut anaglyph = camera.render_stereo(&scene, 0.5);
+    let mut left = camera.with_eye_offset(0.25).render(&scene);
+    let mut right = camera.with_eye_offset(-0.25).render(&scene);
+
+    let mut differs = false;
+
+    for y in 0..anaglyph.height() {
+      for x in 0..anaglyph.width() {
+        let pixel = anaglyph.get_pixel(x, y);
+        let left = left.get_pixel(x, y).luminance();
+        let right = right.get_pixel(x, y).luminance();
+
+        assert!(pixel.r.is_approx(left));
+        assert!(pixel.g.is_approx(right));
+        assert!(pixel.b.is_approx(right));
+
+        differs |= !left.is_approx(right);
+      }
+    }
+
+    // the eyes should see the sphere from slightly different positions
+    assert!(differs);
+  }
+
+  #[test]
+  fn identical_renders_share_a_fingerprint() {
+    let build_scene = |light_x: f64| {
+      let mut scene = Scene::new();
+
+      scene.add_light(PointLight::new(point(light_x, 10., -10.), rgb(1., 1., 1.)));
+      scene.add_object(Sphere::new());
+
+      scene
+    };
+
+    let camera = Camera::new(16, 12, PI / 3.);
+
+    let first = camera.render(&build_scene(-10.)).fingerprint();
+    let second = camera.render(&build_scene(-10.)).fingerprint();
+    let perturbed = camera.render(&build_scene(10.)).fingerprint();
+
+    assert_eq!(first, second);
+    assert_ne!(first, perturbed);
+  }
+
+  #[test]
+  fn streamed_render_matches_buffered_render() {
+    let mut scene = Scene::new();
+
+    scene.add_light(PointLight::new(point(-10., 10., -10.), rgb(1., 1., 1.)));
+    scene.add_object(Sphere::new().with_material(Material::default().with_color(rgb(0.8, 1.0, 0.6))));
+    scene.add_object(Plane::new(vec3(0., 1., 0.)).with_transform(Matrix4x4::translate(0., -1., 0.)));
+
+    let camera = Camera::new(33, 21, PI / 3.);
+
+    let path = std::env::temp_dir().join(format!("raytracer_streamed_render_matches_buffered_{}.png", std::process::id()));
+    camera.render(&scene).save_to_png(path.to_str().unwrap()).unwrap();
+    let buffered = image::open(&path).unwrap().to_rgba8();
+    std::fs::remove_file(&path).unwrap();
+
+    let mut bytes = Vec::new();
+    camera.render_to_png_stream(&scene, &mut bytes).unwrap();
+    let streamed = image::load_from_memory(&bytes).unwrap().to_rgba8();
+
+    assert_eq!(streamed.dimensions(), buffered.dimensions());
+    assert_eq!(streamed.as_raw(), buffered.as_raw());
+  }
+
+  #[test]
+  fn single_and_double_precision_storage_look_the_same() {
+    let mut scene = Scene::new();
+
+    scene.add_light(PointLight::new(point(-10., 10., -10.), rgb(1., 1., 1.)));
+    scene.add_object(Sphere::new().with_material(Material::default()
+      .with_color(rgb(0.8, 1., 0.6))
+      .with_diffuse(0.7)
+      .with_specular(0.2)));
+    scene.add_object(Sphere::new().with_transform(Matrix4x4::scale(0.5, 0.5, 0.5)));
+
+    let camera = Camera::new(32, 24, PI / 3.);
+    let single = camera.render_with_storage::<f32>(&scene);
+    let double = camera.render_with_storage::<f64>(&scene);
+
+    assert!(single.to_precision::<f64>().max_diff(&double) < 1e-6);
+    assert_eq!(single.fingerprint(), double.fingerprint());
+  }
 }
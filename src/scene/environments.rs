@@ -0,0 +1,69 @@
+//! Environments surrounding the scene, seen by rays that escape it.
+
+use crate::maths::{Color, Vector};
+
+/// An environment that provides a color for rays that miss every object in the scene.
+pub trait Environment {
+  /// Samples the color of the environment in the given direction.
+  fn sample(&self, direction: Vector) -> Color;
+
+  /// Approximates the light arriving from the environment onto a surface with the given normal.
+  ///
+  /// By default this is just the environment in the normal direction; a cheap stand-in for
+  /// integrating over the hemisphere.
+  fn irradiance(&self, normal: Vector) -> Color {
+    self.sample(normal)
+  }
+}
+
+/// A vertical gradient sky, blending from the horizon up to the zenith.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GradientSky {
+  pub horizon: Color,
+  pub zenith: Color,
+}
+
+impl GradientSky {
+  /// Creates a new gradient sky with the given colors.
+  pub fn new(horizon: Color, zenith: Color) -> Self {
+    Self { horizon, zenith }
+  }
+}
+
+impl Environment for GradientSky {
+  fn sample(&self, direction: Vector) -> Color {
+    // anything below the horizon takes the horizon color
+    let height = direction.normalize().y.max(0.);
+
+    self.horizon + (self.zenith - self.horizon) * height
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::maths::{rgb, vec3};
+
+  use super::*;
+
+  #[test]
+  fn gradient_sky_is_zenith_color_straight_up() {
+    let sky = GradientSky::new(Color::WHITE, Color::BLUE);
+
+    assert_eq!(sky.sample(vec3(0., 1., 0.)), Color::BLUE);
+  }
+
+  #[test]
+  fn gradient_sky_is_horizon_color_at_and_below_horizon() {
+    let sky = GradientSky::new(Color::WHITE, Color::BLUE);
+
+    assert_eq!(sky.sample(vec3(1., 0., 0.)), Color::WHITE);
+    assert_eq!(sky.sample(vec3(0., -1., 1.)), Color::WHITE);
+  }
+
+  #[test]
+  fn gradient_sky_blends_between_horizon_and_zenith() {
+    let sky = GradientSky::new(Color::WHITE, Color::BLUE);
+
+    assert_eq!(sky.sample(vec3(0., 1., 0.) + vec3(0., 0., 3f64.sqrt())), rgb(0.5, 0.5, 1.));
+  }
+}
@@ -0,0 +1,342 @@
+//! Grouping of objects into hierarchies.
+
+use crate::maths::{BoundingBox, Matrix4x4, Point, Ray, RayCache, Vector};
+use crate::scene::{HitList, Material, Traceable};
+
+/// A collection of objects that share a common transform.
+///
+/// The group's transform is baked into each child as it's added (the child ends up
+/// with `group * child`), so world rays are intersected against the children directly
+/// and normals come out in world space without having to walk back up the hierarchy.
+pub struct Group {
+  children: Vec<Box<dyn Traceable>>,
+  material: Material,
+  transform: Matrix4x4,
+  inverse_transform: Matrix4x4,
+}
+
+impl Group {
+  /// Creates a new empty group.
+  pub fn new() -> Self {
+    Self {
+      children: Vec::new(),
+      material: Material::default(),
+      transform: Matrix4x4::identity(),
+      inverse_transform: Matrix4x4::identity(),
+    }
+  }
+
+  /// Sets the transform for this group and all of its children.
+  ///
+  /// Panics if the transform is singular; use `try_with_transform` to handle that case instead.
+  pub fn with_transform(self, transform: Matrix4x4) -> Self {
+    self
+      .try_with_transform(transform)
+      .expect("Failed to invert group transform")
+  }
+
+  /// Sets the transform for this group and all of its children, failing if it can't be inverted.
+  pub fn try_with_transform(mut self, transform: Matrix4x4) -> anyhow::Result<Self> {
+    let inverse_transform = transform
+      .try_invert()
+      .ok_or_else(|| anyhow::anyhow!("Cannot transform a group by a singular matrix"))?;
+
+    // undo the previous group transform before applying the new one
+    let relative = transform * self.inverse_transform;
+
+    for child in &mut self.children {
+      child.try_apply_parent_transform(relative)?;
+    }
+
+    self.transform = transform;
+    self.inverse_transform = inverse_transform;
+
+    Ok(self)
+  }
+
+  /// Adds a child to the group, relative to the group's transform.
+  pub fn with_child(mut self, child: impl Traceable + 'static) -> Self {
+    self.add_child(child);
+    self
+  }
+
+  /// Adds a child to the group, relative to the group's transform.
+  pub fn add_child(&mut self, child: impl Traceable + 'static) {
+    self.add_child_boxed(Box::new(child));
+  }
+
+  /// Adds a child to the group, relative to the group's transform.
+  ///
+  /// Panics if the child can't be placed under the group; use `try_add_child` to handle that case.
+  pub fn add_child_boxed(&mut self, child: Box<dyn Traceable>) {
+    self
+      .try_add_child(child)
+      .expect("Failed to add child to group");
+  }
+
+  /// Adds a child to the group, failing if the combined transform can't be inverted.
+  pub fn try_add_child(&mut self, mut child: Box<dyn Traceable>) -> anyhow::Result<()> {
+    child.try_apply_parent_transform(self.transform)?;
+
+    self.children.push(child);
+
+    Ok(())
+  }
+
+  /// The children of this group, in the order they were added.
+  pub fn children(&self) -> &[Box<dyn Traceable>] {
+    &self.children
+  }
+}
+
+impl Default for Group {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Traceable for Group {
+  fn material(&self) -> &Material {
+    &self.material
+  }
+
+  fn intersect(&self, world_ray: Ray) -> HitList<'_> {
+    self.intersect_cached(world_ray, &mut RayCache::new())
+  }
+
+  fn intersect_cached(&self, world_ray: Ray, cache: &mut RayCache) -> HitList<'_> {
+    let mut results = HitList::new();
+
+    // children share the group's transform, so the cache usually transforms the ray just once
+    for child in &self.children {
+      results.append(child.intersect_cached(world_ray, cache));
+    }
+
+    results
+  }
+
+  /// Groups have no surface of their own, so this is the zero vector; normals come from the
+  /// children that were hit.
+  fn normal_at(&self, _world_point: Point) -> Vector {
+    Vector::ZERO
+  }
+
+  fn world_to_object(&self, world_point: Point) -> Point {
+    self.inverse_transform * world_point
+  }
+
+  fn object_to_world(&self, object_point: Point) -> Point {
+    self.transform * object_point
+  }
+
+  fn bounds(&self) -> BoundingBox {
+    // children already carry the group transform, so their world bounds can be merged directly
+    self.children
+      .iter()
+      .fold(BoundingBox::EMPTY, |bounds, child| bounds.union(child.bounds()))
+  }
+
+  fn try_apply_parent_transform(&mut self, parent: Matrix4x4) -> anyhow::Result<()> {
+    let transform = parent * self.transform;
+    let inverse_transform = transform
+      .try_invert()
+      .ok_or_else(|| anyhow::anyhow!("Failed to invert group transform under its parent"))?;
+
+    for child in &mut self.children {
+      child.try_apply_parent_transform(parent)?;
+    }
+
+    self.transform = transform;
+    self.inverse_transform = inverse_transform;
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::maths::{ApproxEq, PI, point, vec3};
+  use crate::scene::{Plane, Sphere};
+
+  use super::*;
+
+  #[test]
+  fn empty_group_has_no_intersections() {
+    let group = Group::new();
+    let ray = Ray::new(point(0., 0., 0.), vec3(0., 0., 1.));
+
+    assert_eq!(group.intersect(ray).len(), 0);
+  }
+
+  #[test]
+  fn empty_group_has_empty_bounds() {
+    let group = Group::new().with_transform(Matrix4x4::scale(2., 2., 2.));
+
+    assert!(group.bounds().is_empty());
+    assert_eq!(group.intersect(Ray::new(point(0., 0., -5.), vec3(0., 0., 1.))).len(), 0);
+  }
+
+  #[test]
+  fn group_bounds_merge_children_in_world_space() {
+    let group = Group::new()
+      .with_child(Sphere::new().with_transform(Matrix4x4::translate(-2., 0., 0.)))
+      .with_child(Sphere::new().with_transform(Matrix4x4::translate(2., 1., 0.) * Matrix4x4::scale(0.5, 0.5, 0.5)))
+      .with_transform(Matrix4x4::translate(0., 0., 10.) * Matrix4x4::rotate_y(PI / 2.));
+
+    // rotating a quarter turn about y takes x onto -z
+    let bounds = group.bounds();
+
+    assert_eq!(bounds, BoundingBox::new(point(-1., -1., 7.5), point(1., 1.5, 13.)));
+  }
+
+  #[test]
+  fn group_containing_a_plane_has_infinite_bounds() {
+    let group = Group::new()
+      .with_child(Sphere::new())
+      .with_child(Plane::new(vec3(0., 1., 0.)).with_transform(Matrix4x4::translate(0., -1., 0.)));
+
+    let bounds = group.bounds();
+
+    assert!(!bounds.is_finite());
+    assert!(bounds.min.x.is_infinite() && bounds.max.z.is_infinite());
+    assert!(bounds.min.y.is_approx(-1.) && bounds.max.y.is_approx(1.));
+  }
+
+  #[test]
+  fn group_intersects_all_of_its_children() {
+    let group = Group::new()
+      .with_child(Sphere::new())
+      .with_child(Sphere::new().with_transform(Matrix4x4::translate(0., 0., -3.)))
+      .with_child(Sphere::new().with_transform(Matrix4x4::translate(5., 0., 0.)));
+
+    let ray = Ray::new(point(0., 0., -5.), vec3(0., 0., 1.));
+    let mut set = group.intersect(ray);
+
+    set.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
+
+    assert_eq!(set.len(), 4);
+    assert_eq!(set[0].distance, 1.);
+    assert_eq!(set[1].distance, 3.);
+    assert_eq!(set[2].distance, 4.);
+    assert_eq!(set[3].distance, 6.);
+  }
+
+  #[test]
+  fn group_transform_applies_to_children() {
+    let group = Group::new()
+      .with_transform(Matrix4x4::scale(2., 2., 2.))
+      .with_child(Sphere::new().with_transform(Matrix4x4::translate(5., 0., 0.)));
+
+    let ray = Ray::new(point(10., 0., -10.), vec3(0., 0., 1.));
+
+    assert_eq!(group.intersect(ray).len(), 2);
+  }
+
+  #[test]
+  fn group_transform_can_be_set_after_children() {
+    let group = Group::new()
+      .with_child(Sphere::new().with_transform(Matrix4x4::translate(5., 0., 0.)))
+      .with_transform(Matrix4x4::translate(1., 0., 0.))
+      .with_transform(Matrix4x4::scale(2., 2., 2.));
+
+    let ray = Ray::new(point(10., 0., -10.), vec3(0., 0., 1.));
+
+    assert_eq!(group.intersect(ray).len(), 2);
+  }
+
+  #[test]
+  fn normal_on_nested_child_object_is_in_world_space() {
+    let group = Group::new()
+      .with_transform(Matrix4x4::rotate_y(PI / 2.))
+      .with_child(
+        Group::new()
+          .with_transform(Matrix4x4::scale(1., 2., 3.))
+          .with_child(Sphere::new().with_transform(Matrix4x4::translate(5., 0., 0.)))
+      );
+
+    let surface = point(1.7321, 1.1547, -5.5774);
+    let expected = vec3(0.2857, 0.4286, -0.8571);
+
+    // fire a ray back at the surface along the expected normal
+    let ray = Ray::new(surface + expected * 5., -expected);
+    let hits = group.intersect(ray);
+    let normal = hits[0].object.normal_at(surface);
+
+    assert_eq!(hits.len(), 2);
+    assert!((normal - expected).magnitude() < 0.0001);
+  }
+
+  #[test]
+  fn normal_in_rotated_group_matches_directly_transformed_sphere() {
+    let rotation = Matrix4x4::rotate_z(PI / 3.) * Matrix4x4::rotate_y(PI / 4.);
+    let translation = Matrix4x4::translate(2., 0., 1.) * Matrix4x4::scale(1., 0.5, 2.);
+
+    let group = Group::new()
+      .with_child(Sphere::new().with_transform(translation))
+      .with_transform(rotation);
+    let direct = Sphere::new().with_transform(rotation * translation);
+
+    let ray = Ray::new(point(0., 0., -10.), (direct.object_to_world(Point::ORIGIN) - point(0., 0., -10.)).normalize());
+    let grouped_hits = group.intersect(ray);
+    let direct_hits = direct.intersect(ray);
+
+    assert_eq!(grouped_hits.len(), 2);
+    assert!(grouped_hits[0].distance.is_approx(direct_hits[0].distance));
+
+    let surface = ray.position(direct_hits[0].distance);
+
+    assert_eq!(grouped_hits[0].object.normal_at(surface), direct.normal_at(surface));
+    assert_eq!(grouped_hits[0].object.world_to_object(surface), direct.world_to_object(surface));
+  }
+
+  #[test]
+  fn group_children_with_a_shared_transform_reuse_the_object_ray() {
+    let group = Group::new()
+      .with_child(Sphere::new())
+      .with_child(Sphere::new())
+      .with_child(Sphere::new())
+      .with_transform(Matrix4x4::translate(0., 0., 5.) * Matrix4x4::scale(2., 2., 2.));
+
+    let ray = Ray::new(point(0., 0., -5.), vec3(0., 0., 1.));
+    let mut cache = RayCache::new();
+
+    let hits = group.intersect_cached(ray, &mut cache);
+
+    assert_eq!(hits.len(), 6);
+    assert_eq!(hits[0].distance, 8.);
+    assert_eq!(cache.transforms(), 1);
+  }
+
+  #[test]
+  fn singular_group_transforms_are_reported_rather_than_ignored() {
+    let flatten = Matrix4x4::scale(1., 0., 1.);
+
+    assert!(Group::new().with_child(Sphere::new()).try_with_transform(flatten).is_err());
+    assert!(Group::new().with_child(Sphere::new()).try_with_transform(Matrix4x4::scale(2., 2., 2.)).is_ok());
+  }
+
+  #[test]
+  #[should_panic(expected = "Failed to invert node transform under its parent")]
+  fn singular_parent_transforms_panic_rather_than_render_wrongly() {
+    let mut sphere = Sphere::new();
+
+    sphere.apply_parent_transform(Matrix4x4::scale(0., 0., 0.));
+  }
+
+  #[test]
+  fn children_that_cannot_be_placed_under_the_group_are_reported() {
+    let mut group = Group::new().with_transform(Matrix4x4::scale(1e-60, 1e-60, 1e-60));
+    let child = Sphere::new().with_transform(Matrix4x4::scale(1e-60, 1e-60, 1e-60));
+
+    assert!(group.try_add_child(Box::new(child)).is_err());
+    assert!(group.children().is_empty());
+    assert!(group.try_add_child(Box::new(Sphere::new())).is_ok());
+  }
+
+  #[test]
+  fn groups_have_no_normal_of_their_own() {
+    let group = Group::new().with_child(Sphere::new());
+
+    assert_eq!(group.normal_at(point(0., 0., -1.)), Vector::ZERO);
+  }
+}
@@ -0,0 +1,196 @@
+//! Instancing of shared geometry.
+
+use std::sync::Arc;
+
+use crate::maths::{BoundingBox, Matrix4x4, Point, Ray, transform_normal, Vector};
+use crate::scene::{HitList, Material, Traceable};
+
+/// A placement of shared geometry with its own transform.
+///
+/// Unlike groups, instances don't bake their transform into the geometry; rays are moved into
+/// instance space instead, so any number of instances can reference a single mesh. Hits on the
+/// geometry remember the instance they came through, so that normals and texture lookups can
+/// be brought back out to world space. Instances of geometry that itself contains instances
+/// aren't supported.
+pub struct Instance {
+  geometry: Arc<dyn Traceable>,
+  transform: Matrix4x4,
+  inverse_transform: Matrix4x4,
+  normal_transform: Matrix4x4,
+}
+
+impl Instance {
+  /// Creates a new instance of the given geometry, with an identity transform.
+  pub fn new(geometry: Arc<dyn Traceable>) -> Self {
+    Self {
+      geometry,
+      transform: Matrix4x4::identity(),
+      inverse_transform: Matrix4x4::identity(),
+      normal_transform: Matrix4x4::identity(),
+    }
+  }
+
+  /// Sets the transform for this instance.
+  ///
+  /// Panics if the transform is singular; use `try_with_transform` to handle that case instead.
+  pub fn with_transform(self, transform: Matrix4x4) -> Self {
+    self
+      .try_with_transform(transform)
+      .expect("Failed to invert instance transform")
+  }
+
+  /// Sets the transform for this instance, failing if the transform can't be inverted.
+  pub fn try_with_transform(self, transform: Matrix4x4) -> anyhow::Result<Self> {
+    let inverse_transform = transform
+      .try_invert()
+      .ok_or_else(|| anyhow::anyhow!("Cannot transform an instance by a singular matrix"))?;
+    let normal_transform = inverse_transform.transpose();
+
+    Ok(Self { transform, inverse_transform, normal_transform, ..self })
+  }
+
+  /// The shared geometry placed by this instance.
+  pub fn geometry(&self) -> &Arc<dyn Traceable> {
+    &self.geometry
+  }
+
+  /// Transforms the given world point into the geometry's space.
+  pub fn world_to_instance(&self, world_point: Point) -> Point {
+    self.inverse_transform * world_point
+  }
+
+  /// Transforms a normal in the geometry's space back out to world space.
+  pub fn normal_to_world(&self, instance_normal: Vector) -> Vector {
+    transform_normal(self.normal_transform, instance_normal)
+  }
+}
+
+impl Traceable for Instance {
+  fn material(&self) -> &Material {
+    self.geometry.material()
+  }
+
+  fn intersect(&self, world_ray: Ray) -> HitList<'_> {
+    // distances are unchanged by the transform, since the direction isn't re-normalized
+    let mut results = self.geometry.intersect(self.inverse_transform * world_ray);
+
+    for hit in results.iter_mut() {
+      hit.instance = Some(self);
+    }
+
+    results
+  }
+
+  fn normal_at(&self, world_point: Point) -> Vector {
+    let normal = self.geometry.normal_at(self.world_to_instance(world_point));
+
+    // geometry without a surface of its own, like a group, has no normal to bring out
+    if normal == Vector::ZERO {
+      return Vector::ZERO;
+    }
+
+    self.normal_to_world(normal)
+  }
+
+  fn world_to_object(&self, world_point: Point) -> Point {
+    self.geometry.world_to_object(self.world_to_instance(world_point))
+  }
+
+  fn object_to_world(&self, object_point: Point) -> Point {
+    self.transform * self.geometry.object_to_world(object_point)
+  }
+
+  fn bounds(&self) -> BoundingBox {
+    self.geometry.bounds().transform(self.transform)
+  }
+
+  fn try_apply_parent_transform(&mut self, parent: Matrix4x4) -> anyhow::Result<()> {
+    // the shared geometry is left untouched; only this placement moves
+    let transform = parent * self.transform;
+    let inverse_transform = transform
+      .try_invert()
+      .ok_or_else(|| anyhow::anyhow!("Failed to invert instance transform under its parent"))?;
+
+    self.transform = transform;
+    self.inverse_transform = inverse_transform;
+    self.normal_transform = inverse_transform.transpose();
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::maths::{point, vec3};
+  use crate::scene::{Group, Sphere};
+
+  use super::*;
+
+  #[test]
+  fn instances_share_geometry_and_are_hit_separately() {
+    let sphere: Arc<dyn Traceable> = Arc::new(Sphere::new());
+
+    let left = Instance::new(sphere.clone()).with_transform(Matrix4x4::translate(-3., 0., 0.));
+    let right = Instance::new(sphere.clone()).with_transform(Matrix4x4::translate(3., 0., 0.));
+
+    assert_eq!(Arc::strong_count(&sphere), 3);
+    assert!(Arc::ptr_eq(left.geometry(), right.geometry()));
+
+    let left_hits = left.intersect(Ray::new(point(-3., 0., -5.), vec3(0., 0., 1.)));
+    let right_hits = right.intersect(Ray::new(point(3., 0., -5.), vec3(0., 0., 1.)));
+
+    assert_eq!(left_hits.len(), 2);
+    assert_eq!(right_hits.len(), 2);
+    assert_eq!(left_hits[0].distance, 4.);
+    assert!(left.intersect(Ray::new(point(3., 0., -5.), vec3(0., 0., 1.))).is_empty());
+  }
+
+  #[test]
+  fn hits_through_instances_have_world_space_normals() {
+    let sphere: Arc<dyn Traceable> = Arc::new(Sphere::new());
+    let instance = Instance::new(sphere).with_transform(Matrix4x4::translate(5., 0., 0.) * Matrix4x4::scale(2., 2., 2.));
+
+    let hits = instance.intersect(Ray::new(point(5., 0., -10.), vec3(0., 0., 1.)));
+
+    assert_eq!(hits[0].distance, 8.);
+    assert_eq!(hits[0].normal_at(point(5., 0., -2.), 0.), vec3(0., 0., -1.));
+    assert_eq!(hits[0].world_to_object(point(5., 0., -2.), 0.), point(0., 0., -1.));
+  }
+
+  #[test]
+  fn instances_can_place_shared_groups() {
+    let group: Arc<dyn Traceable> = Arc::new(Group::new()
+      .with_child(Sphere::new().with_transform(Matrix4x4::translate(0., 0., 2.))));
+
+    let instance = Instance::new(group).with_transform(Matrix4x4::translate(10., 0., 0.));
+    let hits = instance.intersect(Ray::new(point(10., 0., -5.), vec3(0., 0., 1.)));
+
+    assert_eq!(hits.len(), 2);
+    assert_eq!(hits[0].distance, 6.);
+    assert_eq!(hits[0].normal_at(point(10., 0., 1.), 0.), vec3(0., 0., -1.));
+  }
+
+  #[test]
+  fn instance_bounds_follow_their_placement() {
+    let sphere: Arc<dyn Traceable> = Arc::new(Sphere::new());
+    let instance = Instance::new(sphere).with_transform(Matrix4x4::translate(3., 0., 0.) * Matrix4x4::scale(2., 2., 2.));
+
+    assert_eq!(instance.bounds(), BoundingBox::new(point(1., -2., -2.), point(5., 2., 2.)));
+  }
+
+  #[test]
+  fn singular_instance_transforms_are_reported_rather_than_ignored() {
+    let sphere: Arc<dyn Traceable> = Arc::new(Sphere::new());
+
+    assert!(Instance::new(sphere.clone()).try_with_transform(Matrix4x4::scale(0., 1., 1.)).is_err());
+    assert!(Instance::new(sphere).try_with_transform(Matrix4x4::translate(1., 0., 0.)).is_ok());
+  }
+
+  #[test]
+  fn instances_of_groups_have_no_normal_of_their_own() {
+    let group: Arc<dyn Traceable> = Arc::new(Group::new().with_child(Sphere::new()));
+    let instance = Instance::new(group).with_transform(Matrix4x4::translate(10., 0., 0.));
+
+    assert_eq!(instance.normal_at(point(10., 0., -1.)), Vector::ZERO);
+  }
+}
@@ -1,18 +1,18 @@
 //! Light sources for scene rendering.
 
-use crate::maths::{Color, Point, Ray, Vector};
-use crate::scene::{Hit, HitList, Material, Traceable};
+use crate::maths::{Color, EPSILON, PI, Point, Ray, rgb, sample_cone, Vector};
+use crate::scene::{Hit, HitList, Material, ShadingModel, Traceable};
 
 /// A point light in the scene.
 #[derive(Clone, Debug)]
 pub struct PointLight {
-  pub position: Vector,
+  pub position: Point,
   pub intensity: Color,
 }
 
 impl PointLight {
   /// Constructs a new point light.
-  pub fn new(position: Vector, intensity: Color) -> Self {
+  pub fn new(position: Point, intensity: Color) -> Self {
     Self {
       position,
       intensity,
@@ -20,6 +20,49 @@ impl PointLight {
   }
 }
 
+/// A glowing sphere that lights the scene from its surface, casting soft shadows.
+///
+/// Altogether it gives off as much light as a point light of the same intensity at its center,
+/// but spread over the part of its surface that can be seen from each shaded point.
+#[derive(Clone, Debug)]
+pub struct SphereLight {
+  pub center: Point,
+  pub radius: f64,
+  pub intensity: Color,
+}
+
+impl SphereLight {
+  /// Constructs a new sphere light.
+  pub fn new(center: Point, radius: f64, intensity: Color) -> Self {
+    Self { center, radius, intensity }
+  }
+
+  /// Picks a point light on the surface of the sphere, as seen from the given point.
+  ///
+  /// The sample (u, v) in [0, 1) chooses a direction within the cone that the sphere fills from
+  /// the point, so only the visible cap is sampled. Points on or inside the sphere can't see it.
+  pub fn sample_from(&self, point: Point, u: f64, v: f64, samples: usize) -> Option<PointLight> {
+    let to_center = self.center - point;
+    let distance = to_center.magnitude();
+
+    if distance <= self.radius + EPSILON {
+      return None;
+    }
+
+    let half_angle = (self.radius / distance).asin();
+    let direction = sample_cone(to_center, half_angle, u, v);
+
+    // the nearest intersection of the sampled direction with the sphere
+    let along = direction.dot(to_center);
+    let offset = (self.radius * self.radius - (distance * distance - along * along)).max(0.).sqrt();
+
+    // stop just short of the surface, so the sphere doesn't shadow its own light
+    let position = point + direction * (along - offset - EPSILON);
+
+    Some(PointLight::new(position, self.intensity / samples as f64))
+  }
+}
+
 /// Lighting data used in the phong model; computed from intersection information in the scene.
 pub struct LightingData<'a> {
   pub object: &'a dyn Traceable,
@@ -31,6 +74,7 @@ pub struct LightingData<'a> {
   pub normal: Vector,
   pub reflect_direction: Vector,
   pub distance: f64,
+  pub time: f64,
   pub inside: bool,
   pub refractivity: [f64; 2],
 }
@@ -44,21 +88,23 @@ impl<'a> LightingData<'a> {
     let world_position = ray.position(hit.distance);
     let eye = -ray.direction;
     let distance = hit.distance;
+    let time = ray.time;
 
-    let mut normal = object.normal_at(world_position);
-
-    let over_position = world_position + normal * 0.0001;
-    let under_position = world_position - normal * 0.0001;
-    let object_position = object.world_to_object(over_position);
-    let reflect_direction = ray.direction.reflect(normal);
+    let mut normal = hit.normal_at(world_position, ray.time);
 
-    // determine if we're inside the object
+    // determine if we're inside the object, or looking at the back of a surface
     let mut inside = false;
     if normal.dot(eye) < 0. {
       normal = -normal;
       inside = true;
     }
 
+    // offset along the flipped normal, so that the back of a surface is lit from its own side
+    let over_position = world_position + normal * 0.0001;
+    let under_position = world_position - normal * 0.0001;
+    let object_position = hit.world_to_object(over_position, ray.time);
+    let reflect_direction = ray.direction.reflect(normal);
+
     // computes object refractivity
     let refractivity = Self::compute_refractivity(hit, hits);
 
@@ -73,11 +119,15 @@ impl<'a> LightingData<'a> {
       reflect_direction,
       inside,
       distance,
+      time,
       refractivity,
     }
   }
 
   /// Computes the refractive indices for hit objects.
+  ///
+  /// Thin surfaces never contain anything; light passes from the surrounding medium into them
+  /// and straight back out, so they report the surrounding index and their own.
   fn compute_refractivity(hit: &Hit, hits: &HitList) -> [f64; 2] {
     // determine first and last refractive indices
     let mut n1 = 0.;
@@ -93,12 +143,20 @@ impl<'a> LightingData<'a> {
           .last()
           .map(|it| it.object.material().refractivity)
           .unwrap_or(1.);
+
+        if hit.object.material().thin {
+          return [n1, hit.object.material().refractivity];
+        }
+      }
+
+      if i.object.material().thin {
+        continue;
       }
 
-      if containers.contains(&i) {
-        containers.retain(|it| *it != i);
-      } else {
-        containers.push(i);
+      // the same surface is entered and exited at different distances
+      match containers.iter().position(|it| it.same_surface(i)) {
+        Some(index) => { containers.remove(index); }
+        None => containers.push(i),
       }
 
       // exiting the object?
@@ -116,8 +174,28 @@ impl<'a> LightingData<'a> {
   }
 }
 
+/// The individual terms of the phong model, useful for debugging and split render passes.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PhongComponents {
+  pub ambient: Color,
+  pub diffuse: Color,
+  pub specular: Color,
+}
+
+impl PhongComponents {
+  /// Combines the components into the final lit color.
+  pub fn total(&self) -> Color {
+    self.ambient + self.diffuse + self.specular
+  }
+}
+
 /// Computes lighting for a particular point in the scene via phong model.
-pub fn phong_lighting(light: &PointLight, material: &Material, world_position: Vector, object_position: Vector, eye: Vector, normal: Vector, in_shadow: bool) -> Color {
+pub fn phong_lighting(light: &PointLight, material: &Material, world_position: Point, object_position: Point, eye: Vector, normal: Vector, in_shadow: bool) -> Color {
+  phong_lighting_components(light, material, world_position, object_position, eye, normal, in_shadow).total()
+}
+
+/// Computes the separate ambient, diffuse and specular terms of the phong model.
+pub fn phong_lighting_components(light: &PointLight, material: &Material, world_position: Point, object_position: Point, eye: Vector, normal: Vector, in_shadow: bool) -> PhongComponents {
   // combine surface color with the light color/intensity
   let effective_color = material.texture.sample_at(object_position) * light.intensity;
 
@@ -125,7 +203,7 @@ pub fn phong_lighting(light: &PointLight, material: &Material, world_position: V
   let light_direction = (light.position - world_position).normalize();
 
   // compute color contributions
-  let ambient = effective_color * material.ambient;
+  let ambient = material.ambient_color_at(object_position) * light.intensity * material.ambient;
   let mut diffuse = Color::BLACK;
   let mut specular = Color::BLACK;
 
@@ -135,46 +213,107 @@ pub fn phong_lighting(light: &PointLight, material: &Material, world_position: V
     // compute the diffuse contribution
     diffuse = effective_color * material.diffuse * light_dot_normal;
 
-    // A negative number means the light reflects away from the eye
-    let reflect_direction = -light_direction.reflect(normal);
-    let reflect_dot_eye = reflect_direction.dot(eye);
+    if material.blinn_phong {
+      // normalizing by (n + 8) / 8π keeps the reflected energy constant as the highlight tightens
+      let halfway = (light_direction + eye).normalize();
+      let normal_dot_halfway = normal.dot(halfway).max(0.);
+      let normalization = (material.shininess + 8.) / (8. * PI);
+      let factor = normalization * normal_dot_halfway.powf(material.shininess) * light_dot_normal;
 
-    if reflect_dot_eye >= 0. {
-      // compute the specular contribution
-      let factor = reflect_dot_eye.powf(material.shininess);
       specular = light.intensity * material.specular * factor;
+    } else {
+      // A negative number means the light reflects away from the eye
+      let reflect_direction = -light_direction.reflect(normal);
+      let reflect_dot_eye = reflect_direction.dot(eye);
+
+      if reflect_dot_eye >= 0. {
+        // compute the specular contribution
+        let factor = reflect_dot_eye.powf(material.shininess);
+        specular = light.intensity * material.specular * factor;
+      }
     }
   }
 
   if in_shadow {
-    ambient
-  } else {
-    ambient + diffuse + specular
+    diffuse = Color::BLACK;
+    specular = Color::BLACK;
+  }
+
+  PhongComponents { ambient, diffuse, specular }
+}
+
+/// Computes lighting for a particular point in the scene via a Cook-Torrance microfacet model.
+///
+/// Uses the GGX normal distribution, Smith-Schlick geometry and Schlick fresnel terms. The
+/// result is scaled by π so that a white lambertian surface lit head-on matches the phong
+/// model's diffuse term; roughness and metallic come from the material's shading model.
+pub fn cook_torrance_lighting(light: &PointLight, material: &Material, world_position: Point, object_position: Point, eye: Vector, normal: Vector, in_shadow: bool) -> Color {
+  let (roughness, metallic) = match material.shading_model {
+    ShadingModel::CookTorrance { roughness, metallic } => (roughness.clamp(0.01, 1.), metallic.clamp(0., 1.)),
+    ShadingModel::Phong => (1., 0.),
+  };
+
+  let albedo = material.texture.sample_at(object_position);
+  let ambient = material.ambient_color_at(object_position) * light.intensity * material.ambient;
+
+  let light_direction = (light.position - world_position).normalize();
+  let normal_dot_light = normal.dot(light_direction);
+  let normal_dot_eye = normal.dot(eye);
+
+  // A negative number means the light (or eye) is on the other side of the surface
+  if in_shadow || normal_dot_light <= 0. || normal_dot_eye <= 0. {
+    return ambient;
   }
+
+  let halfway = (light_direction + eye).normalize();
+  let normal_dot_halfway = normal.dot(halfway).max(0.);
+  let eye_dot_halfway = eye.dot(halfway).max(0.);
+
+  // GGX normal distribution
+  let alpha = roughness * roughness;
+  let alpha2 = alpha * alpha;
+  let denominator = normal_dot_halfway * normal_dot_halfway * (alpha2 - 1.) + 1.;
+  let distribution = alpha2 / (PI * denominator * denominator);
+
+  // Smith-Schlick geometric shadowing/masking
+  let k = (roughness + 1.) * (roughness + 1.) / 8.;
+  let g1 = |x: f64| x / (x * (1. - k) + k);
+  let geometry = g1(normal_dot_light) * g1(normal_dot_eye);
+
+  // Schlick fresnel; metals tint their reflections with the albedo
+  let dielectric = rgb(0.04, 0.04, 0.04);
+  let f0 = dielectric * (1. - metallic) + albedo * metallic;
+  let fresnel = f0 + (Color::WHITE - f0) * (1. - eye_dot_halfway).powi(5);
+
+  let specular = fresnel * (distribution * geometry / (4. * normal_dot_light * normal_dot_eye));
+  let diffuse = (Color::WHITE - fresnel) * albedo * (1. - metallic) * (1. / PI);
+
+  ambient + (diffuse + specular) * light.intensity * (PI * normal_dot_light)
 }
 
 #[cfg(test)]
 mod tests {
-  use crate::maths::{EPSILON, Matrix4x4, point, rgb, vec3};
+  use crate::graphics::StripedPattern;
+  use crate::maths::{EPSILON, Matrix4x4, PI, point, rgb, vec3};
   use crate::scene::{HitList, Plane, SceneNode, Sphere};
 
   use super::*;
 
   #[test]
   fn point_light_should_have_position_and_intensity() {
-    let light = PointLight::new(vec3(0., 0., 2.), rgb(1., 0., 1.));
+    let light = PointLight::new(point(0., 0., 2.), rgb(1., 0., 1.));
 
-    assert_eq!(light.position, vec3(0., 0., 2.));
+    assert_eq!(light.position, point(0., 0., 2.));
     assert_eq!(light.intensity, rgb(1., 0., 1.));
   }
 
   #[test]
   fn phong_lighting_with_the_eye_between_light_and_surface() {
     let material = Material::default();
-    let position = vec3(0., 0., 0.);
+    let position = point(0., 0., 0.);
     let eye = vec3(0., 0., -1.);
     let normal = vec3(0., 0., -1.);
-    let light = PointLight::new(vec3(0., 0., -10.), rgb(1., 1., 1.));
+    let light = PointLight::new(point(0., 0., -10.), rgb(1., 1., 1.));
 
     let result = phong_lighting(&light, &material, position, position, eye, normal, false);
 
@@ -184,10 +323,10 @@ mod tests {
   #[test]
   fn phong_lighting_with_eye_between_light_and_surface_offset_45_degrees() {
     let material = Material::default();
-    let position = vec3(0., 0., 0.);
+    let position = point(0., 0., 0.);
     let eye = vec3(0., 2f64.sqrt() / 2., 2f64.sqrt() / 2.);
     let normal = vec3(0., 0., -1.);
-    let light = PointLight::new(vec3(0., 0., -10.), rgb(1., 1., 1.));
+    let light = PointLight::new(point(0., 0., -10.), rgb(1., 1., 1.));
 
     let result = phong_lighting(&light, &material, position, position, eye, normal, false);
 
@@ -197,10 +336,10 @@ mod tests {
   #[test]
   fn phong_lighting_with_eye_opposite_surface_light_offset_45_degrees() {
     let material = Material::default();
-    let position = vec3(0., 0., 0.);
+    let position = point(0., 0., 0.);
     let eye = vec3(0., 0., -1.);
     let normal = vec3(0., 0., -1.);
-    let light = PointLight::new(vec3(0., 10., -10.), rgb(1., 1., 1.));
+    let light = PointLight::new(point(0., 10., -10.), rgb(1., 1., 1.));
 
     let result = phong_lighting(&light, &material, position, position, eye, normal, false);
 
@@ -210,10 +349,10 @@ mod tests {
   #[test]
   fn phong_lighting_with_eye_in_the_path_of_the_reflection_vector() {
     let material = Material::default();
-    let position = vec3(0., 0., 0.);
+    let position = point(0., 0., 0.);
     let eye = vec3(0., -2f64.sqrt() / 2., -2f64.sqrt() / 2.);
     let normal = vec3(0., 0., -1.);
-    let light = PointLight::new(vec3(0., 10., -10.), rgb(1., 1., 1.));
+    let light = PointLight::new(point(0., 10., -10.), rgb(1., 1., 1.));
 
     let result = phong_lighting(&light, &material, position, position, eye, normal, false);
 
@@ -223,16 +362,159 @@ mod tests {
   #[test]
   fn phong_lighting_with_light_behind_the_surface() {
     let material = Material::default();
-    let position = vec3(0., 0., 0.);
+    let position = point(0., 0., 0.);
     let eye = vec3(0., 0., -1.);
     let normal = vec3(0., 0., -1.);
-    let light = PointLight::new(vec3(0., 0., 10.), rgb(1., 1., 1.));
+    let light = PointLight::new(point(0., 0., 10.), rgb(1., 1., 1.));
 
     let result = phong_lighting(&light, &material, position, position, eye, normal, false);
 
     assert_eq!(result, rgb(0.1, 0.1, 0.1));
   }
 
+  #[test]
+  fn phong_components_sum_to_combined_lighting() {
+    let material = Material::default();
+    let position = point(0., 0., 0.);
+    let eye = vec3(0., -2f64.sqrt() / 2., -2f64.sqrt() / 2.);
+    let normal = vec3(0., 0., -1.);
+    let light = PointLight::new(point(0., 10., -10.), rgb(1., 1., 1.));
+
+    let components = phong_lighting_components(&light, &material, position, position, eye, normal, false);
+    let combined = phong_lighting(&light, &material, position, position, eye, normal, false);
+
+    assert_eq!(components.ambient, rgb(0.1, 0.1, 0.1));
+    assert_eq!(components.ambient + components.diffuse + components.specular, combined);
+  }
+
+  #[test]
+  fn phong_components_have_no_specular_with_light_behind_the_surface() {
+    let material = Material::default();
+    let position = point(0., 0., 0.);
+    let eye = vec3(0., 0., -1.);
+    let normal = vec3(0., 0., -1.);
+    let light = PointLight::new(point(0., 0., 10.), rgb(1., 1., 1.));
+
+    let components = phong_lighting_components(&light, &material, position, position, eye, normal, false);
+
+    assert_eq!(components.diffuse, Color::BLACK);
+    assert_eq!(components.specular, Color::BLACK);
+  }
+
+  #[test]
+  fn blinn_phong_highlight_peaks_along_the_half_vector() {
+    let material = Material::default().with_blinn_phong(true).with_specular(1.).with_shininess(50.);
+    let position = point(0., 0., 0.);
+    let normal = vec3(0., 0., -1.);
+    let light = PointLight::new(point(0., 10., -10.), rgb(1., 1., 1.));
+
+    let specular_at = |angle: f64| {
+      let eye = vec3(0., -angle.sin(), -angle.cos());
+
+      phong_lighting_components(&light, &material, position, position, eye, normal, false).specular.r
+    };
+
+    // the half-vector lines up with the normal when the eye mirrors the light, 45° below it
+    let angles: Vec<f64> = (0..=90).map(|degrees| (degrees as f64).to_radians()).collect();
+    let peak = angles.iter().copied().max_by(|a, b| specular_at(*a).total_cmp(&specular_at(*b))).unwrap();
+    let bound = (50. + 8.) / (8. * PI) * 2f64.sqrt() / 2.;
+
+    assert!((peak - PI / 4.).abs() < 0.02);
+    assert!(specular_at(peak) <= bound + EPSILON);
+    assert!(angles.iter().all(|&angle| specular_at(angle).is_finite() && specular_at(angle) <= bound + EPSILON));
+  }
+
+  #[test]
+  fn occlusion_map_only_darkens_the_ambient_term() {
+    let gray = rgb(0.5, 0.5, 0.5);
+    let plain = Material::default();
+    let occluded = Material::default().with_occlusion(StripedPattern::new(gray, gray));
+
+    let position = point(0., 0., 0.);
+    let eye = vec3(0., 0., -1.);
+    let normal = vec3(0., 0., -1.);
+    let light = PointLight::new(point(0., 10., -10.), rgb(1., 1., 1.));
+
+    let plain = phong_lighting_components(&light, &plain, position, position, eye, normal, false);
+    let occluded = phong_lighting_components(&light, &occluded, position, position, eye, normal, false);
+
+    assert_eq!(occluded.ambient, plain.ambient * 0.5);
+    assert_eq!(occluded.diffuse, plain.diffuse);
+    assert_eq!(occluded.specular, plain.specular);
+  }
+
+  #[test]
+  fn cook_torrance_rough_metal_spreads_highlight_wider() {
+    let smooth = Material::default().with_shading_model(ShadingModel::CookTorrance { roughness: 0.1, metallic: 1. });
+    let rough = Material::default().with_shading_model(ShadingModel::CookTorrance { roughness: 0.6, metallic: 1. });
+
+    let position = point(0., 0., 0.);
+    let normal = vec3(0., 1., 0.);
+    let light = PointLight::new(point(-10., 10., 0.), rgb(1., 1., 1.));
+
+    let mirror = vec3(1., 1., 0.).normalize();
+    let off_peak = vec3(1., 2., 0.).normalize();
+
+    let lighting = |material: &Material, eye: Vector| {
+      cook_torrance_lighting(&light, material, position, position, eye, normal, false).r
+    };
+
+    // smooth metal concentrates energy at the mirror direction, rough spreads it out
+    assert!(lighting(&smooth, mirror) > lighting(&rough, mirror));
+    assert!(lighting(&rough, off_peak) > lighting(&smooth, off_peak));
+  }
+
+  #[test]
+  fn cook_torrance_does_not_gain_energy() {
+    let normal = vec3(0., 1., 0.);
+    let position = point(0., 0., 0.);
+    let light_direction = vec3(-1., 1., 0.).normalize();
+    let light = PointLight::new(position + light_direction * 1000., rgb(1., 1., 1.));
+
+    for roughness in [0.2, 0.5, 1.] {
+      for metallic in [0., 1.] {
+        let material = Material::default()
+          .with_ambient(0.)
+          .with_shading_model(ShadingModel::CookTorrance { roughness, metallic });
+
+        // integrate the reflected light over the hemisphere of view directions
+        let (steps_theta, steps_phi) = (90, 180);
+        let (d_theta, d_phi) = (PI / 2. / steps_theta as f64, 2. * PI / steps_phi as f64);
+        let mut reflectance = 0.;
+
+        for i in 0..steps_theta {
+          let theta = (i as f64 + 0.5) * d_theta;
+
+          for j in 0..steps_phi {
+            let phi = (j as f64 + 0.5) * d_phi;
+            let eye = vec3(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin());
+
+            let color = cook_torrance_lighting(&light, &material, position, position, eye, normal, false);
+            let radiance = color.r / (PI * light_direction.dot(normal));
+
+            reflectance += radiance * theta.cos() * theta.sin() * d_theta * d_phi;
+          }
+        }
+
+        assert!(reflectance <= 1.01, "roughness {} metallic {} reflects {}", roughness, metallic, reflectance);
+        assert!(reflectance > 0.2);
+      }
+    }
+  }
+
+  #[test]
+  fn cook_torrance_in_shadow_is_only_ambient() {
+    let material = Material::default().with_shading_model(ShadingModel::CookTorrance { roughness: 0.5, metallic: 0. });
+    let position = point(0., 0., 0.);
+    let eye = vec3(0., 0., -1.);
+    let normal = vec3(0., 0., -1.);
+    let light = PointLight::new(point(0., 0., -10.), rgb(1., 1., 1.));
+
+    let color = cook_torrance_lighting(&light, &material, position, position, eye, normal, true);
+
+    assert_eq!(color, rgb(0.1, 0.1, 0.1));
+  }
+
   #[test]
   fn calculate_lighting_data_for_an_intersection() {
     let ray = Ray::new(point(0., 0., -5.), vec3(0., 0., 1.));
@@ -249,6 +531,7 @@ mod tests {
   }
 
   #[test]
+  #[allow(clippy::bool_assert_comparison)]
   fn calculate_lighting_data_determines_outside() {
     let ray = Ray::new(point(0., 0., -5.), vec3(0., 0., 1.));
     let sphere = Sphere::new();
@@ -262,6 +545,7 @@ mod tests {
   }
 
   #[test]
+  #[allow(clippy::bool_assert_comparison)]
   fn calculate_lighting_data_determines_inside() {
     let ray = Ray::new(point(0., 0., 0.), vec3(0., 0., 1.));
     let sphere = Sphere::new();
@@ -277,6 +561,21 @@ mod tests {
     assert_eq!(data.inside, true);
   }
 
+  #[test]
+  fn calculate_lighting_data_offsets_towards_the_viewed_side_of_a_plane() {
+    let ray = Ray::new(point(0., -1., 0.), vec3(0., 1., 0.));
+    let plane = Plane::new(vec3(0., 1., 0.));
+
+    let hit = Hit::new(&plane, 1.);
+    let hits = HitList::from(&[hit]);
+
+    let data = LightingData::calculate(ray, &hits[0], &hits);
+
+    assert_eq!(data.normal, vec3(0., -1., 0.));
+    assert!(data.over_position.y < 0.);
+    assert!(data.under_position.y > 0.);
+  }
+
   #[test]
   fn calculate_lighting_data_adds_point_in_direction_of_normal() {
     let ray = Ray::new(point(0., 0., -5.), vec3(0., 0., 1.));
@@ -297,7 +596,7 @@ mod tests {
     let position = point(0., 0., 0.);
     let eye = vec3(0., 0., -1.);
     let normal = vec3(0., 0., -1.);
-    let light = PointLight::new(vec3(0., 0., -10.), rgb(1., 1., 1.));
+    let light = PointLight::new(point(0., 0., -10.), rgb(1., 1., 1.));
 
     let color = phong_lighting(&light, &material, position, position, eye, normal, true);
 
@@ -317,8 +616,7 @@ mod tests {
     assert_eq!(data.reflect_direction, vec3(0., 2f64.sqrt() / 2., 2f64.sqrt() / 2.));
   }
 
-  // TODO: fix these up?
-  // #[test]
+  #[test]
   fn calculate_lighting_data_finds_refractive_indices_at_various_intersections() {
     // build nodes
     let a = create_glass_sphere(1.5);
@@ -350,6 +648,30 @@ mod tests {
     assert_eq!(data[5].refractivity, [1.5, 1.0]);
   }
 
+  #[test]
+  fn thin_surfaces_do_not_contain_what_is_behind_them() {
+    let pane = Plane::new(vec3(0., 0., 1.))
+      .with_material(Material::default().with_transparency(1.).with_refractivity(1.5).with_thin(true))
+      .with_transform(Matrix4x4::rotate_x(PI / 2.));
+    let sphere = create_glass_sphere(2.).with_transform(Matrix4x4::translate(0., 0., 4.));
+
+    let ray = Ray::new(point(0., 0., -4.), vec3(0., 0., 1.));
+
+    let mut hits = HitList::new();
+    hits.push(&pane, 4.);
+    hits.push(&sphere, 7.);
+    hits.push(&sphere, 9.);
+
+    let data: Vec<_> = hits
+      .iter()
+      .map(|hit| LightingData::calculate(ray, hit, &hits))
+      .collect();
+
+    assert_eq!(data[0].refractivity, [1.0, 1.5]);
+    assert_eq!(data[1].refractivity, [1.0, 2.0]);
+    assert_eq!(data[2].refractivity, [2.0, 1.0]);
+  }
+
   #[test]
   fn calculate_lighting_data_under_point_is_just_below_surface() {
     let ray = Ray::new(point(0., 0., -5.), vec3(0., 0., 1.));
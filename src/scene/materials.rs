@@ -1,17 +1,22 @@
 //! Material management for objects.
 
+use std::sync::Arc;
+
 use crate::graphics::ColorPattern;
-use crate::maths::{Color, Vector};
+use crate::maths::{ApproxEq, Color, Point};
 
 /// A texture for use in material rendering.
+///
+/// Patterns are shared, so cloning a texture is cheap.
+#[derive(Clone)]
 pub enum Texture {
   Solid(Color),
-  Pattern(Box<dyn ColorPattern>),
+  Pattern(Arc<dyn ColorPattern>),
 }
 
 impl Texture {
   /// Samples the materials color at the given object point.
-  pub fn sample_at(&self, point: Vector) -> Color {
+  pub fn sample_at(&self, point: Point) -> Color {
     match self {
       Texture::Solid(color) => *color,
       Texture::Pattern(pattern) => pattern.sample_at(point)
@@ -19,9 +24,47 @@ impl Texture {
   }
 }
 
+impl PartialEq for Texture {
+  fn eq(&self, other: &Self) -> bool {
+    match (self, other) {
+      (Texture::Solid(a), Texture::Solid(b)) => a == b,
+      (Texture::Pattern(a), Texture::Pattern(b)) => same_pattern(a, b),
+      _ => false,
+    }
+  }
+}
+
+/// Determines if two shared patterns are the same instance, or are equal by value.
+fn same_pattern(a: &Arc<dyn ColorPattern>, b: &Arc<dyn ColorPattern>) -> bool {
+  Arc::ptr_eq(a, b) || a.pattern_eq(b.as_ref())
+}
+
+/// Determines if two optional shared patterns are both absent, or are the same pattern.
+fn same_optional_pattern(a: &Option<Arc<dyn ColorPattern>>, b: &Option<Arc<dyn ColorPattern>>) -> bool {
+  match (a, b) {
+    (Some(a), Some(b)) => same_pattern(a, b),
+    (None, None) => true,
+    _ => false,
+  }
+}
+
+/// The lighting model used to shade a material.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ShadingModel {
+  /// The classic phong model; ambient, diffuse and specular terms.
+  Phong,
+  /// A physically-based microfacet model using the GGX distribution.
+  CookTorrance { roughness: f64, metallic: f64 },
+}
+
 /// Defines a material used in scene rendering.
+#[derive(Clone)]
 pub struct Material {
   pub texture: Texture,
+  pub ambient_map: Option<Arc<dyn ColorPattern>>,
+  pub occlusion: Option<Arc<dyn ColorPattern>>,
+  pub reflectivity_map: Option<Arc<dyn ColorPattern>>,
+  pub shading_model: ShadingModel,
   pub ambient: f64,
   pub diffuse: f64,
   pub specular: f64,
@@ -29,6 +72,26 @@ pub struct Material {
   pub transparency: f64,
   pub reflectivity: f64,
   pub refractivity: f64,
+  /// Derive reflectance from the refractive index and view angle when no reflectivity is set.
+  pub fresnel: bool,
+  /// Skip hits on the back of flat faces, for closed meshes.
+  pub cull_backfaces: bool,
+  /// The distance over which reflections fade by a factor of e; infinite reflections never fade.
+  pub reflection_falloff: f64,
+  /// Blurs reflections from 0 (a perfect mirror) to 1 (spread over the whole hemisphere).
+  pub reflection_roughness: f64,
+  /// Blurs refractions from 0 (clear glass) to 1 (spread over the whole hemisphere), for frosted glass.
+  pub refraction_roughness: f64,
+  /// Light given off by the surface itself, regardless of any lights.
+  pub emission: Color,
+  /// Tints reflections, as metals do; with Fresnel enabled, grazing reflections fade back to white.
+  pub reflection_tint: Color,
+  /// Use a normalized Blinn-Phong highlight, which keeps its energy as the shininess changes.
+  pub blinn_phong: bool,
+  /// Treat the surface as a thin sheet, like a glass pane, which light passes straight through.
+  pub thin: bool,
+  /// Share the light between reflection, refraction and the surface itself, so they never sum past 1.
+  pub conserve_energy: bool,
 }
 
 impl Default for Material {
@@ -36,6 +99,10 @@ impl Default for Material {
   fn default() -> Self {
     Self {
       texture: Texture::Solid(Color::WHITE),
+      ambient_map: None,
+      occlusion: None,
+      reflectivity_map: None,
+      shading_model: ShadingModel::Phong,
       ambient: 0.1,
       diffuse: 0.9,
       specular: 0.9,
@@ -43,11 +110,132 @@ impl Default for Material {
       transparency: 0.,
       reflectivity: 0.,
       refractivity: 1.,
+      fresnel: false,
+      cull_backfaces: false,
+      reflection_falloff: f64::INFINITY,
+      reflection_roughness: 0.,
+      refraction_roughness: 0.,
+      emission: Color::BLACK,
+      reflection_tint: Color::WHITE,
+      blinn_phong: false,
+      thin: false,
+      conserve_energy: false,
     }
   }
 }
 
+impl PartialEq for Material {
+  fn eq(&self, other: &Self) -> bool {
+    self.texture == other.texture
+      && same_optional_pattern(&self.ambient_map, &other.ambient_map)
+      && same_optional_pattern(&self.occlusion, &other.occlusion)
+      && same_optional_pattern(&self.reflectivity_map, &other.reflectivity_map)
+      && self.shading_model == other.shading_model
+      && self.ambient == other.ambient
+      && self.diffuse == other.diffuse
+      && self.specular == other.specular
+      && self.shininess == other.shininess
+      && self.transparency == other.transparency
+      && self.reflectivity == other.reflectivity
+      && self.refractivity == other.refractivity
+      && self.fresnel == other.fresnel
+      && self.cull_backfaces == other.cull_backfaces
+      && self.reflection_falloff == other.reflection_falloff
+      && self.reflection_roughness == other.reflection_roughness
+      && self.refraction_roughness == other.refraction_roughness
+      && self.emission == other.emission
+      && self.reflection_tint == other.reflection_tint
+      && self.blinn_phong == other.blinn_phong
+      && self.thin == other.thin
+      && self.conserve_energy == other.conserve_energy
+  }
+}
+
 impl Material {
+  /// Creates a dielectric material whose reflectance follows the Fresnel equations for the given
+  /// index of refraction, rather than an authored reflectivity.
+  pub fn dielectric(ior: f64) -> Self {
+    Self {
+      refractivity: ior,
+      fresnel: true,
+      ..Self::default()
+    }
+  }
+
+  /// Creates a polished metal, which reflects its own color and barely scatters any light diffusely.
+  pub fn metal(color: Color) -> Self {
+    Self {
+      texture: Texture::Solid(color),
+      diffuse: 0.1,
+      specular: 1.,
+      shininess: 300.,
+      reflectivity: 1.,
+      fresnel: true,
+      reflection_tint: color,
+      ..Self::default()
+    }
+  }
+
+  /// Determines if reflectance should be computed from the index of refraction.
+  pub fn uses_fresnel(&self) -> bool {
+    self.fresnel && self.reflectivity == 0.
+  }
+
+  /// Determines if back faces should be culled; transparent materials always keep both faces.
+  pub fn culls_backfaces(&self) -> bool {
+    self.cull_backfaces && self.transparency.is_approx(0.)
+  }
+
+  /// Approximates the fraction of light reflected by this material at the given view angle.
+  ///
+  /// Uses Schlick's approximation with F0 = ((ior - 1) / (ior + 1))², for light arriving from air.
+  pub fn fresnel_reflectance(&self, cos_theta: f64) -> f64 {
+    let r0 = (self.refractivity - 1.) / (self.refractivity + 1.);
+    let r02 = r0 * r0;
+
+    r02 + (1. - r02) * (1. - cos_theta.clamp(0., 1.)).powi(5)
+  }
+
+  /// Computes the tint applied to reflections at the given view angle.
+  ///
+  /// With Fresnel enabled, the tint acts as the reflectance at normal incidence in Schlick's
+  /// approximation, rising to white at grazing angles.
+  pub fn reflection_tint_at(&self, cos_theta: f64) -> Color {
+    if !self.fresnel {
+      return self.reflection_tint;
+    }
+
+    let grazing = (1. - cos_theta.clamp(0., 1.)).powi(5);
+
+    self.reflection_tint + (Color::WHITE - self.reflection_tint) * grazing
+  }
+
+  /// Samples the color used for the ambient term at the given object point.
+  ///
+  /// This is the surface color, unless a separate ambient map is present, darkened by the
+  /// occlusion map if there is one.
+  pub fn ambient_color_at(&self, point: Point) -> Color {
+    let color = match &self.ambient_map {
+      Some(pattern) => pattern.sample_at(point),
+      None => self.texture.sample_at(point),
+    };
+
+    match &self.occlusion {
+      Some(pattern) => color * pattern.sample_at(point),
+      None => color,
+    }
+  }
+
+  /// Samples the per-channel reflectivity at the given object point.
+  ///
+  /// The reflectivity map, if present, masks the material's scalar reflectivity.
+  pub fn reflectivity_at(&self, point: Point) -> Color {
+    match &self.reflectivity_map {
+      Some(pattern) => pattern.sample_at(point) * self.reflectivity,
+      None => Color::WHITE * self.reflectivity,
+    }
+  }
+
   /// Applies the given color.
   pub fn with_color(self, color: Color) -> Self {
     Material { texture: Texture::Solid(color), ..self }
@@ -55,7 +243,27 @@ impl Material {
 
   /// Applies the given pattern.
   pub fn with_pattern(self, pattern: impl ColorPattern + 'static) -> Self {
-    Material { texture: Texture::Pattern(Box::new(pattern)), ..self }
+    Material { texture: Texture::Pattern(Arc::new(pattern)), ..self }
+  }
+
+  /// Applies the given pattern to the ambient term only.
+  pub fn with_ambient_map(self, pattern: impl ColorPattern + 'static) -> Self {
+    Material { ambient_map: Some(Arc::new(pattern)), ..self }
+  }
+
+  /// Applies the given (usually grayscale) pattern as baked occlusion over the ambient term.
+  pub fn with_occlusion(self, pattern: impl ColorPattern + 'static) -> Self {
+    Material { occlusion: Some(Arc::new(pattern)), ..self }
+  }
+
+  /// Applies the given pattern as a mask over the reflectivity.
+  pub fn with_reflectivity_map(self, pattern: impl ColorPattern + 'static) -> Self {
+    Material { reflectivity_map: Some(Arc::new(pattern)), ..self }
+  }
+
+  /// Applies the given shading model.
+  pub fn with_shading_model(self, shading_model: ShadingModel) -> Self {
+    Material { shading_model, ..self }
   }
 
   /// Applies the given ambient value.
@@ -92,15 +300,102 @@ impl Material {
   pub fn with_refractivity(self, refractivity: f64) -> Self {
     Material { refractivity, ..self }
   }
+
+  /// Toggles Fresnel-derived reflectance.
+  pub fn with_fresnel(self, fresnel: bool) -> Self {
+    Material { fresnel, ..self }
+  }
+
+  /// Toggles back-face culling.
+  pub fn with_cull_backfaces(self, cull_backfaces: bool) -> Self {
+    Material { cull_backfaces, ..self }
+  }
+
+  /// Fades reflections exponentially with the distance the reflected ray travels.
+  ///
+  /// Reflections of the background or environment, which are infinitely far away, vanish.
+  pub fn with_reflection_falloff(self, reflection_falloff: f64) -> Self {
+    Material { reflection_falloff, ..self }
+  }
+
+  /// Blurs reflections, as on brushed or unpolished surfaces; 0 gives a perfect mirror.
+  pub fn with_reflection_roughness(self, reflection_roughness: f64) -> Self {
+    Material { reflection_roughness: reflection_roughness.clamp(0., 1.), ..self }
+  }
+
+  /// Blurs refractions, as through frosted glass; 0 gives a clear image.
+  pub fn with_refraction_roughness(self, refraction_roughness: f64) -> Self {
+    Material { refraction_roughness: refraction_roughness.clamp(0., 1.), ..self }
+  }
+
+  /// Tints reflections with the given color.
+  pub fn with_reflection_tint(self, reflection_tint: Color) -> Self {
+    Material { reflection_tint, ..self }
+  }
+
+  /// Switches the phong highlight to a normalized Blinn-Phong one, based on the half-vector.
+  pub fn with_blinn_phong(self, blinn_phong: bool) -> Self {
+    Material { blinn_phong, ..self }
+  }
+
+  /// Marks the surface as a thin sheet, such as a single-sided glass pane.
+  ///
+  /// Thin surfaces don't bend light or enclose a medium; they only reflect and filter it.
+  pub fn with_thin(self, thin: bool) -> Self {
+    Material { thin, ..self }
+  }
+
+  /// Splits the light between reflection, refraction and the surface so the total never exceeds it.
+  ///
+  /// Without this, reflective and transparent surfaces simply add their contributions together.
+  pub fn with_conserve_energy(self, conserve_energy: bool) -> Self {
+    Material { conserve_energy, ..self }
+  }
+
+  /// Makes the surface glow with the given color, even when unlit.
+  ///
+  /// This only affects how the surface looks; see `Scene::add_emissive` to light other objects with it.
+  pub fn with_emission(self, emission: Color) -> Self {
+    Material { emission, ..self }
+  }
 }
 
 #[cfg(test)]
 mod tests {
-  use crate::graphics::StripedPattern;
-  use crate::maths::point;
+  use crate::graphics::{CheckerPattern, StripedPattern, TransformPattern};
+  use crate::maths::{Matrix4x4, point, rgb};
 
   use super::*;
 
+  #[test]
+  fn cloned_material_with_pattern_is_equal_and_independent() {
+    let original = Material::default()
+      .with_pattern(StripedPattern::new(Color::WHITE, Color::BLACK))
+      .with_occlusion(CheckerPattern::new(Color::WHITE, Color::BLACK));
+
+    let copy = original.clone();
+
+    assert!(copy == original);
+
+    let edited = copy.with_ambient(0.5).with_pattern(StripedPattern::new(Color::RED, Color::BLACK));
+
+    assert!(edited != original);
+    assert_eq!(original.ambient, 0.1);
+    assert_eq!(original.texture.sample_at(point(0., 0., 0.)), Color::WHITE);
+  }
+
+  #[test]
+  fn materials_with_equal_patterns_are_equal() {
+    let build = |a: Color| Material::default().with_pattern(
+      TransformPattern::new(StripedPattern::new(a, Color::BLACK)).with_transform(Matrix4x4::scale(2., 2., 2.))
+    );
+
+    assert!(build(Color::WHITE) == build(Color::WHITE));
+    assert!(build(Color::WHITE) != build(Color::RED));
+    assert!(build(Color::WHITE) != Material::default().with_pattern(CheckerPattern::new(Color::WHITE, Color::BLACK)));
+    assert!(Material::default() != Material::default().with_color(Color::RED));
+  }
+
   #[test]
   fn material_should_yield_solid_texture() {
     let material = Material::default().with_color(Color::WHITE);
@@ -118,4 +413,58 @@ mod tests {
     assert_eq!(material.texture.sample_at(point(1., 0., 0.)), Color::BLACK);
     assert_eq!(material.texture.sample_at(point(2., 0., 0.)), Color::WHITE);
   }
-}
\ No newline at end of file
+
+  #[test]
+  fn material_should_sample_ambient_map_separately() {
+    let material = Material::default()
+      .with_color(Color::RED)
+      .with_ambient_map(StripedPattern::new(Color::WHITE, Color::BLACK));
+
+    assert_eq!(material.ambient_color_at(point(0., 0., 0.)), Color::WHITE);
+    assert_eq!(material.ambient_color_at(point(1., 0., 0.)), Color::BLACK);
+    assert_eq!(material.texture.sample_at(point(1., 0., 0.)), Color::RED);
+  }
+
+  #[test]
+  fn material_should_mask_reflectivity_with_map() {
+    let material = Material::default()
+      .with_reflective(0.5)
+      .with_reflectivity_map(CheckerPattern::new(Color::WHITE, Color::BLACK));
+
+    assert_eq!(material.reflectivity_at(point(0.5, 0., 0.5)), Color::WHITE * 0.5);
+    assert_eq!(material.reflectivity_at(point(1.5, 0., 0.5)), Color::BLACK);
+    assert_eq!(Material::default().with_reflective(0.5).reflectivity_at(point(1.5, 0., 0.5)), Color::WHITE * 0.5);
+  }
+
+  #[test]
+  fn dielectric_should_reflect_little_at_normal_incidence() {
+    let water = Material::dielectric(1.33);
+
+    assert!(water.uses_fresnel());
+    assert!((water.fresnel_reflectance(1.) - 0.02006).abs() < 1e-4);
+  }
+
+  #[test]
+  fn dielectric_should_reflect_almost_everything_at_grazing_angles() {
+    let water = Material::dielectric(1.33);
+
+    assert!(water.fresnel_reflectance(0.) > 0.999);
+    assert!(water.fresnel_reflectance(0.01) > 0.95);
+  }
+
+  #[test]
+  fn authored_reflectivity_should_override_fresnel() {
+    let material = Material::dielectric(1.5).with_reflective(0.3);
+
+    assert!(!material.uses_fresnel());
+  }
+
+  #[test]
+  fn metal_reflection_tint_fades_to_white_at_grazing_angles() {
+    let gold = Material::metal(rgb(1., 0.7, 0.2));
+
+    assert_eq!(gold.reflection_tint_at(1.), rgb(1., 0.7, 0.2));
+    assert_eq!(gold.reflection_tint_at(0.), Color::WHITE);
+    assert_eq!(gold.clone().with_fresnel(false).reflection_tint_at(0.), rgb(1., 0.7, 0.2));
+  }
+}
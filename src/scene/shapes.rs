@@ -1,18 +1,46 @@
 //! Shape rendering.
 
+pub use cones::*;
+pub use cubes::*;
+pub use cylinders::*;
 pub use planes::*;
+pub use sdfs::*;
 pub use spheres::*;
+pub use triangles::*;
 
-use crate::maths::{Matrix4x4, Ray, Vector};
+use crate::maths::{BoundingBox, Matrix4x4, Point, Ray, Vector};
 
+mod cones;
+mod cubes;
+mod cylinders;
 mod planes;
+mod sdfs;
 mod spheres;
+mod triangles;
 
 /// A shape in 3-space that can compute ray intersection and normals.
-pub trait Shape {
+pub trait Shape: Send + Sync {
   /// Computes the distances at which the given ray intersects the shape.
   fn intersect(&self, object_ray: Ray) -> Vec<f64>;
 
-  /// Computes the normal vector at a given world point on the surface of the object.
-  fn normal_at(&self, object_point: Vector, inverse_transform: Matrix4x4) -> Vector;
+  /// Computes the bounds of the shape in object space.
+  fn bounds(&self) -> BoundingBox;
+
+  /// Computes the world space normal vector at a given object point on the surface of the object.
+  ///
+  /// The normal transform is the inverse-transpose of the object's transform, which carries
+  /// object space normals into world space.
+  fn normal_at(&self, object_point: Point, normal_transform: Matrix4x4) -> Vector;
+
+  /// Determines if the given ray meets the shape from behind, for back-face culling.
+  ///
+  /// Only flat shapes have a single facing; by default nothing is culled.
+  fn is_back_facing(&self, _object_ray: Ray) -> bool {
+    false
+  }
+
+  /// Computes the (u, v) texture coordinates at a given object point on the surface, if the shape has any.
+  fn uv_at(&self, _object_point: Point) -> Option<(f64, f64)> {
+    None
+  }
 }
\ No newline at end of file
@@ -0,0 +1,240 @@
+//! Cone objects for use in scene rendering.
+
+use crate::graphics::UvMapping;
+use crate::maths::{BoundingBox, EPSILON, Matrix4x4, point, Point, Ray, transform_normal, vec3, Vector};
+use crate::scene::{SceneNode, Shape};
+
+/// A double-napped cone about the y axis, truncated between a minimum and maximum height.
+#[derive(Clone, Debug)]
+pub struct Cone {
+  pub minimum: f64,
+  pub maximum: f64,
+  pub closed: bool,
+}
+
+impl Cone {
+  /// Constructs a new cone node
+  pub fn new(minimum: f64, maximum: f64, closed: bool) -> SceneNode<Self> {
+    SceneNode::new(Self { minimum, maximum, closed })
+  }
+
+  /// Determines if the ray at the given distance is within the given radius of a cap.
+  fn check_cap(ray: Ray, distance: f64, radius: f64) -> bool {
+    let x = ray.origin.x + distance * ray.direction.x;
+    let z = ray.origin.z + distance * ray.direction.z;
+
+    (x * x + z * z) <= radius * radius
+  }
+
+  /// Intersects the ray with the end caps of the cone, if it's closed.
+  fn intersect_caps(&self, ray: Ray, results: &mut Vec<f64>) {
+    if !self.closed || ray.direction.y.abs() < EPSILON {
+      return;
+    }
+
+    // the radius of the cone at any y is equal to |y|
+    let lower = (self.minimum - ray.origin.y) / ray.direction.y;
+    if Self::check_cap(ray, lower, self.minimum.abs()) {
+      results.push(lower);
+    }
+
+    let upper = (self.maximum - ray.origin.y) / ray.direction.y;
+    if Self::check_cap(ray, upper, self.maximum.abs()) {
+      results.push(upper);
+    }
+  }
+}
+
+impl Shape for Cone {
+  fn intersect(&self, object_ray: Ray) -> Vec<f64> {
+    let mut results = Vec::new();
+
+    let origin = object_ray.origin;
+    let direction = object_ray.direction;
+
+    let a = direction.x * direction.x - direction.y * direction.y + direction.z * direction.z;
+    let b = 2. * origin.x * direction.x - 2. * origin.y * direction.y + 2. * origin.z * direction.z;
+    let c = origin.x * origin.x - origin.y * origin.y + origin.z * origin.z;
+
+    if a.abs() < EPSILON {
+      // the ray is parallel to one of the cone's halves, so there's at most one hit
+      if b.abs() >= EPSILON {
+        let t = -c / (2. * b);
+        let y = origin.y + t * direction.y;
+
+        if self.minimum < y && y < self.maximum {
+          results.push(t);
+        }
+      }
+    } else {
+      let discriminant = b * b - 4. * a * c;
+      if discriminant < 0. {
+        return results;
+      }
+
+      let mut t0 = (-b - discriminant.sqrt()) / (2. * a);
+      let mut t1 = (-b + discriminant.sqrt()) / (2. * a);
+
+      if t0 > t1 {
+        std::mem::swap(&mut t0, &mut t1);
+      }
+
+      for t in [t0, t1] {
+        let y = origin.y + t * direction.y;
+
+        if self.minimum < y && y < self.maximum {
+          results.push(t);
+        }
+      }
+    }
+
+    self.intersect_caps(object_ray, &mut results);
+
+    results
+  }
+
+  fn uv_at(&self, object_point: Point) -> Option<(f64, f64)> {
+    let distance = object_point.x * object_point.x + object_point.z * object_point.z;
+    let radius = object_point.y.abs();
+    let on_cap = distance < radius * radius && (object_point.y >= self.maximum - EPSILON || object_point.y <= self.minimum + EPSILON);
+
+    if on_cap {
+      // caps are mapped flat, with the cap's disc filling the texture
+      return Some(((object_point.x / radius + 1.) / 2., (object_point.z / radius + 1.) / 2.));
+    }
+
+    let (u, _) = UvMapping::Cylindrical.map(object_point);
+    let v = if self.minimum.is_finite() && self.maximum.is_finite() {
+      (object_point.y - self.minimum) / (self.maximum - self.minimum)
+    } else {
+      object_point.y.rem_euclid(1.)
+    };
+
+    Some((u, v))
+  }
+
+  fn bounds(&self) -> BoundingBox {
+    let radius = self.minimum.abs().max(self.maximum.abs());
+
+    BoundingBox::new(point(-radius, self.minimum, -radius), point(radius, self.maximum, radius))
+  }
+
+  fn normal_at(&self, object_point: Point, normal_transform: Matrix4x4) -> Vector {
+    let distance = object_point.x * object_point.x + object_point.z * object_point.z;
+
+    let object_normal = if distance < object_point.y * object_point.y && object_point.y >= self.maximum - EPSILON {
+      vec3(0., 1., 0.)
+    } else if distance < object_point.y * object_point.y && object_point.y <= self.minimum + EPSILON {
+      vec3(0., -1., 0.)
+    } else {
+      let mut y = distance.sqrt();
+
+      if object_point.y > 0. {
+        y = -y;
+      }
+
+      vec3(object_point.x, y, object_point.z)
+    };
+
+    transform_normal(normal_transform, object_normal)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::maths::point;
+  use crate::scene::Traceable;
+
+  use super::*;
+
+  #[test]
+  fn ray_should_intersect_cone() {
+    let cone = Cone::new(f64::NEG_INFINITY, f64::INFINITY, false);
+    let cases = [
+      (point(0., 0., -5.), vec3(0., 0., 1.), 5., 5.),
+      (point(0., 0., -5.), vec3(1., 1., 1.), 8.66025, 8.66025),
+      (point(1., 1., -5.), vec3(-0.5, -1., 1.), 4.55006, 49.44994),
+    ];
+
+    for (origin, direction, t0, t1) in cases {
+      let set = cone.intersect(Ray::new(origin, direction.normalize()));
+
+      assert_eq!(set.len(), 2);
+      assert!((set[0].distance - t0).abs() < 0.0001);
+      assert!((set[1].distance - t1).abs() < 0.0001);
+    }
+  }
+
+  #[test]
+  fn ray_parallel_to_one_half_of_cone() {
+    let cone = Cone::new(f64::NEG_INFINITY, f64::INFINITY, false);
+    let ray = Ray::new(point(0., 0., -1.), vec3(0., 1., 1.).normalize());
+
+    let set = cone.intersect(ray);
+
+    assert_eq!(set.len(), 1);
+    assert!((set[0].distance - 0.35355).abs() < 0.0001);
+  }
+
+  #[test]
+  fn intersecting_the_caps_of_a_closed_cone() {
+    let cone = Cone::new(-0.5, 0.5, true);
+    let cases = [
+      (point(0., 0., -5.), vec3(0., 1., 0.), 0),
+      (point(0., 0., -0.25), vec3(0., 1., 1.), 2),
+      (point(0., 0., -0.25), vec3(0., 1., 0.), 4),
+    ];
+
+    for (origin, direction, count) in cases {
+      let set = cone.intersect(Ray::new(origin, direction.normalize()));
+
+      assert_eq!(set.len(), count);
+    }
+  }
+
+  #[test]
+  fn normal_on_surface_of_cone() {
+    let cone = Cone::new(f64::NEG_INFINITY, f64::INFINITY, false);
+    let cases = [
+      (point(1., 1., 1.), vec3(1., -2f64.sqrt(), 1.).normalize()),
+      (point(-1., -1., 0.), vec3(-1., 1., 0.).normalize()),
+    ];
+
+    for (point, normal) in cases {
+      assert_eq!(cone.normal_at(point), normal);
+    }
+  }
+
+  #[test]
+  fn uv_on_cone_body_wraps_around_axis() {
+    let cone = Cone { minimum: 0., maximum: 2., closed: true };
+    let cases = [
+      (point(0., 1., -1.), (0., 0.5)),
+      (point(0., 1., 1.), (0.5, 0.5)),
+      (point(2., 2., 0.), (0.25, 1.)),
+    ];
+
+    for (point, (u, v)) in cases {
+      let uv = cone.uv_at(point).unwrap();
+
+      assert!((uv.0 - u).abs() < EPSILON, "u at {:?}", point);
+      assert!((uv.1 - v).abs() < EPSILON, "v at {:?}", point);
+    }
+  }
+
+  #[test]
+  fn uv_on_cone_cap_is_planar() {
+    let cone = Cone { minimum: 0., maximum: 2., closed: true };
+    let cases = [
+      (point(0., 2., 0.), (0.5, 0.5)),
+      (point(1., 2., 0.), (0.75, 0.5)),
+    ];
+
+    for (point, (u, v)) in cases {
+      let uv = cone.uv_at(point).unwrap();
+
+      assert!((uv.0 - u).abs() < EPSILON, "u at {:?}", point);
+      assert!((uv.1 - v).abs() < EPSILON, "v at {:?}", point);
+    }
+  }
+}
@@ -0,0 +1,115 @@
+//! Cube objects for use in scene rendering.
+
+use crate::maths::{BoundingBox, Matrix4x4, point, Point, Ray, transform_normal, vec3, Vector};
+use crate::scene::{SceneNode, Shape};
+
+/// An axis-aligned cube in 3-space, spanning -1 to 1 on each axis.
+#[derive(Clone, Debug)]
+pub struct Cube;
+
+impl Cube {
+  const BOUNDS: BoundingBox = BoundingBox::new(point(-1., -1., -1.), point(1., 1., 1.));
+
+  /// Constructs a new cube node
+  pub fn new() -> SceneNode<Self> {
+    SceneNode::new(Self)
+  }
+}
+
+impl Shape for Cube {
+  fn intersect(&self, object_ray: Ray) -> Vec<f64> {
+    match Self::BOUNDS.intersect(object_ray) {
+      Some((min, max)) => vec![min, max],
+      None => vec![],
+    }
+  }
+
+  fn bounds(&self) -> BoundingBox {
+    Self::BOUNDS
+  }
+
+  fn normal_at(&self, object_point: Point, normal_transform: Matrix4x4) -> Vector {
+    let x = object_point.x.abs();
+    let y = object_point.y.abs();
+    let z = object_point.z.abs();
+
+    // the normal points along the axis with the largest component
+    let object_normal = if x >= y && x >= z {
+      vec3(object_point.x, 0., 0.)
+    } else if y >= x && y >= z {
+      vec3(0., object_point.y, 0.)
+    } else {
+      vec3(0., 0., object_point.z)
+    };
+
+    transform_normal(normal_transform, object_normal)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::maths::point;
+  use crate::scene::Traceable;
+
+  use super::*;
+
+  #[test]
+  fn ray_should_intersect_cube_on_each_face() {
+    let cube = Cube::new();
+    let cases = [
+      (point(5., 0.5, 0.), vec3(-1., 0., 0.), 4., 6.),
+      (point(-5., 0.5, 0.), vec3(1., 0., 0.), 4., 6.),
+      (point(0.5, 5., 0.), vec3(0., -1., 0.), 4., 6.),
+      (point(0.5, -5., 0.), vec3(0., 1., 0.), 4., 6.),
+      (point(0.5, 0., 5.), vec3(0., 0., -1.), 4., 6.),
+      (point(0.5, 0., -5.), vec3(0., 0., 1.), 4., 6.),
+      (point(0., 0.5, 0.), vec3(0., 0., 1.), -1., 1.),
+    ];
+
+    for (origin, direction, t1, t2) in cases {
+      let set = cube.intersect(Ray::new(origin, direction));
+
+      assert_eq!(set.len(), 2);
+      assert_eq!(set[0].distance, t1);
+      assert_eq!(set[1].distance, t2);
+    }
+  }
+
+  #[test]
+  fn ray_should_miss_cube() {
+    let cube = Cube::new();
+    let cases = [
+      (point(-2., 0., 0.), vec3(0.2673, 0.5345, 0.8018)),
+      (point(0., -2., 0.), vec3(0.8018, 0.2673, 0.5345)),
+      (point(0., 0., -2.), vec3(0.5345, 0.8018, 0.2673)),
+      (point(2., 0., 2.), vec3(0., 0., -1.)),
+      (point(0., 2., 2.), vec3(0., -1., 0.)),
+      (point(2., 2., 0.), vec3(-1., 0., 0.)),
+    ];
+
+    for (origin, direction) in cases {
+      let set = cube.intersect(Ray::new(origin, direction));
+
+      assert_eq!(set.len(), 0);
+    }
+  }
+
+  #[test]
+  fn normal_on_surface_of_cube() {
+    let cube = Cube::new();
+    let cases = [
+      (point(1., 0.5, -0.8), vec3(1., 0., 0.)),
+      (point(-1., -0.2, 0.9), vec3(-1., 0., 0.)),
+      (point(-0.4, 1., -0.1), vec3(0., 1., 0.)),
+      (point(0.3, -1., -0.7), vec3(0., -1., 0.)),
+      (point(-0.6, 0.3, 1.), vec3(0., 0., 1.)),
+      (point(0.4, 0.4, -1.), vec3(0., 0., -1.)),
+      (point(1., 1., 1.), vec3(1., 0., 0.)),
+      (point(-1., -1., -1.), vec3(-1., 0., 0.)),
+    ];
+
+    for (point, normal) in cases {
+      assert_eq!(cube.normal_at(point), normal);
+    }
+  }
+}
@@ -0,0 +1,276 @@
+//! Cylinder objects for use in scene rendering.
+
+use crate::graphics::UvMapping;
+use crate::maths::{BoundingBox, EPSILON, Matrix4x4, point, Point, Ray, transform_normal, vec3, Vector};
+use crate::scene::{SceneNode, Shape};
+
+/// A cylinder of unit radius about the y axis, truncated between a minimum and maximum height.
+#[derive(Clone, Debug)]
+pub struct Cylinder {
+  pub minimum: f64,
+  pub maximum: f64,
+  pub closed: bool,
+}
+
+impl Cylinder {
+  /// Constructs a new cylinder node
+  pub fn new(minimum: f64, maximum: f64, closed: bool) -> SceneNode<Self> {
+    SceneNode::new(Self { minimum, maximum, closed })
+  }
+
+  /// Determines if the ray at the given distance is within the radius of a cap.
+  fn check_cap(ray: Ray, distance: f64) -> bool {
+    let x = ray.origin.x + distance * ray.direction.x;
+    let z = ray.origin.z + distance * ray.direction.z;
+
+    (x * x + z * z) <= 1.
+  }
+
+  /// Intersects the ray with the end caps of the cylinder, if it's closed.
+  fn intersect_caps(&self, ray: Ray, results: &mut Vec<f64>) {
+    if !self.closed || ray.direction.y.abs() < EPSILON {
+      return;
+    }
+
+    let lower = (self.minimum - ray.origin.y) / ray.direction.y;
+    if Self::check_cap(ray, lower) {
+      results.push(lower);
+    }
+
+    let upper = (self.maximum - ray.origin.y) / ray.direction.y;
+    if Self::check_cap(ray, upper) {
+      results.push(upper);
+    }
+  }
+}
+
+impl Shape for Cylinder {
+  fn intersect(&self, object_ray: Ray) -> Vec<f64> {
+    let mut results = Vec::new();
+
+    let a = object_ray.direction.x * object_ray.direction.x + object_ray.direction.z * object_ray.direction.z;
+
+    // rays parallel to the y axis can only hit the caps
+    if a.abs() >= EPSILON {
+      let b = 2. * object_ray.origin.x * object_ray.direction.x + 2. * object_ray.origin.z * object_ray.direction.z;
+      let c = object_ray.origin.x * object_ray.origin.x + object_ray.origin.z * object_ray.origin.z - 1.;
+
+      let discriminant = b * b - 4. * a * c;
+      if discriminant < 0. {
+        return results;
+      }
+
+      let mut t0 = (-b - discriminant.sqrt()) / (2. * a);
+      let mut t1 = (-b + discriminant.sqrt()) / (2. * a);
+
+      if t0 > t1 {
+        std::mem::swap(&mut t0, &mut t1);
+      }
+
+      for t in [t0, t1] {
+        let y = object_ray.origin.y + t * object_ray.direction.y;
+
+        if self.minimum < y && y < self.maximum {
+          results.push(t);
+        }
+      }
+    }
+
+    self.intersect_caps(object_ray, &mut results);
+
+    results
+  }
+
+  fn uv_at(&self, object_point: Point) -> Option<(f64, f64)> {
+    let distance = object_point.x * object_point.x + object_point.z * object_point.z;
+    let on_cap = distance < 1. && (object_point.y >= self.maximum - EPSILON || object_point.y <= self.minimum + EPSILON);
+
+    if on_cap {
+      // caps are mapped flat, with the unit disc filling the texture
+      return Some(((object_point.x + 1.) / 2., (object_point.z + 1.) / 2.));
+    }
+
+    let (u, _) = UvMapping::Cylindrical.map(object_point);
+    let v = if self.minimum.is_finite() && self.maximum.is_finite() {
+      (object_point.y - self.minimum) / (self.maximum - self.minimum)
+    } else {
+      object_point.y.rem_euclid(1.)
+    };
+
+    Some((u, v))
+  }
+
+  fn bounds(&self) -> BoundingBox {
+    BoundingBox::new(point(-1., self.minimum, -1.), point(1., self.maximum, 1.))
+  }
+
+  fn normal_at(&self, object_point: Point, normal_transform: Matrix4x4) -> Vector {
+    let distance = object_point.x * object_point.x + object_point.z * object_point.z;
+
+    let object_normal = if distance < 1. && object_point.y >= self.maximum - EPSILON {
+      vec3(0., 1., 0.)
+    } else if distance < 1. && object_point.y <= self.minimum + EPSILON {
+      vec3(0., -1., 0.)
+    } else {
+      vec3(object_point.x, 0., object_point.z)
+    };
+
+    transform_normal(normal_transform, object_normal)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::maths::point;
+  use crate::scene::Traceable;
+
+  use super::*;
+
+  #[test]
+  fn ray_should_miss_cylinder() {
+    let cylinder = Cylinder::new(f64::NEG_INFINITY, f64::INFINITY, false);
+    let cases = [
+      (point(1., 0., 0.), vec3(0., 1., 0.)),
+      (point(0., 0., 0.), vec3(0., 1., 0.)),
+      (point(0., 0., -5.), vec3(1., 1., 1.)),
+    ];
+
+    for (origin, direction) in cases {
+      let set = cylinder.intersect(Ray::new(origin, direction.normalize()));
+
+      assert_eq!(set.len(), 0);
+    }
+  }
+
+  #[test]
+  fn ray_should_strike_cylinder() {
+    let cylinder = Cylinder::new(f64::NEG_INFINITY, f64::INFINITY, false);
+    let cases = [
+      (point(1., 0., -5.), vec3(0., 0., 1.), 5., 5.),
+      (point(0., 0., -5.), vec3(0., 0., 1.), 4., 6.),
+      (point(0.5, 0., -5.), vec3(0.1, 1., 1.), 6.80798, 7.08872),
+    ];
+
+    for (origin, direction, t0, t1) in cases {
+      let set = cylinder.intersect(Ray::new(origin, direction.normalize()));
+
+      assert_eq!(set.len(), 2);
+      assert!((set[0].distance - t0).abs() < 0.0001);
+      assert!((set[1].distance - t1).abs() < 0.0001);
+    }
+  }
+
+  #[test]
+  fn intersecting_a_truncated_cylinder() {
+    let cylinder = Cylinder::new(1., 2., false);
+    let cases = [
+      (point(0., 1.5, 0.), vec3(0.1, 1., 0.), 0),
+      (point(0., 3., -5.), vec3(0., 0., 1.), 0),
+      (point(0., 0., -5.), vec3(0., 0., 1.), 0),
+      (point(0., 2., -5.), vec3(0., 0., 1.), 0),
+      (point(0., 1., -5.), vec3(0., 0., 1.), 0),
+      (point(0., 1.5, -2.), vec3(0., 0., 1.), 2),
+    ];
+
+    for (origin, direction, count) in cases {
+      let set = cylinder.intersect(Ray::new(origin, direction.normalize()));
+
+      assert_eq!(set.len(), count);
+    }
+  }
+
+  #[test]
+  fn intersecting_the_caps_of_a_closed_cylinder() {
+    let cylinder = Cylinder::new(1., 2., true);
+    let cases = [
+      (point(0., 3., 0.), vec3(0., -1., 0.), 2),
+      (point(0., 3., -2.), vec3(0., -1., 2.), 2),
+      (point(0., 4., -2.), vec3(0., -1., 1.), 2),
+      (point(0., 0., -2.), vec3(0., 1., 2.), 2),
+      (point(0., -1., -2.), vec3(0., 1., 1.), 2),
+    ];
+
+    for (origin, direction, count) in cases {
+      let set = cylinder.intersect(Ray::new(origin, direction.normalize()));
+
+      assert_eq!(set.len(), count);
+    }
+  }
+
+  #[test]
+  fn normal_on_surface_of_cylinder() {
+    let cylinder = Cylinder::new(f64::NEG_INFINITY, f64::INFINITY, false);
+    let cases = [
+      (point(1., 0., 0.), vec3(1., 0., 0.)),
+      (point(0., 5., -1.), vec3(0., 0., -1.)),
+      (point(0., -2., 1.), vec3(0., 0., 1.)),
+      (point(-1., 1., 0.), vec3(-1., 0., 0.)),
+    ];
+
+    for (point, normal) in cases {
+      assert_eq!(cylinder.normal_at(point), normal);
+    }
+  }
+
+  #[test]
+  fn normal_on_cylinder_end_caps() {
+    let cylinder = Cylinder::new(1., 2., true);
+    let cases = [
+      (point(0., 1., 0.), vec3(0., -1., 0.)),
+      (point(0.5, 1., 0.), vec3(0., -1., 0.)),
+      (point(0., 2., 0.), vec3(0., 1., 0.)),
+      (point(0., 2., 0.5), vec3(0., 1., 0.)),
+    ];
+
+    for (point, normal) in cases {
+      assert_eq!(cylinder.normal_at(point), normal);
+    }
+  }
+
+  #[test]
+  fn uv_on_cylinder_body_wraps_around_axis() {
+    let cylinder = Cylinder { minimum: 1., maximum: 2., closed: true };
+    let cases = [
+      (point(0., 1.5, -1.), (0., 0.5)),
+      (point(1., 1.5, 0.), (0.25, 0.5)),
+      (point(0., 1.5, 1.), (0.5, 0.5)),
+      (point(-1., 1.5, 0.), (0.75, 0.5)),
+      (point(1., 1., 0.), (0.25, 0.)),
+      (point(1., 2., 0.), (0.25, 1.)),
+    ];
+
+    for (point, (u, v)) in cases {
+      let uv = cylinder.uv_at(point).unwrap();
+
+      assert!((uv.0 - u).abs() < EPSILON, "u at {:?}", point);
+      assert!((uv.1 - v).abs() < EPSILON, "v at {:?}", point);
+    }
+  }
+
+  #[test]
+  fn uv_on_cylinder_caps_is_planar() {
+    let cylinder = Cylinder { minimum: 1., maximum: 2., closed: true };
+    let cases = [
+      (point(0., 2., 0.), (0.5, 0.5)),
+      (point(0.5, 2., 0.), (0.75, 0.5)),
+      (point(0., 1., -0.5), (0.5, 0.25)),
+    ];
+
+    for (point, (u, v)) in cases {
+      let uv = cylinder.uv_at(point).unwrap();
+
+      assert!((uv.0 - u).abs() < EPSILON, "u at {:?}", point);
+      assert!((uv.1 - v).abs() < EPSILON, "v at {:?}", point);
+    }
+  }
+
+  #[test]
+  fn hits_on_cylinder_should_carry_uv() {
+    let cylinder = Cylinder::new(1., 2., true);
+    let set = cylinder.intersect(Ray::new(point(0., 1.5, -5.), vec3(0., 0., 1.)));
+
+    assert_eq!(set.len(), 2);
+    assert_eq!(set[0].uv, Some((0., 0.5)));
+    assert_eq!(set[1].uv, Some((0.5, 0.5)));
+  }
+}
@@ -1,6 +1,6 @@
 //! Plane objects for use in scene rendering.
 
-use crate::maths::{Matrix4x4, Ray, Vector};
+use crate::maths::{BoundingBox, intersect_plane, Matrix4x4, point, Point, Ray, transform_normal, vec3, Vector};
 use crate::scene::SceneNode;
 
 use super::Shape;
@@ -20,21 +20,24 @@ impl Plane {
 
 impl Shape for Plane {
   fn intersect(&self, object_ray: Ray) -> Vec<f64> {
-    if object_ray.direction.y.abs() < 0.00001 {
-      vec![]
-    } else {
-      vec![-object_ray.origin.y / object_ray.direction.y]
-    }
+    intersect_plane(object_ray, Point::ORIGIN, vec3(0., 1., 0.)).into_iter().collect()
   }
 
-  fn normal_at(&self, _object_point: Vector, _inverse_transform: Matrix4x4) -> Vector {
-    self.normal
+  fn bounds(&self) -> BoundingBox {
+    BoundingBox::new(
+      point(f64::NEG_INFINITY, 0., f64::NEG_INFINITY),
+      point(f64::INFINITY, 0., f64::INFINITY),
+    )
+  }
+
+  fn normal_at(&self, _object_point: Point, normal_transform: Matrix4x4) -> Vector {
+    transform_normal(normal_transform, self.normal)
   }
 }
 
 #[cfg(test)]
 mod tests {
-  use crate::maths::{point, vec3};
+  use crate::maths::{EPSILON, PI, point, vec3};
   use crate::scene::Traceable;
 
   use super::*;
@@ -52,6 +55,14 @@ mod tests {
     assert_eq!(n3, vec3(0., 1., 0.));
   }
 
+  #[test]
+  fn the_normal_of_a_plane_follows_its_transform() {
+    let plane = Plane::new(vec3(0., 1., 0.))
+      .with_transform(Matrix4x4::translate(0., 0., 5.) * Matrix4x4::rotate_x(PI / 2.));
+
+    assert_eq!(plane.normal_at(point(1., 2., 5.)), vec3(0., 0., 1.));
+  }
+
   #[test]
   fn intersect_with_a_ray_parallel_to_the_plane() {
     let plane = Plane::new(vec3(0., 1., 0.));
@@ -93,4 +104,18 @@ mod tests {
     assert_eq!(set.len(), 1);
     assert_eq!(set[0].distance, 1.);
   }
-}
\ No newline at end of file
+
+  #[test]
+  fn intersect_with_nearly_parallel_rays_at_the_epsilon_threshold() {
+    let plane = Plane::new(vec3(0., 1., 0.));
+
+    let above = Ray::new(point(0., 1., 0.), vec3(0., -EPSILON * 1.01, 1.));
+    let below = Ray::new(point(0., 1., 0.), vec3(0., -EPSILON * 0.99, 1.));
+
+    let set = plane.intersect(above);
+
+    assert_eq!(set.len(), 1);
+    assert!((set[0].distance - 1. / (EPSILON * 1.01)).abs() < 1e-3);
+    assert_eq!(plane.intersect(below).len(), 0);
+  }
+}
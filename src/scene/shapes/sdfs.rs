@@ -0,0 +1,177 @@
+//! Implicit surfaces described by signed distance fields.
+
+use crate::maths::{BoundingBox, Matrix4x4, Point, Ray, transform_normal, vec3, Vector};
+use crate::scene::{SceneNode, Shape};
+
+/// A signed distance function; negative inside the surface, positive outside.
+pub type DistanceFn = Box<dyn Fn(Point) -> f64 + Send + Sync>;
+
+/// A surface defined implicitly by a signed distance field, rendered by sphere tracing.
+///
+/// The distance function must never overestimate the distance to the surface, or the
+/// marcher may step straight through it.
+pub struct SdfShape {
+  distance: DistanceFn,
+  bounds: BoundingBox,
+}
+
+impl SdfShape {
+  /// The distance below which a sample is considered to be on the surface.
+  const HIT_EPSILON: f64 = 0.00001;
+
+  /// The step taken to move off the surface after a hit, to look for the next one.
+  const MIN_STEP: f64 = 0.0001;
+
+  /// The most steps taken along any one ray.
+  const MAX_STEPS: usize = 256;
+
+  /// The furthest distance marched along rays that don't pass through finite bounds.
+  const MAX_DISTANCE: f64 = 1000.;
+
+  /// Constructs a new SDF node with the given distance function, and no bounds.
+  ///
+  /// Without bounds, rays are marched forwards from their origin only.
+  pub fn new(distance: impl Fn(Point) -> f64 + Send + Sync + 'static) -> SceneNode<Self> {
+    Self::bounded(distance, BoundingBox::INFINITE)
+  }
+
+  /// Constructs a new SDF node with the given distance function, enclosed by the given bounds.
+  ///
+  /// Rays are only marched through the bounds, which makes misses much cheaper.
+  pub fn bounded(distance: impl Fn(Point) -> f64 + Send + Sync + 'static, bounds: BoundingBox) -> SceneNode<Self> {
+    SceneNode::new(Self { distance: Box::new(distance), bounds })
+  }
+
+  /// Evaluates the distance function at the given object point.
+  pub fn distance_at(&self, object_point: Point) -> f64 {
+    (self.distance)(object_point)
+  }
+}
+
+impl Shape for SdfShape {
+  fn intersect(&self, object_ray: Ray) -> Vec<f64> {
+    let mut results = Vec::new();
+
+    let Some((entry, exit)) = self.bounds.intersect(object_ray) else {
+      return results;
+    };
+
+    // the object ray may be scaled, so distances are converted into steps along it
+    let speed = object_ray.direction.magnitude();
+    let start = if entry.is_finite() { entry } else { 0. };
+    let end = if exit.is_finite() { exit } else { Self::MAX_DISTANCE / speed };
+
+    let mut t = start;
+    let mut on_surface = false;
+
+    for _ in 0..Self::MAX_STEPS {
+      if t > end {
+        break;
+      }
+
+      // march by the unsigned distance, so that exits are found from the inside as well
+      let distance = self.distance_at(object_ray.position(t)).abs();
+
+      if distance < Self::HIT_EPSILON {
+        if !on_surface {
+          results.push(t);
+          on_surface = true;
+        }
+
+        t += Self::MIN_STEP / speed;
+      } else {
+        on_surface = false;
+        t += distance / speed;
+      }
+    }
+
+    results
+  }
+
+  fn bounds(&self) -> BoundingBox {
+    self.bounds
+  }
+
+  fn normal_at(&self, object_point: Point, normal_transform: Matrix4x4) -> Vector {
+    const DELTA: f64 = 0.0001;
+
+    // estimate the gradient of the field by central differences
+    let gradient = |offset: Vector| self.distance_at(object_point + offset) - self.distance_at(object_point - offset);
+
+    let object_normal = vec3(
+      gradient(vec3(DELTA, 0., 0.)),
+      gradient(vec3(0., DELTA, 0.)),
+      gradient(vec3(0., 0., DELTA)),
+    );
+
+    transform_normal(normal_transform, object_normal)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::maths::{point, vec3};
+  use crate::scene::{Sphere, Traceable};
+
+  use super::*;
+
+  fn unit_sphere(point: Point) -> f64 {
+    (point - Point::ORIGIN).magnitude() - 1.
+  }
+
+  #[test]
+  fn sdf_sphere_intersections_match_analytic_sphere() {
+    let sdf = SdfShape::new(unit_sphere);
+    let sphere = Sphere::new();
+
+    let rays = [
+      Ray::new(point(0., 0., -5.), vec3(0., 0., 1.)),
+      Ray::new(point(0., 0.5, -5.), vec3(0., 0., 1.)),
+      Ray::new(point(-3., -2., -4.), vec3(3., 2., 4.).normalize()),
+    ];
+
+    for ray in rays {
+      let marched = sdf.intersect(ray);
+      let analytic = sphere.intersect(ray);
+
+      assert_eq!(marched.len(), 2);
+
+      for (a, b) in marched.iter().zip(analytic.iter()) {
+        assert!((a.distance - b.distance).abs() < 0.001, "{} vs {}", a.distance, b.distance);
+      }
+    }
+  }
+
+  #[test]
+  fn sdf_sphere_can_be_missed() {
+    let sdf = SdfShape::bounded(unit_sphere, BoundingBox::new(point(-1., -1., -1.), point(1., 1., 1.)));
+
+    assert_eq!(sdf.intersect(Ray::new(point(0., 2., -5.), vec3(0., 0., 1.))).len(), 0);
+    assert_eq!(sdf.intersect(Ray::new(point(1.5, 0., -5.), vec3(0., 0., 1.))).len(), 0);
+    assert_eq!(sdf.intersect(Ray::new(point(0.9, 0.9, -5.), vec3(0., 0., 1.))).len(), 0);
+  }
+
+  #[test]
+  fn sdf_normals_follow_field_gradient() {
+    let sdf = SdfShape::new(unit_sphere).with_transform(Matrix4x4::translate(0., 1., 0.));
+
+    let normal = sdf.normal_at(point(0., 1., -1.));
+
+    assert!((normal - vec3(0., 0., -1.)).magnitude() < 0.0001);
+  }
+
+  #[test]
+  fn sdf_transformed_intersections_match_analytic_sphere() {
+    let sdf = SdfShape::new(unit_sphere).with_transform(Matrix4x4::scale(2., 2., 2.));
+    let sphere = Sphere::new().with_transform(Matrix4x4::scale(2., 2., 2.));
+
+    let ray = Ray::new(point(0., 1., -5.), vec3(0., 0., 1.));
+
+    let marched = sdf.intersect(ray);
+    let analytic = sphere.intersect(ray);
+
+    assert_eq!(marched.len(), 2);
+    assert!((marched[0].distance - analytic[0].distance).abs() < 0.001);
+    assert!((marched[1].distance - analytic[1].distance).abs() < 0.001);
+  }
+}
@@ -1,6 +1,7 @@
 //! Sphere objects for use in scene rendering.
 
-use crate::maths::{Matrix4x4, point, Ray, Vector};
+use crate::graphics::UvMapping;
+use crate::maths::{BoundingBox, intersect_sphere, Matrix4x4, point, Point, Ray, transform_normal, Vector};
 use crate::scene::{SceneNode, Shape};
 
 /// A sphere in 3-space.
@@ -15,32 +16,21 @@ impl Sphere {
 }
 
 impl Shape for Sphere {
-  fn intersect(&self, world_ray: Ray) -> Vec<f64> {
-    // standard ray sphere intersection
-    let sphere_to_ray = world_ray.origin - point(0., 0., 0.);
-
-    let a = world_ray.direction.dot(world_ray.direction);
-    let b = 2. * sphere_to_ray.dot(world_ray.direction);
-    let c = sphere_to_ray.dot(sphere_to_ray) - 1.;
-
-    let discriminant = b * b - 4. * a * c;
-    let mut results = Vec::new();
-
-    if discriminant >= 0. {
-      results.push((-b - discriminant.sqrt()) / (2. * a));
-      results.push((-b + discriminant.sqrt()) / (2. * a));
-    }
-
-    results
+  fn intersect(&self, object_ray: Ray) -> Vec<f64> {
+    intersect_sphere(object_ray, Point::ORIGIN, 1.)
   }
 
-  fn normal_at(&self, object_point: Vector, inverse_transform: Matrix4x4) -> Vector {
-    let object_normal = object_point - point(0., 0., 0.);
-    let mut world_normal = inverse_transform.transpose() * object_normal;
+  fn bounds(&self) -> BoundingBox {
+    BoundingBox::new(point(-1., -1., -1.), point(1., 1., 1.))
+  }
 
-    world_normal.w = 0.;
+  fn normal_at(&self, object_point: Point, normal_transform: Matrix4x4) -> Vector {
+    let object_normal = object_point - Point::ORIGIN;
+    transform_normal(normal_transform, object_normal)
+  }
 
-    world_normal.normalize()
+  fn uv_at(&self, object_point: Point) -> Option<(f64, f64)> {
+    Some(UvMapping::Spherical.map(object_point))
   }
 }
 
@@ -128,6 +118,28 @@ mod tests {
     assert_eq!(set[1].distance, 7.);
   }
 
+  #[test]
+  fn scaled_sphere_caches_inverse_transform() {
+    let transform = Matrix4x4::scale(2., 2., 2.);
+    let sphere = Sphere::new().with_transform(transform);
+
+    assert_eq!(sphere.transform(), transform);
+    assert_eq!(sphere.inverse_transform(), Matrix4x4::scale(0.5, 0.5, 0.5));
+  }
+
+  #[test]
+  fn transformed_sphere_caches_normal_transform() {
+    let transform = Matrix4x4::scale(1., 0.5, 1.) * Matrix4x4::rotate_z(PI / 5.);
+    let mut sphere = Sphere::new().with_transform(transform);
+
+    assert_eq!(sphere.normal_transform(), transform.invert().unwrap().transpose());
+
+    // the cache follows the transform when the sphere is placed in a parent space
+    sphere.apply_parent_transform(Matrix4x4::translate(0., 1., 0.));
+
+    assert_eq!(sphere.normal_transform(), sphere.inverse_transform().transpose());
+  }
+
   #[test]
   fn translated_sphere_intersection_with_ray() {
     let ray = Ray::new(point(0., 0., -5.), vec3(0., 0., 1.));
@@ -184,6 +196,7 @@ mod tests {
   }
 
   #[test]
+  #[allow(clippy::approx_constant)]
   fn normal_on_translated_sphere() {
     let sphere = Sphere::new().with_transform(Matrix4x4::translate(0., 1., 0.));
 
@@ -192,6 +205,15 @@ mod tests {
     assert_eq!(normal, vec3(0., 0.70711, -0.70711));
   }
 
+  #[test]
+  fn normal_on_anisotropically_scaled_sphere() {
+    let sphere = Sphere::new().with_transform(Matrix4x4::scale(1., 0.5, 1.));
+
+    let normal = sphere.normal_at(point(2f64.sqrt() / 2., 2f64.sqrt() / 4., 0.));
+
+    assert_eq!(normal, vec3(0.44721, 0.89443, 0.));
+  }
+
   #[test]
   fn normal_on_transformed_sphere() {
     let sphere = Sphere::new()
@@ -201,4 +223,11 @@ mod tests {
 
     assert_eq!(normal, vec3(0., 0.97014, -0.24254));
   }
+
+  #[test]
+  fn zero_length_ray_should_miss_sphere() {
+    let ray = Ray::new(point(0., 0., 0.), vec3(0., 0., 0.));
+
+    assert!(Shape::intersect(&Sphere, ray).is_empty());
+  }
 }
@@ -0,0 +1,256 @@
+//! Triangle objects for use in scene rendering.
+
+use crate::maths::{BoundingBox, EPSILON, Matrix4x4, Point, Ray, transform_normal, Vector};
+use crate::scene::{SceneNode, Shape};
+
+/// A flat triangle in 3-space between three points.
+#[derive(Clone, Debug)]
+pub struct Triangle {
+  pub p1: Point,
+  pub p2: Point,
+  pub p3: Point,
+  e1: Vector,
+  e2: Vector,
+  normal: Vector,
+}
+
+impl Triangle {
+  /// Constructs a new triangle node
+  pub fn new(p1: Point, p2: Point, p3: Point) -> SceneNode<Self> {
+    // pre-compute the edges and normal
+    let e1 = p2 - p1;
+    let e2 = p3 - p1;
+    let normal = e2.cross(e1).normalize();
+
+    SceneNode::new(Self { p1, p2, p3, e1, e2, normal })
+  }
+}
+
+impl Shape for Triangle {
+  fn intersect(&self, object_ray: Ray) -> Vec<f64> {
+    intersect_triangle(self.p1, self.e1, self.e2, object_ray)
+  }
+
+  fn bounds(&self) -> BoundingBox {
+    BoundingBox::from_points([self.p1, self.p2, self.p3])
+  }
+
+  fn is_back_facing(&self, object_ray: Ray) -> bool {
+    object_ray.direction.dot(self.normal) > 0.
+  }
+
+  fn normal_at(&self, _object_point: Point, normal_transform: Matrix4x4) -> Vector {
+    transform_normal(normal_transform, self.normal)
+  }
+}
+
+/// A triangle with a normal at each vertex, interpolated across its face.
+#[derive(Clone, Debug)]
+pub struct SmoothTriangle {
+  pub p1: Point,
+  pub p2: Point,
+  pub p3: Point,
+  pub n1: Vector,
+  pub n2: Vector,
+  pub n3: Vector,
+  e1: Vector,
+  e2: Vector,
+}
+
+impl SmoothTriangle {
+  /// Constructs a new smooth triangle node
+  pub fn new(p1: Point, p2: Point, p3: Point, n1: Vector, n2: Vector, n3: Vector) -> SceneNode<Self> {
+    let e1 = p2 - p1;
+    let e2 = p3 - p1;
+
+    SceneNode::new(Self { p1, p2, p3, n1, n2, n3, e1, e2 })
+  }
+
+  /// Computes the barycentric (u, v) coordinates of the given point, relative to p2 and p3.
+  fn barycentric(&self, point: Point) -> (f64, f64) {
+    let p1_to_point = point - self.p1;
+
+    let d00 = self.e1.dot(self.e1);
+    let d01 = self.e1.dot(self.e2);
+    let d11 = self.e2.dot(self.e2);
+    let d20 = p1_to_point.dot(self.e1);
+    let d21 = p1_to_point.dot(self.e2);
+
+    let denominator = d00 * d11 - d01 * d01;
+
+    ((d11 * d20 - d01 * d21) / denominator, (d00 * d21 - d01 * d20) / denominator)
+  }
+}
+
+impl Shape for SmoothTriangle {
+  fn intersect(&self, object_ray: Ray) -> Vec<f64> {
+    intersect_triangle(self.p1, self.e1, self.e2, object_ray)
+  }
+
+  fn bounds(&self) -> BoundingBox {
+    BoundingBox::from_points([self.p1, self.p2, self.p3])
+  }
+
+  fn is_back_facing(&self, object_ray: Ray) -> bool {
+    // cull by the face itself, not the interpolated vertex normals
+    object_ray.direction.dot(self.e2.cross(self.e1)) > 0.
+  }
+
+  fn normal_at(&self, object_point: Point, normal_transform: Matrix4x4) -> Vector {
+    let (u, v) = self.barycentric(object_point);
+    let object_normal = self.n2 * u + self.n3 * v + self.n1 * (1. - u - v);
+
+    transform_normal(normal_transform, object_normal)
+  }
+}
+
+/// Intersects a ray with the triangle at p1 spanned by the given edges.
+fn intersect_triangle(p1: Point, e1: Vector, e2: Vector, object_ray: Ray) -> Vec<f64> {
+  // standard Möller–Trumbore intersection
+  let direction_cross_e2 = object_ray.direction.cross(e2);
+  let determinant = e1.dot(direction_cross_e2);
+
+  if determinant.abs() < EPSILON {
+    return vec![];
+  }
+
+  let f = 1. / determinant;
+  let p1_to_origin = object_ray.origin - p1;
+
+  let u = f * p1_to_origin.dot(direction_cross_e2);
+  if !(0. ..=1.).contains(&u) {
+    return vec![];
+  }
+
+  let origin_cross_e1 = p1_to_origin.cross(e1);
+
+  let v = f * object_ray.direction.dot(origin_cross_e1);
+  if v < 0. || u + v > 1. {
+    return vec![];
+  }
+
+  vec![f * e2.dot(origin_cross_e1)]
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::maths::{point, vec3};
+  use crate::scene::{Material, Traceable};
+
+  use super::*;
+
+  #[test]
+  fn triangle_should_precompute_edges_and_normal() {
+    let triangle = Triangle::new(point(0., 1., 0.), point(-1., 0., 0.), point(1., 0., 0.));
+
+    assert_eq!(triangle.object.e1, vec3(-1., -1., 0.));
+    assert_eq!(triangle.object.e2, vec3(1., -1., 0.));
+    assert_eq!(triangle.object.normal, vec3(0., 0., -1.));
+  }
+
+  #[test]
+  fn normal_on_triangle_is_constant() {
+    let triangle = Triangle::new(point(0., 1., 0.), point(-1., 0., 0.), point(1., 0., 0.));
+
+    assert_eq!(triangle.normal_at(point(0., 0.5, 0.)), vec3(0., 0., -1.));
+    assert_eq!(triangle.normal_at(point(-0.5, 0.75, 0.)), vec3(0., 0., -1.));
+    assert_eq!(triangle.normal_at(point(0.5, 0.25, 0.)), vec3(0., 0., -1.));
+  }
+
+  #[test]
+  fn back_facing_triangle_is_skipped_when_culling() {
+    let front = Ray::new(point(0., 0.5, -2.), vec3(0., 0., 1.));
+    let back = Ray::new(point(0., 0.5, 2.), vec3(0., 0., -1.));
+
+    let culled = Triangle::new(point(0., 1., 0.), point(-1., 0., 0.), point(1., 0., 0.))
+      .with_material(Material::default().with_cull_backfaces(true));
+    let kept = Triangle::new(point(0., 1., 0.), point(-1., 0., 0.), point(1., 0., 0.));
+
+    assert_eq!(culled.intersect(front).len(), 1);
+    assert_eq!(culled.intersect(back).len(), 0);
+    assert_eq!(kept.intersect(back).len(), 1);
+  }
+
+  #[test]
+  fn transparent_triangles_keep_both_faces_when_culling() {
+    let back = Ray::new(point(0., 0.5, 2.), vec3(0., 0., -1.));
+
+    let triangle = Triangle::new(point(0., 1., 0.), point(-1., 0., 0.), point(1., 0., 0.))
+      .with_material(Material::default().with_cull_backfaces(true).with_transparency(0.5));
+
+    assert_eq!(triangle.intersect(back).len(), 1);
+  }
+
+  #[test]
+  fn smooth_triangles_cull_by_their_face() {
+    let triangle = SmoothTriangle::new(
+      point(0., 1., 0.), point(-1., 0., 0.), point(1., 0., 0.),
+      vec3(0., 1., 0.), vec3(-1., 0., 0.), vec3(1., 0., 0.),
+    ).with_material(Material::default().with_cull_backfaces(true));
+
+    assert_eq!(triangle.intersect(Ray::new(point(0., 0.5, -2.), vec3(0., 0., 1.))).len(), 1);
+    assert_eq!(triangle.intersect(Ray::new(point(0., 0.5, 2.), vec3(0., 0., -1.))).len(), 0);
+  }
+
+  #[test]
+  fn ray_parallel_to_triangle_should_miss() {
+    let triangle = Triangle::new(point(0., 1., 0.), point(-1., 0., 0.), point(1., 0., 0.));
+    let ray = Ray::new(point(0., -1., -2.), vec3(0., 1., 0.));
+
+    assert_eq!(triangle.intersect(ray).len(), 0);
+  }
+
+  #[test]
+  fn ray_should_miss_triangle_edges() {
+    let triangle = Triangle::new(point(0., 1., 0.), point(-1., 0., 0.), point(1., 0., 0.));
+    let origins = [point(1., 1., -2.), point(-1., 1., -2.), point(0., -1., -2.)];
+
+    for origin in origins {
+      let ray = Ray::new(origin, vec3(0., 0., 1.));
+
+      assert_eq!(triangle.intersect(ray).len(), 0);
+    }
+  }
+
+  #[test]
+  fn ray_should_strike_triangle() {
+    let triangle = Triangle::new(point(0., 1., 0.), point(-1., 0., 0.), point(1., 0., 0.));
+    let ray = Ray::new(point(0., 0.5, -2.), vec3(0., 0., 1.));
+
+    let set = triangle.intersect(ray);
+
+    assert_eq!(set.len(), 1);
+    assert_eq!(set[0].distance, 2.);
+  }
+
+  fn create_smooth_triangle() -> SceneNode<SmoothTriangle> {
+    SmoothTriangle::new(
+      point(0., 1., 0.),
+      point(-1., 0., 0.),
+      point(1., 0., 0.),
+      vec3(0., 1., 0.),
+      vec3(-1., 0., 0.),
+      vec3(1., 0., 0.),
+    )
+  }
+
+  #[test]
+  fn ray_should_strike_smooth_triangle() {
+    let triangle = create_smooth_triangle();
+    let ray = Ray::new(point(-0.2, 0.3, -2.), vec3(0., 0., 1.));
+
+    let set = triangle.intersect(ray);
+
+    assert_eq!(set.len(), 1);
+    assert_eq!(set[0].distance, 2.);
+  }
+
+  #[test]
+  fn smooth_triangle_interpolates_normals() {
+    let triangle = create_smooth_triangle();
+
+    assert_eq!(triangle.normal_at(point(-0.2, 0.3, 0.)), vec3(-0.5547, 0.83205, 0.));
+    assert_eq!(triangle.normal_at(point(0., 1., 0.)), vec3(0., 1., 0.));
+    assert_eq!(triangle.normal_at(point(1., 0., 0.)), vec3(1., 0., 0.));
+  }
+}
@@ -0,0 +1,105 @@
+//! Instrumentation for measuring render performance.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Counts of the work performed while rendering a scene.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RenderStats {
+  /// Rays cast from the camera into the scene.
+  pub primary_rays: usize,
+  /// Rays cast from surfaces towards lights to test for occlusion.
+  pub shadow_rays: usize,
+  /// Secondary rays cast for reflection.
+  pub reflection_rays: usize,
+  /// Secondary rays cast for refraction.
+  pub refraction_rays: usize,
+  /// Ray tests against top-level objects in the scene.
+  pub intersection_tests: usize,
+}
+
+/// Thread-safe counters accumulating `RenderStats` during a render.
+#[derive(Debug, Default)]
+pub struct RenderCounters {
+  primary_rays: AtomicUsize,
+  shadow_rays: AtomicUsize,
+  reflection_rays: AtomicUsize,
+  refraction_rays: AtomicUsize,
+  intersection_tests: AtomicUsize,
+}
+
+impl RenderCounters {
+  /// Records a ray cast from the camera.
+  pub fn add_primary_ray(&self) {
+    self.primary_rays.fetch_add(1, Ordering::Relaxed);
+  }
+
+  /// Records a ray cast towards a light.
+  pub fn add_shadow_ray(&self) {
+    self.shadow_rays.fetch_add(1, Ordering::Relaxed);
+  }
+
+  /// Records a reflected ray.
+  pub fn add_reflection_ray(&self) {
+    self.reflection_rays.fetch_add(1, Ordering::Relaxed);
+  }
+
+  /// Records a refracted ray.
+  pub fn add_refraction_ray(&self) {
+    self.refraction_rays.fetch_add(1, Ordering::Relaxed);
+  }
+
+  /// Records the given number of ray-object tests.
+  pub fn add_intersection_tests(&self, count: usize) {
+    self.intersection_tests.fetch_add(count, Ordering::Relaxed);
+  }
+
+  /// Takes a snapshot of the current counts.
+  pub fn snapshot(&self) -> RenderStats {
+    RenderStats {
+      primary_rays: self.primary_rays.load(Ordering::Relaxed),
+      shadow_rays: self.shadow_rays.load(Ordering::Relaxed),
+      reflection_rays: self.reflection_rays.load(Ordering::Relaxed),
+      refraction_rays: self.refraction_rays.load(Ordering::Relaxed),
+      intersection_tests: self.intersection_tests.load(Ordering::Relaxed),
+    }
+  }
+
+  /// Resets all counts back to zero.
+  pub fn reset(&self) {
+    self.primary_rays.store(0, Ordering::Relaxed);
+    self.shadow_rays.store(0, Ordering::Relaxed);
+    self.reflection_rays.store(0, Ordering::Relaxed);
+    self.refraction_rays.store(0, Ordering::Relaxed);
+    self.intersection_tests.store(0, Ordering::Relaxed);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn render_counters_should_accumulate_and_reset() {
+    let counters = RenderCounters::default();
+
+    counters.add_primary_ray();
+    counters.add_primary_ray();
+    counters.add_shadow_ray();
+    counters.add_reflection_ray();
+    counters.add_refraction_ray();
+    counters.add_refraction_ray();
+    counters.add_intersection_tests(3);
+
+    assert_eq!(counters.snapshot(), RenderStats {
+      primary_rays: 2,
+      shadow_rays: 1,
+      reflection_rays: 1,
+      refraction_rays: 2,
+      intersection_tests: 3,
+    });
+
+    counters.reset();
+
+    assert_eq!(counters.snapshot(), RenderStats::default());
+  }
+}